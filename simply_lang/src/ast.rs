@@ -0,0 +1,136 @@
+use std::fmt;
+
+use aho_compilers::span::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Eq,
+    Ne,
+    Lt,
+    And,
+}
+
+impl fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnaryOp::Neg => write!(f, "-"),
+        }
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::And => "and",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Number { value: i64, span: Span },
+    Bool { value: bool, span: Span },
+    Variable { name: String, span: Span },
+    Unary { op: UnaryOp, operand: Box<Expr>, span: Span },
+    Binary { op: BinaryOp, left: Box<Expr>, right: Box<Expr>, span: Span },
+}
+
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Number { span, .. }
+            | Expr::Bool { span, .. }
+            | Expr::Variable { span, .. }
+            | Expr::Unary { span, .. }
+            | Expr::Binary { span, .. } => *span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stmt {
+    /// Присваивание создаёт переменную в текущей области, если её нет во внешних
+    Assign { name: String, value: Expr, span: Span },
+    If { condition: Expr, then_branch: Box<Stmt>, else_branch: Option<Box<Stmt>>, span: Span },
+    /// for (init; condition; step) body; init и step - присваивания
+    For { init: Box<Stmt>, condition: Expr, step: Box<Stmt>, body: Box<Stmt>, span: Span },
+    Block { statements: Vec<Stmt>, span: Span },
+    Print { value: Expr, span: Span },
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Assign { span, .. }
+            | Stmt::If { span, .. }
+            | Stmt::For { span, .. }
+            | Stmt::Block { span, .. }
+            | Stmt::Print { span, .. } => *span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Program {
+    pub statements: Vec<Stmt>,
+}
+
+// S-выражения: (+ 1 (* 2 x))
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Number { value, .. } => write!(f, "{}", value),
+            Expr::Bool { value, .. } => write!(f, "{}", value),
+            Expr::Variable { name, .. } => write!(f, "{}", name),
+            Expr::Unary { op, operand, .. } => write!(f, "({} {})", op, operand),
+            Expr::Binary { op, left, right, .. } => write!(f, "({} {} {})", op, left, right),
+        }
+    }
+}
+
+impl fmt::Display for Stmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stmt::Assign { name, value, .. } => write!(f, "(= {} {})", name, value),
+            Stmt::If { condition, then_branch, else_branch: Some(else_branch), .. } =>
+                write!(f, "(if {} {} {})", condition, then_branch, else_branch),
+            Stmt::If { condition, then_branch, else_branch: None, .. } => write!(f, "(if {} {})", condition, then_branch),
+            Stmt::For { init, condition, step, body, .. } => write!(f, "(for {} {} {} {})", init, condition, step, body),
+            Stmt::Block { statements, .. } => {
+                write!(f, "(block")?;
+                for statement in statements {
+                    write!(f, " {}", statement)?;
+                }
+                write!(f, ")")
+            }
+            Stmt::Print { value, .. } => write!(f, "(print {})", value),
+        }
+    }
+}
+
+/// По оператору на строку
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for statement in &self.statements {
+            writeln!(f, "{}", statement)?;
+        }
+        Ok(())
+    }
+}
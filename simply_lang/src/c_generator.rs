@@ -0,0 +1,125 @@
+use std::fmt::Write;
+
+use crate::ast::{BinaryOp, UnaryOp};
+use crate::check::Type;
+use crate::ir::{Code, Instr, Operand};
+
+//Перевод трёхадресного кода в C: каждая инструкция - один оператор C,
+//метки и goto переносятся как есть. Все значения хранятся в long long,
+//bool - как 0 и 1. Переменные программы получают префикс v_, чтобы не
+//столкнуться с временными t1, t2, ... и словами C.
+//Переполнение проверяется встроенными функциями gcc и clang: в C оно -
+//неопределённое поведение, а интерпретатор сообщает о нём.
+
+const OVERFLOW: &str = "{ fputs(\"Integer overflow\\n\", stderr); return 3; }";
+
+struct Names<'a>(&'a Code);
+
+impl<'a> Names<'a> {
+    fn name(&self, name: &str) -> String {
+        if self.0.temps.iter().any(|x| x == name) {
+            name.to_owned()
+        } else {
+            format!("v_{}", name)
+        }
+    }
+
+    fn operand(&self, value: &Operand) -> String {
+        match value {
+            Operand::Int(value) => format!("{}LL", value),
+            Operand::Bool(value) => (*value as i64).to_string(),
+            Operand::Name(x) => self.name(x),
+        }
+    }
+}
+
+/// Программа на C, печатающая то же, что интерпретатор
+pub fn generate(code: &Code) -> String {
+    let names = Names(code);
+    let (name, operand) = (|x: &str| names.name(x), |x: &Operand| names.operand(x));
+    let mut result = String::from("#include <limits.h>\n#include <stdio.h>\n\nint main(void) {\n");
+    for x in code.variables.iter().chain(&code.temps) {
+        writeln!(result, "    long long {} = 0;", name(x)).unwrap();
+    }
+    for instruction in &code.instructions {
+        let line = match instruction {
+            Instr::Copy { dest, src } => format!("{} = {};", name(dest), operand(src)),
+            Instr::Unary { dest, op: UnaryOp::Neg, arg } =>
+                format!("if (__builtin_sub_overflow(0LL, {}, &{})) {}", operand(arg), name(dest), OVERFLOW),
+            Instr::Binary { dest, op, left, right } => {
+                let (dest, left, right) = (name(dest), operand(left), operand(right));
+                let checked = |builtin: &str| format!("if (__builtin_{}_overflow({}, {}, &{})) {}", builtin, left, right, dest, OVERFLOW);
+                match op {
+                    BinaryOp::Add => checked("add"),
+                    BinaryOp::Sub => checked("sub"),
+                    BinaryOp::Mul => checked("mul"),
+                    BinaryOp::Div => format!(
+                        "if ({2} == 0) {{ fputs(\"Division by zero\\n\", stderr); return 3; }} \
+                         if ({1} == LLONG_MIN && {2} == -1LL) {3} {0} = {1} / {2};",
+                        dest, left, right, OVERFLOW),
+                    BinaryOp::Eq => format!("{} = {} == {};", dest, left, right),
+                    BinaryOp::Ne => format!("{} = {} != {};", dest, left, right),
+                    BinaryOp::Lt => format!("{} = {} < {};", dest, left, right),
+                    BinaryOp::And => format!("{} = {} && {};", dest, left, right),
+                }
+            }
+            Instr::Label(label) => {
+                writeln!(result, "L{}:;", label).unwrap();
+                continue;
+            }
+            Instr::Goto(label) => format!("goto L{};", label),
+            Instr::IfFalse { condition, target } => format!("if (!{}) goto L{};", operand(condition), target),
+            Instr::Print { value, value_type: Type::Int } => format!("printf(\"%lld\\n\", {});", operand(value)),
+            Instr::Print { value, value_type: Type::Bool } =>
+                format!("puts({} ? \"true\" : \"false\");", operand(value)),
+        };
+        writeln!(result, "    {}", line).unwrap();
+    }
+    result += "    return 0;\n}\n";
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::check;
+    use crate::ir;
+    use crate::parser::parse;
+
+    #[test]
+    fn generate_test() {
+        let program = parse("x = 7; if x / 2 == 3 then print true;").unwrap();
+        let code = ir::generate(&program, &check(&program).unwrap());
+        assert_eq!(generate(&code), "\
+#include <limits.h>
+#include <stdio.h>
+
+int main(void) {
+    long long v_x = 0;
+    long long t1 = 0;
+    long long t2 = 0;
+    v_x = 7LL;
+    if (2LL == 0) { fputs(\"Division by zero\\n\", stderr); return 3; } \
+if (v_x == LLONG_MIN && 2LL == -1LL) { fputs(\"Integer overflow\\n\", stderr); return 3; } t1 = v_x / 2LL;
+    t2 = t1 == 3LL;
+    if (!t2) goto L1;
+    puts(1 ? \"true\" : \"false\");
+L1:;
+    return 0;
+}
+");
+    }
+
+    #[test]
+    fn overflow_test() {
+        let program = parse("print 9223372036854775807 + 1; print -(2 * 3);").unwrap();
+        let code = ir::generate(&program, &check(&program).unwrap());
+        let lines: Vec<String> = generate(&code).lines().filter(|x| x.contains("overflow")).map(|x| x.trim().to_owned()).collect();
+        let overflow = "{ fputs(\"Integer overflow\\n\", stderr); return 3; }";
+        assert_eq!(lines, vec![
+            format!("if (__builtin_add_overflow(9223372036854775807LL, 1LL, &t1)) {}", overflow),
+            format!("if (__builtin_mul_overflow(2LL, 3LL, &t2)) {}", overflow),
+            format!("if (__builtin_sub_overflow(0LL, t2, &t3)) {}", overflow),
+        ]);
+    }
+}
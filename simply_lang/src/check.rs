@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use aho_compilers::diagnostics::Diagnostic;
+use aho_compilers::span::Span;
+
+use crate::ast::{BinaryOp, Expr, Program, Stmt, UnaryOp};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Type {
+    Int,
+    Bool,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Bool => write!(f, "bool"),
+        }
+    }
+}
+
+/// Тип каждого выражения по его участку текста
+pub type Types = BTreeMap<Span, Type>;
+
+//Семантический анализ повторяет правила интерпретатора статически:
+// - переменная объявляется первым присваиванием и дальше не меняет тип;
+// - блок и for открывают область видимости;
+// - после if без блока остаются только переменные, объявленные в обеих ветвях.
+struct Checker {
+    scopes: Vec<BTreeMap<String, Type>>,
+    types: Types,
+    errors: Vec<Diagnostic>,
+}
+
+/// Проверка типов и объявлений; при успехе - типы выражений для генерации кода
+pub fn check(program: &Program) -> Result<Types, Vec<Diagnostic>> {
+    check_with(program, BTreeMap::new()).map(|x| x.0)
+}
+
+/// Проверка с уже объявленными глобальными переменными, например из предыдущих строк REPL;
+/// вместе с типами возвращает глобальные переменные, объявленные после программы
+pub fn check_with(program: &Program, globals: BTreeMap<String, Type>)
+                  -> Result<(Types, BTreeMap<String, Type>), Vec<Diagnostic>> {
+    let mut checker = Checker { scopes: vec![globals], types: Types::new(), errors: Vec::new() };
+    program.statements.iter().for_each(|x| checker.statement(x));
+    if checker.errors.is_empty() {
+        let globals = checker.scopes.pop().unwrap();
+        Ok((checker.types, globals))
+    } else {
+        Err(checker.errors)
+    }
+}
+
+impl Checker {
+    fn lookup(&self, name: &str) -> Option<Type> {
+        self.scopes.iter().rev().find_map(|x| x.get(name)).copied()
+    }
+
+    fn error(&mut self, message: &str, span: Span, label: &str) {
+        self.errors.push(Diagnostic::error(message).with_label(span, label));
+    }
+
+    fn scoped<F: FnOnce(&mut Self)>(&mut self, f: F) {
+        self.scopes.push(BTreeMap::new());
+        f(self);
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, statement: &Stmt) {
+        match statement {
+            Stmt::Assign { name, value, .. } => {
+                let value_type = self.expr(value);
+                match (self.lookup(name), value_type) {
+                    (Some(expected), Some(found)) if expected != found => self.error(
+                        &format!("Mismatched types: '{}' is {}, assigned {}", name, expected, found),
+                        value.span(), &format!("expected {}", expected)),
+                    (Some(_), _) => {}
+                    (None, Some(found)) => {
+                        self.scopes.last_mut().unwrap().insert(name.to_owned(), found);
+                    }
+                    // тип неизвестен из-за ошибки в выражении
+                    (None, None) => {}
+                }
+            }
+            Stmt::If { condition, then_branch, else_branch, span } => {
+                self.condition(condition);
+                let before = self.scopes.last().unwrap().clone();
+                self.statement(then_branch);
+                let after_then = std::mem::replace(self.scopes.last_mut().unwrap(), before);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+                let after_else = std::mem::take(self.scopes.last_mut().unwrap());
+                for (name, else_type) in after_else {
+                    match after_then.get(&name) {
+                        Some(then_type) if *then_type != else_type => self.error(
+                            &format!("Mismatched types: '{}' is {} in one branch and {} in another", name, then_type, else_type),
+                            *span, ""),
+                        Some(_) => {
+                            self.scopes.last_mut().unwrap().insert(name, else_type);
+                        }
+                        None => {}
+                    }
+                }
+            }
+            Stmt::For { init, condition, step, body, .. } => self.scoped(|this| {
+                this.statement(init);
+                this.condition(condition);
+                this.statement(body);
+                this.statement(step);
+            }),
+            Stmt::Block { statements, .. } => self.scoped(|this| statements.iter().for_each(|x| this.statement(x))),
+            Stmt::Print { value, .. } => {
+                self.expr(value);
+            }
+        }
+    }
+
+    fn condition(&mut self, condition: &Expr) {
+        if let Some(Type::Int) = self.expr(condition) {
+            self.error("Expected bool condition, found int", condition.span(), "expected bool");
+        }
+    }
+
+    // None - тип не удалось вывести, ошибка уже записана
+    fn expr(&mut self, expr: &Expr) -> Option<Type> {
+        let result = match expr {
+            Expr::Number { .. } => Some(Type::Int),
+            Expr::Bool { .. } => Some(Type::Bool),
+            Expr::Variable { name, span } => {
+                let result = self.lookup(name);
+                if result.is_none() {
+                    self.error(&format!("Undefined variable '{}'", name), *span, "not defined here");
+                }
+                result
+            }
+            Expr::Unary { op: UnaryOp::Neg, operand, span } => match self.expr(operand)? {
+                Type::Int => Some(Type::Int),
+                found => {
+                    self.error(&format!("Cannot negate {}", found), *span, "");
+                    None
+                }
+            },
+            Expr::Binary { op, left, right, span } => {
+                let (left, right) = (self.expr(left)?, self.expr(right)?);
+                let result = match op {
+                    BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div if left == Type::Int && right == Type::Int =>
+                        Some(Type::Int),
+                    BinaryOp::Lt if left == Type::Int && right == Type::Int => Some(Type::Bool),
+                    BinaryOp::Eq | BinaryOp::Ne if left == right => Some(Type::Bool),
+                    BinaryOp::And if left == Type::Bool && right == Type::Bool => Some(Type::Bool),
+                    _ => None,
+                };
+                if result.is_none() {
+                    self.error(&format!("Cannot apply '{}' to {} and {}", op, left, right), *span, "");
+                }
+                result
+            }
+        };
+        if let Some(result) = result {
+            self.types.insert(expr.span(), result);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn errors(source: &str) -> Vec<String> {
+        match check(&parse(source).unwrap()) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.iter().map(|x| x.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn types_test() {
+        let source = "x = 1; b = x < 2 and true; print -x;";
+        let types = check(&parse(source).unwrap()).unwrap();
+        let types: Vec<(&str, Type)> = types.iter().map(|(span, t)| (span.text(source), *t)).collect();
+        assert!(types.contains(&("x < 2 and true", Type::Bool)));
+        assert!(types.contains(&("-x", Type::Int)));
+        assert!(types.contains(&("x", Type::Int)));
+    }
+
+    #[test]
+    fn errors_test() {
+        assert_eq!(errors("x = 1; x = true; print y + 1; if x then print 1 + true;"), vec![
+            "error: Mismatched types: 'x' is int, assigned bool at 1:12",
+            "error: Undefined variable 'y' at 1:24",
+            "error: Expected bool condition, found int at 1:34",
+            "error: Cannot apply '+' to int and bool at 1:47",
+        ]);
+        assert_eq!(errors("print -true; print 1 == false;"), vec![
+            "error: Cannot negate bool at 1:7",
+            "error: Cannot apply '==' to int and bool at 1:20",
+        ]);
+    }
+
+    #[test]
+    fn scopes_test() {
+        assert!(errors("x = 1; { x = 2; y = x; } { y = true; } print x;").is_empty());
+        assert_eq!(errors("{ y = 1; } print y;"), vec!["error: Undefined variable 'y' at 1:18"]);
+        assert_eq!(errors("for (i = 0; i < 3; i = i + 1) print i; print i;"), vec!["error: Undefined variable 'i' at 1:46"]);
+        assert!(errors("if true then a = 1; else a = 2; print a;").is_empty());
+        assert_eq!(errors("if true then a = 1; print a;"), vec!["error: Undefined variable 'a' at 1:27"]);
+        assert_eq!(errors("if true then a = 1; else a = false;"),
+                   vec!["error: Mismatched types: 'a' is int in one branch and bool in another at 1:1"]);
+    }
+}
@@ -0,0 +1,264 @@
+use std::io::{BufRead, Write};
+
+use aho_compilers::diagnostics::{render_all, render_all_json, Diagnostic};
+use aho_compilers::span::SourceMap;
+
+use crate::check::check;
+use crate::interpreter::Interpreter;
+use crate::lexer::Lexer;
+use crate::repl::Repl;
+use crate::{c_generator, ir, parser};
+
+pub const EXIT_OK: i32 = 0;
+/// Лексические, синтаксические или семантические ошибки в программе
+pub const EXIT_ERRORS: i32 = 1;
+/// Неверные аргументы или файл не читается
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_RUNTIME: i32 = 3;
+
+pub const USAGE: &str = "\
+Usage: simply_lang <command> [--json] <file.sl>
+       simply_lang <file.sl>
+       simply_lang repl
+
+Commands:
+  tokens   print tokens with their positions
+  parse    print the syntax tree
+  check    run semantic analysis only
+  ir       print three-address code
+  run      execute the program (default)
+  compile  translate to C; -o <file> writes it to a file
+  repl     interactive session; type :help inside
+
+Exit codes: 0 success, 1 errors in the program, 2 usage error, 3 runtime error.
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Tokens,
+    Parse,
+    Check,
+    Ir,
+    Run,
+    Compile,
+    Repl,
+}
+
+impl Command {
+    fn from_str(name: &str) -> Option<Command> {
+        match name {
+            "tokens" => Some(Command::Tokens),
+            "parse" => Some(Command::Parse),
+            "check" => Some(Command::Check),
+            "ir" => Some(Command::Ir),
+            "run" => Some(Command::Run),
+            "compile" => Some(Command::Compile),
+            "repl" => Some(Command::Repl),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub command: Command,
+    /// Нет только у repl
+    pub path: Option<String>,
+    /// Сообщения об ошибках в JSON, по одному массиву на запуск
+    pub json: bool,
+    pub output: Option<String>,
+}
+
+impl Options {
+    /// Аргументы без имени программы
+    pub fn parse(args: &[String]) -> Result<Options, String> {
+        let mut command = None;
+        let mut path = None;
+        let mut json = false;
+        let mut output = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--json" => json = true,
+                "-o" => output = Some(args.next().ok_or("Missing file name after -o")?.to_owned()),
+                flag if flag.starts_with('-') => return Err(format!("Unknown option '{}'", flag)),
+                name if command.is_none() && path.is_none() && Command::from_str(name).is_some() =>
+                    command = Command::from_str(name),
+                name if path.is_none() => path = Some(name.to_owned()),
+                name => return Err(format!("Unexpected argument '{}'", name)),
+            }
+        }
+        let command = command.unwrap_or(Command::Run);
+        if output.is_some() && command != Command::Compile {
+            return Err("Option -o is only valid for compile".to_owned());
+        }
+        match (command, &path) {
+            (Command::Repl, Some(_)) => return Err("Command repl does not take a file".to_owned()),
+            (Command::Repl, None) => {}
+            (_, None) => return Err("Missing file name".to_owned()),
+            _ => {}
+        }
+        Ok(Options { command, path, json, output })
+    }
+}
+
+/// Запуск с аргументами командной строки; возвращает код выхода
+pub fn main<R: BufRead>(args: &[String], input: R, out: &mut dyn Write, err: &mut dyn Write) -> i32 {
+    let options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(message) => {
+            let _ = write!(err, "{}\n\n{}", message, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+    let path = match &options.path {
+        Some(path) => path,
+        None => {
+            Repl::new().run(input, out);
+            return EXIT_OK;
+        }
+    };
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            let _ = writeln!(err, "Cannot read {}: {}", path, error);
+            return EXIT_USAGE;
+        }
+    };
+    execute(&options, &source, out, err)
+}
+
+/// Выполнение команды над текстом программы
+pub fn execute(options: &Options, source: &str, out: &mut dyn Write, err: &mut dyn Write) -> i32 {
+    let report = |diagnostics: &[Diagnostic], err: &mut dyn Write, code: i32| {
+        let map = SourceMap::new(source);
+        let _ = if options.json {
+            writeln!(err, "{}", render_all_json(diagnostics, &map))
+        } else {
+            write!(err, "{}", render_all(diagnostics, &map))
+        };
+        code
+    };
+    if options.command == Command::Tokens {
+        let lexer = Lexer::new(source.to_owned());
+        let (tokens, errors) = lexer.scan_all();
+        for token in tokens {
+            let _ = match token.tag() {
+                (tag, Some(attribute)) => writeln!(out, "{}\t{:?}\t{}", token.span(), tag, attribute),
+                (tag, None) => writeln!(out, "{}\t{:?}", token.span(), tag),
+            };
+        }
+        let errors: Vec<Diagnostic> = errors.iter().map(Diagnostic::from).collect();
+        return if errors.is_empty() { EXIT_OK } else { report(&errors, err, EXIT_ERRORS) };
+    }
+    let program = match parser::parse(source) {
+        Ok(program) => program,
+        Err(errors) => return report(&errors, err, EXIT_ERRORS),
+    };
+    if options.command == Command::Parse {
+        let _ = write!(out, "{}", program);
+        return EXIT_OK;
+    }
+    let types = match check(&program) {
+        Ok(types) => types,
+        Err(errors) => return report(&errors, err, EXIT_ERRORS),
+    };
+    match options.command {
+        Command::Ir => {
+            let _ = write!(out, "{}", ir::generate(&program, &types));
+        }
+        Command::Compile => {
+            let code = c_generator::generate(&ir::generate(&program, &types));
+            match &options.output {
+                None => {
+                    let _ = write!(out, "{}", code);
+                }
+                Some(path) => {
+                    if let Err(error) = std::fs::write(path, code) {
+                        let _ = writeln!(err, "Cannot write {}: {}", path, error);
+                        return EXIT_USAGE;
+                    }
+                }
+            }
+        }
+        Command::Run => {
+            if let Err(error) = Interpreter::new().run(&program, out) {
+                return report(&[Diagnostic::from(&error)], err, EXIT_RUNTIME);
+            }
+        }
+        Command::Check | Command::Tokens | Command::Parse | Command::Repl => {}
+    }
+    EXIT_OK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|x| x.to_owned()).collect()
+    }
+
+    fn exec(line: &str, source: &str) -> (i32, String, String) {
+        let options = Options::parse(&args(line)).unwrap();
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let code = execute(&options, source, &mut out, &mut err);
+        (code, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn options_test() {
+        assert_eq!(Options::parse(&args("a.sl")),
+                   Ok(Options { command: Command::Run, path: Some("a.sl".to_owned()), json: false, output: None }));
+        assert_eq!(Options::parse(&args("compile --json a.sl -o a.c")),
+                   Ok(Options { command: Command::Compile, path: Some("a.sl".to_owned()), json: true, output: Some("a.c".to_owned()) }));
+        // файл может называться как команда
+        assert_eq!(Options::parse(&args("parse run")).map(|x| x.path), Ok(Some("run".to_owned())));
+        assert_eq!(Options::parse(&args("repl")).map(|x| x.path), Ok(None));
+        assert_eq!(Options::parse(&args("repl a.sl")), Err("Command repl does not take a file".to_owned()));
+        assert_eq!(Options::parse(&args("ir")), Err("Missing file name".to_owned()));
+        assert_eq!(Options::parse(&args("run a.sl b.sl")), Err("Unexpected argument 'b.sl'".to_owned()));
+        assert_eq!(Options::parse(&args("run -x a.sl")), Err("Unknown option '-x'".to_owned()));
+        assert_eq!(Options::parse(&args("run a.sl -o a.c")), Err("Option -o is only valid for compile".to_owned()));
+    }
+
+    #[test]
+    fn commands_test() {
+        let source = "x = 1;\nprint x + 2;";
+        assert_eq!(exec("tokens f", "x = 1;"),
+                   (EXIT_OK, "1:1-1:2\tID\tx\n1:3-1:4\tASSIGN\n1:5-1:6\tNUMBER\t1\n1:6-1:7\tSEMICOLON\n".to_owned(), String::new()));
+        assert_eq!(exec("parse f", source), (EXIT_OK, "(= x 1)\n(print (+ x 2))\n".to_owned(), String::new()));
+        assert_eq!(exec("check f", source), (EXIT_OK, String::new(), String::new()));
+        assert_eq!(exec("ir f", source), (EXIT_OK, "    x = 1\n    t1 = x + 2\n    print t1\n".to_owned(), String::new()));
+        assert_eq!(exec("run f", source), (EXIT_OK, "3\n".to_owned(), String::new()));
+        assert!(exec("compile f", source).1.contains("printf(\"%lld\\n\", t1);"));
+    }
+
+    #[test]
+    fn exit_codes_test() {
+        let (code, out, err) = exec("tokens f", "x = 1 @;");
+        assert_eq!(code, EXIT_ERRORS);
+        assert!(out.contains("NUMBER"));
+        assert!(err.starts_with("error: Invalid character '@'"));
+        assert_eq!(exec("parse f", "x = ;").0, EXIT_ERRORS);
+        assert_eq!(exec("ir f", "print y;").0, EXIT_ERRORS);
+        let (code, out, err) = exec("run f", "print 1; print 1 / 0;");
+        assert_eq!((code, out.as_str()), (EXIT_RUNTIME, "1\n"));
+        assert!(err.starts_with("error: Division by zero\n --> 1:16"));
+
+        let (code, _, err) = exec("check --json f", "print y;");
+        assert_eq!(code, EXIT_ERRORS);
+        assert!(err.starts_with("[{\"severity\":\"error\",\"message\":\"Undefined variable 'y'\""));
+
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        assert_eq!(main(&args("frobnicate"), &b""[..], &mut out, &mut err), EXIT_USAGE);
+        assert_eq!(main(&args("run /nonexistent/file.sl"), &b""[..], &mut out, &mut err), EXIT_USAGE);
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/sum.sl").to_owned();
+        assert_eq!(main(&[path], &b""[..], &mut out, &mut err), EXIT_OK);
+        assert_eq!(String::from_utf8(out).unwrap(), "3\n6\n55\ntrue\n");
+
+        let mut out = Vec::new();
+        assert_eq!(main(&args("repl"), &b"x = 2;\nprint x;\n"[..], &mut out, &mut err), EXIT_OK);
+        assert_eq!(String::from_utf8(out).unwrap(), "> > 2\n> \n");
+    }
+}
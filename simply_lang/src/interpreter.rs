@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+
+use aho_compilers::diagnostics::Diagnostic;
+use aho_compilers::span::Span;
+
+use crate::ast::{BinaryOp, Expr, Program, Stmt, UnaryOp};
+use crate::check::Type;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value {
+    Int(i64),
+    Bool(bool),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Bool(_) => "bool",
+        }
+    }
+
+    pub fn value_type(&self) -> Type {
+        match self {
+            Value::Int(_) => Type::Int,
+            Value::Bool(_) => Type::Bool,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl RuntimeError {
+    fn new(message: &str, span: Span) -> Self {
+        RuntimeError { message: message.to_owned(), span }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.span.start)
+    }
+}
+
+impl From<&RuntimeError> for Diagnostic {
+    fn from(error: &RuntimeError) -> Self {
+        Diagnostic::error(&error.message).with_label(error.span, "")
+    }
+}
+
+///Интерпретатор, обходящий AST; print сразу пишет в out, переданный в run
+pub struct Interpreter {
+    /// Области видимости от внешней к внутренней
+    scopes: Vec<BTreeMap<String, Value>>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter { scopes: vec![BTreeMap::new()] }
+    }
+
+    pub fn scopes(&self) -> &[BTreeMap<String, Value>] {
+        &self.scopes
+    }
+
+    /// Оставляет в глобальной области только переменные, для которых keep - true
+    pub fn retain_globals<F: FnMut(&str) -> bool>(&mut self, mut keep: F) {
+        self.scopes[0].retain(|name, _| keep(name));
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<Value> {
+        self.scopes.iter().rev().find_map(|x| x.get(name)).copied()
+    }
+
+    pub fn run(&mut self, program: &Program, out: &mut dyn Write) -> Result<(), RuntimeError> {
+        program.statements.iter().try_for_each(|x| self.execute(x, out))
+    }
+
+    // во вложенной области; область снимается и при ошибке
+    fn scoped<F: FnOnce(&mut Self) -> Result<(), RuntimeError>>(&mut self, f: F) -> Result<(), RuntimeError> {
+        self.scopes.push(BTreeMap::new());
+        let result = f(self);
+        self.scopes.pop();
+        result
+    }
+
+    pub fn execute(&mut self, statement: &Stmt, out: &mut dyn Write) -> Result<(), RuntimeError> {
+        match statement {
+            Stmt::Assign { name, value, .. } => {
+                let value = self.evaluate(value)?;
+                let scope = self.scopes.iter_mut().rev().find(|x| x.contains_key(name));
+                match scope {
+                    Some(scope) => scope.insert(name.to_owned(), value),
+                    None => self.scopes.last_mut().unwrap().insert(name.to_owned(), value),
+                };
+                Ok(())
+            }
+            Stmt::If { condition, then_branch, else_branch, .. } => {
+                if self.condition(condition)? {
+                    self.execute(then_branch, out)
+                } else if let Some(else_branch) = else_branch {
+                    self.execute(else_branch, out)
+                } else {
+                    Ok(())
+                }
+            }
+            // переменная цикла живёт только внутри for
+            Stmt::For { init, condition, step, body, .. } => self.scoped(|this| {
+                this.execute(init, out)?;
+                while this.condition(condition)? {
+                    this.execute(body, out)?;
+                    this.execute(step, out)?;
+                }
+                Ok(())
+            }),
+            Stmt::Block { statements, .. } => self.scoped(|this| statements.iter().try_for_each(|x| this.execute(x, out))),
+            Stmt::Print { value, span } => {
+                let value = self.evaluate(value)?;
+                writeln!(out, "{}", value).map_err(|e| RuntimeError::new(&e.to_string(), *span))
+            }
+        }
+    }
+
+    fn condition(&mut self, condition: &Expr) -> Result<bool, RuntimeError> {
+        match self.evaluate(condition)? {
+            Value::Bool(value) => Ok(value),
+            value => Err(RuntimeError::new(&format!("Expected bool condition, found {}", value.type_name()), condition.span())),
+        }
+    }
+
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        match expr {
+            Expr::Number { value, .. } => Ok(Value::Int(*value)),
+            Expr::Bool { value, .. } => Ok(Value::Bool(*value)),
+            Expr::Variable { name, span } => self.lookup(name)
+                .ok_or_else(|| RuntimeError::new(&format!("Undefined variable '{}'", name), *span)),
+            Expr::Unary { op: UnaryOp::Neg, operand, span } => match self.evaluate(operand)? {
+                Value::Int(value) => value.checked_neg().map(Value::Int)
+                    .ok_or_else(|| RuntimeError::new("Integer overflow", *span)),
+                value => Err(RuntimeError::new(&format!("Cannot negate {}", value.type_name()), *span)),
+            },
+            // and вычисляется сокращённо
+            Expr::Binary { op: BinaryOp::And, left, right, span } => {
+                let left = self.evaluate(left)?;
+                if left == Value::Bool(false) {
+                    return Ok(left);
+                }
+                match (left, self.evaluate(right)?) {
+                    (Value::Bool(_), Value::Bool(right)) => Ok(Value::Bool(right)),
+                    (left, right) => Err(type_error(BinaryOp::And, left, right, *span)),
+                }
+            }
+            Expr::Binary { op, left, right, span } => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+                binary(*op, left, right, *span)
+            }
+        }
+    }
+}
+
+fn type_error(op: BinaryOp, left: Value, right: Value, span: Span) -> RuntimeError {
+    RuntimeError::new(&format!("Cannot apply '{}' to {} and {}", op, left.type_name(), right.type_name()), span)
+}
+
+fn binary(op: BinaryOp, left: Value, right: Value, span: Span) -> Result<Value, RuntimeError> {
+    let overflow = || RuntimeError::new("Integer overflow", span);
+    match (op, left, right) {
+        (BinaryOp::Eq, left, right) if left.type_name() == right.type_name() => Ok(Value::Bool(left == right)),
+        (BinaryOp::Ne, left, right) if left.type_name() == right.type_name() => Ok(Value::Bool(left != right)),
+        (BinaryOp::Add, Value::Int(a), Value::Int(b)) => a.checked_add(b).map(Value::Int).ok_or_else(overflow),
+        (BinaryOp::Sub, Value::Int(a), Value::Int(b)) => a.checked_sub(b).map(Value::Int).ok_or_else(overflow),
+        (BinaryOp::Mul, Value::Int(a), Value::Int(b)) => a.checked_mul(b).map(Value::Int).ok_or_else(overflow),
+        (BinaryOp::Div, Value::Int(_), Value::Int(0)) => Err(RuntimeError::new("Division by zero", span)),
+        (BinaryOp::Div, Value::Int(a), Value::Int(b)) => a.checked_div(b).map(Value::Int).ok_or_else(overflow),
+        (BinaryOp::Lt, Value::Int(a), Value::Int(b)) => Ok(Value::Bool(a < b)),
+        (op, left, right) => Err(type_error(op, left, right, span)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn run(source: &str) -> Result<String, String> {
+        let program = parse(source).map_err(|x| x[0].to_string())?;
+        let mut out = Vec::new();
+        Interpreter::new().run(&program, &mut out).map_err(|x| x.to_string())?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn arithmetic_test() {
+        assert_eq!(run("x = 2; y = x * (3 + 4) - -1; print y; print y / 2; print 1 - 2 - 3;"), Ok("15\n7\n-4\n".to_owned()));
+        assert_eq!(run("print 1 < 2 and 2 == 2; print 1 != 1;"), Ok("true\nfalse\n".to_owned()));
+    }
+
+    #[test]
+    fn control_flow_test() {
+        let source = "
+            sum = 0;
+            for (i = 1; i < 11; i = i + 1) {
+                if i == 3 and true then sum = sum + 100; else sum = sum + i;
+            }
+            print sum;
+            if sum == 0 then print 0;
+        ";
+        assert_eq!(run(source), Ok("152\n".to_owned()));
+    }
+
+    #[test]
+    fn scopes_test() {
+        assert_eq!(run("x = 1; { x = 2; y = 3; } print x;"), Ok("2\n".to_owned()));
+        assert_eq!(run("{ y = 3; } print y;"), Err("Undefined variable 'y' at 1:18".to_owned()));
+        assert_eq!(run("for (i = 0; i < 2; i = i + 1) print i; print i;"), Err("Undefined variable 'i' at 1:46".to_owned()));
+
+        let mut interpreter = Interpreter::new();
+        interpreter.run(&parse("a = 1; { b = 2; }").unwrap(), &mut Vec::new()).unwrap();
+        assert_eq!(interpreter.scopes().len(), 1);
+        assert_eq!(interpreter.lookup("a"), Some(Value::Int(1)));
+        assert_eq!(interpreter.lookup("b"), None);
+    }
+
+    #[test]
+    fn errors_test() {
+        assert_eq!(run("print 1 / (2 - 2);"), Err("Division by zero at 1:7".to_owned()));
+        assert_eq!(run("if 1 then print 1;"), Err("Expected bool condition, found int at 1:4".to_owned()));
+        assert_eq!(run("print true + 1;"), Err("Cannot apply '+' to bool and int at 1:7".to_owned()));
+        assert_eq!(run("print 9223372036854775807 + 1;"), Err("Integer overflow at 1:7".to_owned()));
+        assert_eq!(run("print false and 1; print 1 and true;"), Err("Cannot apply 'and' to int and bool at 1:26".to_owned()));
+    }
+}
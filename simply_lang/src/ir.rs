@@ -0,0 +1,344 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::ast::{BinaryOp, Expr, Program, Stmt, UnaryOp};
+use crate::check::{Type, Types};
+
+//Трёхадресный код (раздел 6.2): не больше одного оператора справа,
+//управление - метки, goto и ifFalse. Вложенные области видимости не нужны:
+//семантический анализ гарантирует, что одноимённые переменные не живут одновременно.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    Int(i64),
+    Bool(bool),
+    /// Переменная программы или временная t1, t2, ...
+    Name(String),
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Int(value) => write!(f, "{}", value),
+            Operand::Bool(value) => write!(f, "{}", value),
+            Operand::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instr {
+    Copy { dest: String, src: Operand },
+    Unary { dest: String, op: UnaryOp, arg: Operand },
+    Binary { dest: String, op: BinaryOp, left: Operand, right: Operand },
+    Label(usize),
+    Goto(usize),
+    IfFalse { condition: Operand, target: usize },
+    /// Тип нужен, чтобы напечатать bool как true/false
+    Print { value: Operand, value_type: Type },
+}
+
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instr::Copy { dest, src } => write!(f, "    {} = {}", dest, src),
+            Instr::Unary { dest, op, arg } => write!(f, "    {} = {}{}", dest, op, arg),
+            Instr::Binary { dest, op, left, right } => write!(f, "    {} = {} {} {}", dest, left, op, right),
+            Instr::Label(label) => write!(f, "L{}:", label),
+            Instr::Goto(label) => write!(f, "    goto L{}", label),
+            Instr::IfFalse { condition, target } => write!(f, "    ifFalse {} goto L{}", condition, target),
+            Instr::Print { value, .. } => write!(f, "    print {}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Code {
+    pub instructions: Vec<Instr>,
+    /// Переменные программы в порядке первого присваивания
+    pub variables: Vec<String>,
+    pub temps: Vec<String>,
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for instruction in &self.instructions {
+            writeln!(f, "{}", instruction)?;
+        }
+        Ok(())
+    }
+}
+
+struct Generator<'a> {
+    types: &'a Types,
+    code: Code,
+    /// Имена переменных программы; временные их не занимают
+    reserved: BTreeSet<String>,
+    temps: usize,
+    labels: usize,
+}
+
+/// Генерация кода для проверенной программы; types - результат check
+pub fn generate(program: &Program, types: &Types) -> Code {
+    let mut reserved = BTreeSet::new();
+    program.statements.iter().for_each(|x| assigned(x, &mut reserved));
+    let mut generator = Generator { types, code: Code::default(), reserved, temps: 0, labels: 0 };
+    program.statements.iter().for_each(|x| generator.statement(x));
+    generator.code
+}
+
+fn assigned(statement: &Stmt, names: &mut BTreeSet<String>) {
+    match statement {
+        Stmt::Assign { name, .. } => {
+            names.insert(name.to_owned());
+        }
+        Stmt::If { then_branch, else_branch, .. } => {
+            assigned(then_branch, names);
+            if let Some(else_branch) = else_branch {
+                assigned(else_branch, names);
+            }
+        }
+        Stmt::For { init, step, body, .. } => {
+            assigned(init, names);
+            assigned(step, names);
+            assigned(body, names);
+        }
+        Stmt::Block { statements, .. } => statements.iter().for_each(|x| assigned(x, names)),
+        Stmt::Print { .. } => {}
+    }
+}
+
+impl<'a> Generator<'a> {
+    fn emit(&mut self, instruction: Instr) {
+        let dest = match &instruction {
+            Instr::Copy { dest, .. } | Instr::Unary { dest, .. } | Instr::Binary { dest, .. } => Some(dest),
+            _ => None,
+        };
+        if let Some(dest) = dest {
+            if !self.code.variables.contains(dest) && !self.code.temps.contains(dest) {
+                self.code.variables.push(dest.to_owned());
+            }
+        }
+        self.code.instructions.push(instruction);
+    }
+
+    fn temp(&mut self) -> String {
+        loop {
+            self.temps += 1;
+            let name = format!("t{}", self.temps);
+            if !self.reserved.contains(&name) {
+                self.code.temps.push(name.to_owned());
+                return name;
+            }
+        }
+    }
+
+    fn label(&mut self) -> usize {
+        self.labels += 1;
+        self.labels
+    }
+
+    fn statement(&mut self, statement: &Stmt) {
+        match statement {
+            Stmt::Assign { name, value, .. } => {
+                let src = self.expr(value);
+                self.emit(Instr::Copy { dest: name.to_owned(), src });
+            }
+            Stmt::If { condition, then_branch, else_branch, .. } => {
+                let condition = self.expr(condition);
+                let after = self.label();
+                match else_branch {
+                    None => {
+                        self.emit(Instr::IfFalse { condition, target: after });
+                        self.statement(then_branch);
+                    }
+                    Some(else_branch) => {
+                        let otherwise = self.label();
+                        self.emit(Instr::IfFalse { condition, target: otherwise });
+                        self.statement(then_branch);
+                        self.emit(Instr::Goto(after));
+                        self.emit(Instr::Label(otherwise));
+                        self.statement(else_branch);
+                    }
+                }
+                self.emit(Instr::Label(after));
+            }
+            Stmt::For { init, condition, step, body, .. } => {
+                self.statement(init);
+                let (begin, after) = (self.label(), self.label());
+                self.emit(Instr::Label(begin));
+                let condition = self.expr(condition);
+                self.emit(Instr::IfFalse { condition, target: after });
+                self.statement(body);
+                self.statement(step);
+                self.emit(Instr::Goto(begin));
+                self.emit(Instr::Label(after));
+            }
+            Stmt::Block { statements, .. } => statements.iter().for_each(|x| self.statement(x)),
+            Stmt::Print { value, .. } => {
+                let value_type = self.types.get(&value.span()).copied().unwrap_or(Type::Int);
+                let value = self.expr(value);
+                self.emit(Instr::Print { value, value_type });
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Operand {
+        match expr {
+            Expr::Number { value, .. } => Operand::Int(*value),
+            Expr::Bool { value, .. } => Operand::Bool(*value),
+            Expr::Variable { name, .. } => Operand::Name(name.to_owned()),
+            Expr::Unary { op, operand, .. } => {
+                let arg = self.expr(operand);
+                let dest = self.temp();
+                self.emit(Instr::Unary { dest: dest.to_owned(), op: *op, arg });
+                Operand::Name(dest)
+            }
+            // and вычисляется сокращённо: правая часть только если левая истинна
+            Expr::Binary { op: BinaryOp::And, left, right, .. } => {
+                let left = self.expr(left);
+                let dest = self.temp();
+                let after = self.label();
+                self.emit(Instr::Copy { dest: dest.to_owned(), src: left });
+                self.emit(Instr::IfFalse { condition: Operand::Name(dest.to_owned()), target: after });
+                let right = self.expr(right);
+                self.emit(Instr::Copy { dest: dest.to_owned(), src: right });
+                self.emit(Instr::Label(after));
+                Operand::Name(dest)
+            }
+            Expr::Binary { op, left, right, .. } => {
+                let left = self.expr(left);
+                let right = self.expr(right);
+                let dest = self.temp();
+                self.emit(Instr::Binary { dest: dest.to_owned(), op: *op, left, right });
+                Operand::Name(dest)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::check;
+    use crate::parser::parse;
+    use std::collections::BTreeMap;
+
+    fn code(source: &str) -> Code {
+        let program = parse(source).unwrap();
+        let types = check(&program).unwrap();
+        generate(&program, &types)
+    }
+
+    // исполнитель трёхадресного кода: результат должен совпасть с интерпретатором AST
+    fn execute(code: &Code) -> String {
+        let labels: BTreeMap<usize, usize> = code.instructions.iter().enumerate()
+            .filter_map(|(i, x)| match x {
+                Instr::Label(label) => Some((*label, i)),
+                _ => None,
+            })
+            .collect();
+        let mut memory: BTreeMap<String, i64> = BTreeMap::new();
+        let mut out = String::new();
+        let mut pc = 0;
+        while let Some(instruction) = code.instructions.get(pc) {
+            let get = |x: &Operand| match x {
+                Operand::Int(value) => *value,
+                Operand::Bool(value) => *value as i64,
+                Operand::Name(name) => memory[name],
+            };
+            pc += 1;
+            match instruction {
+                Instr::Copy { dest, src } => {
+                    let value = get(src);
+                    memory.insert(dest.to_owned(), value);
+                }
+                Instr::Unary { dest, arg, .. } => {
+                    let value = -get(arg);
+                    memory.insert(dest.to_owned(), value);
+                }
+                Instr::Binary { dest, op, left, right } => {
+                    let (a, b) = (get(left), get(right));
+                    let value = match op {
+                        BinaryOp::Add => a + b,
+                        BinaryOp::Sub => a - b,
+                        BinaryOp::Mul => a * b,
+                        BinaryOp::Div => a / b,
+                        BinaryOp::Eq => (a == b) as i64,
+                        BinaryOp::Ne => (a != b) as i64,
+                        BinaryOp::Lt => (a < b) as i64,
+                        BinaryOp::And => unreachable!(),
+                    };
+                    memory.insert(dest.to_owned(), value);
+                }
+                Instr::Label(_) => {}
+                Instr::Goto(label) => pc = labels[label],
+                Instr::IfFalse { condition, target } => {
+                    if get(condition) == 0 {
+                        pc = labels[target];
+                    }
+                }
+                Instr::Print { value, value_type: Type::Int } => out += &format!("{}\n", get(value)),
+                Instr::Print { value, value_type: Type::Bool } => out += &format!("{}\n", get(value) != 0),
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn generate_test() {
+        let code = code("x = 2 * (3 + 4); if x < 10 and true then print x; else print -x;");
+        assert_eq!(code.to_string(), concat!(
+            "    t1 = 3 + 4\n",
+            "    t2 = 2 * t1\n",
+            "    x = t2\n",
+            "    t3 = x < 10\n",
+            "    t4 = t3\n",
+            "    ifFalse t4 goto L1\n",
+            "    t4 = true\n",
+            "L1:\n",
+            "    ifFalse t4 goto L3\n",
+            "    print x\n",
+            "    goto L2\n",
+            "L3:\n",
+            "    t5 = -x\n",
+            "    print t5\n",
+            "L2:\n",
+        ));
+        assert_eq!(code.variables, vec!["x"]);
+        assert_eq!(code.temps, vec!["t1", "t2", "t3", "t4", "t5"]);
+    }
+
+    #[test]
+    fn loop_test() {
+        let code = code("for (i = 0; i < 2; i = i + 1) print i == 1;");
+        assert_eq!(code.to_string(), concat!(
+            "    i = 0\n",
+            "L1:\n",
+            "    t1 = i < 2\n",
+            "    ifFalse t1 goto L2\n",
+            "    t2 = i == 1\n",
+            "    print t2\n",
+            "    t3 = i + 1\n",
+            "    i = t3\n",
+            "    goto L1\n",
+            "L2:\n",
+        ));
+        assert_eq!(execute(&code), "false\ntrue\n");
+    }
+
+    #[test]
+    fn temps_test() {
+        let code = code("t1 = 1; t3 = -t1 * 2;");
+        assert_eq!(code.temps, vec!["t2", "t4"]);
+        assert_eq!(code.to_string(), "    t1 = 1\n    t2 = -t1\n    t4 = t2 * 2\n    t3 = t4\n");
+    }
+
+    #[test]
+    fn same_as_interpreter_test() {
+        let source = include_str!("../examples/sum.sl");
+        let mut out = Vec::new();
+        crate::run(source, &mut out).unwrap();
+        assert_eq!(execute(&code(source)), String::from_utf8(out).unwrap());
+    }
+}
@@ -1,10 +1,12 @@
 use std::cell::Cell;
 use std::fmt::{Debug, Formatter, Error};
 
-const BUFFERSIZE: usize = 2048;
+use aho_compilers::lex_error::LexError;
+use aho_compilers::span::{SourceMap, Span};
 
-trait Token {
+pub trait Token {
     fn tag(&self) -> (Tag, Option<String>);
+    fn span(&self) -> Span;
 }
 
 impl Debug for dyn Token {
@@ -20,7 +22,8 @@ impl Debug for dyn Token {
 
 #[derive(Ord, PartialOrd, Eq, PartialEq, Debug, Copy, Clone)]
 pub struct Num {
-    value: u64,
+    value: i64,
+    span: Span,
 }
 
 impl Num {}
@@ -30,14 +33,39 @@ impl Token for Num {
     fn tag(&self) -> (Tag, Option<String>) {
         (Tag::NUMBER, Option::Some(self.value.to_string()))
     }
+
+    fn span(&self) -> Span {
+        self.span
+    }
 }
 
-pub struct Word {}
+///Ключевые слова, идентификаторы и знаки операций
+#[derive(Ord, PartialOrd, Eq, PartialEq, Debug, Clone)]
+pub struct Word {
+    tag: Tag,
+    lexeme: String,
+    span: Span,
+}
 
 impl Word {}
 
+impl Token for Word {
+    // атрибут есть только у идентификатора
+    fn tag(&self) -> (Tag, Option<String>) {
+        match self.tag {
+            Tag::ID => (Tag::ID, Some(self.lexeme.to_owned())),
+            tag => (tag, None),
+        }
+    }
+
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
-enum Tag {
+pub enum Tag {
     IF,
     THEN,
     ELSE,
@@ -48,11 +76,67 @@ enum Tag {
     MINUS,
     ID,
     NUMBER,
+    PRINT,
+    TRUE,
+    FALSE,
+    PLUS,
+    STAR,
+    SLASH,
+    LT,
+    ASSIGN,
+    SEMICOLON,
+    LPAREN,
+    RPAREN,
+    LBRACE,
+    RBRACE,
 }
 
+impl Tag {
+    /// Как тэг выглядит в тексте программы; для сообщений об ошибках
+    pub fn text(self) -> &'static str {
+        match self {
+            Tag::IF => "if",
+            Tag::THEN => "then",
+            Tag::ELSE => "else",
+            Tag::FOR => "for",
+            Tag::AND => "and",
+            Tag::EQ => "==",
+            Tag::NE => "!=",
+            Tag::MINUS => "-",
+            Tag::ID => "identifier",
+            Tag::NUMBER => "number",
+            Tag::PRINT => "print",
+            Tag::TRUE => "true",
+            Tag::FALSE => "false",
+            Tag::PLUS => "+",
+            Tag::STAR => "*",
+            Tag::SLASH => "/",
+            Tag::LT => "<",
+            Tag::ASSIGN => "=",
+            Tag::SEMICOLON => ";",
+            Tag::LPAREN => "(",
+            Tag::RPAREN => ")",
+            Tag::LBRACE => "{",
+            Tag::RBRACE => "}",
+        }
+    }
+}
+
+const KEYWORDS: [Tag; 8] = [Tag::IF, Tag::THEN, Tag::ELSE, Tag::FOR, Tag::AND, Tag::PRINT, Tag::TRUE, Tag::FALSE];
+
+// сначала двухсимвольные, чтобы == не распалось на два =
+const OPERATORS: [Tag; 13] = [
+    Tag::EQ, Tag::NE, Tag::MINUS, Tag::PLUS, Tag::STAR, Tag::SLASH, Tag::LT, Tag::ASSIGN,
+    Tag::SEMICOLON, Tag::LPAREN, Tag::RPAREN, Tag::LBRACE, Tag::RBRACE,
+];
+
 
 pub struct Lexer {
+    /// Весь вход и ограничитель '\0' в конце
     buffer: Vec<char>,
+    /// Смещение в байтах каждого символа буфера
+    offsets: Vec<usize>,
+    source_map: SourceMap,
     lexeme_begin: Cell<usize>,
     forward: Cell<usize>,
 }
@@ -60,52 +144,138 @@ pub struct Lexer {
 
 impl Lexer {
     pub fn new(input: String) -> Self {
-        let mut chars: Vec<char> = input.chars().take(2048).collect();
+        let mut chars: Vec<char> = input.chars().collect();
+        let mut offsets: Vec<usize> = input.char_indices().map(|(i, _)| i).collect();
+        offsets.push(input.len());
         chars.push('\0');
         Self {
             buffer: chars,
+            offsets,
+            source_map: SourceMap::new(&input),
             lexeme_begin: Cell::new(0),
             forward: Cell::new(0),
         }
     }
 
-    fn next_token() -> Box<dyn Token> {
-        unimplemented!()
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
     }
 
-    fn scan(&self) -> Option<Box<Token>> {
-        let lexeme_begin = &self.lexeme_begin;
+    fn peek(&self) -> char {
+        self.buffer[self.forward.get()]
+    }
+
+    fn at_end(&self) -> bool {
+        self.forward.get() + 1 == self.buffer.len()
+    }
+
+    fn lexeme(&self) -> String {
+        self.buffer[self.lexeme_begin.get()..self.forward.get()].iter().collect()
+    }
+
+    // пробелы и комментарии // до конца строки
+    fn skip_whitespaces(&self) {
         let forward = &self.forward;
-        //skip whitespaces
-        while self.buffer[forward.get()].is_whitespace() {
-            dbg!("Whitespace at position: {}", forward.get());
-            forward.set(forward.get() + 1);
+        loop {
+            if self.peek().is_whitespace() {
+                forward.set(forward.get() + 1);
+            } else if self.peek() == '/' && self.buffer[forward.get() + 1] == '/' {
+                while !self.at_end() && self.peek() != '\n' {
+                    forward.set(forward.get() + 1);
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next_token(&self) -> Option<Box<dyn Token>> {
+        let forward = &self.forward;
+        let c = self.peek();
+        if c.is_alphabetic() || c == '_' {
+            while self.peek().is_alphanumeric() || self.peek() == '_' {
+                forward.set(forward.get() + 1);
+            }
+            let lexeme = self.lexeme();
+            let tag = KEYWORDS.iter().find(|x| x.text() == lexeme).copied().unwrap_or(Tag::ID);
+            return Some(Box::new(Word { tag, lexeme, span: self.span() }));
         }
+        let rest: String = self.buffer[forward.get()..].iter().take(2).collect();
+        let tag = OPERATORS.iter().find(|x| rest.starts_with(x.text()))?;
+        forward.set(forward.get() + tag.text().chars().count());
+        Some(Box::new(Word { tag: *tag, lexeme: self.lexeme(), span: self.span() }))
+    }
+
+    pub fn scan(&self) -> Result<Option<Box<dyn Token>>, LexError> {
+        let lexeme_begin = &self.lexeme_begin;
+        let forward = &self.forward;
+        self.skip_whitespaces();
         lexeme_begin.set(forward.get());
+        if self.at_end() {
+            return Ok(None);
+        }
 
-        if self.buffer[forward.get()].is_numeric() {
+        if self.buffer[forward.get()].is_ascii_digit() {
             forward.set(forward.get() + 1);
-            while self.buffer[forward.get()].is_numeric() {
+            while self.buffer[forward.get()].is_ascii_digit() {
                 forward.set(forward.get() + 1);
             }
 
-            let number = self.buffer[lexeme_begin.get()..forward.get()].iter()
-                .fold("".to_string(), |mut acc, value| {
-                    acc.push(*value);
-                    acc
-                }).parse::<u64>().unwrap();
+            let literal = self.lexeme();
+            let number = literal.parse::<i64>()
+                .map_err(|_| LexError::NumberOverflow { literal, span: self.span() })?;
 
             let token: Num = Num {
-                value: number.to_owned()
+                value: number.to_owned(),
+                span: self.span(),
             };
+            return Ok(Some(Box::new(token)));
+        }
 
-            dbg!("Get token [{:?}] in position [{:?}]", &token ,lexeme_begin.get());
+        if let Some(token) = self.next_token() {
+            return Ok(Some(token));
+        }
 
-            return Some(Box::new(token));
+        //режим паники: пропускаем символы, с которых не начинается ни один токен
+        let character = self.buffer[forward.get()];
+        forward.set(forward.get() + 1);
+        while !self.at_end() {
+            let begin = forward.get();
+            let c = self.peek();
+            let starts_token = c.is_whitespace() || c.is_ascii_digit() || self.next_token().is_some();
+            forward.set(begin);
+            if starts_token {
+                break;
+            }
+            forward.set(begin + 1);
+        }
+        Err(LexError::InvalidCharacter { character, span: self.span() })
+    }
+
+    /// Все токены до конца входа и все ошибки
+    pub fn scan_all(&self) -> (Vec<Box<dyn Token>>, Vec<LexError>) {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        loop {
+            match self.scan() {
+                Ok(Some(token)) => tokens.push(token),
+                Ok(None) => return (tokens, errors),
+                Err(error) => errors.push(error),
+            }
         }
-        return None;
     }
 
+    fn span(&self) -> Span {
+        self.source_map.span(self.offsets[self.lexeme_begin.get()], self.offsets[self.forward.get()])
+    }
+
+    /// Пустой участок в конце входа
+    pub fn end_span(&self) -> Span {
+        let end = self.source_map.position(self.source_map.source().len());
+        Span::new(end, end)
+    }
+
+    #[allow(dead_code)]
     fn test_print(&self) {
         self.buffer.iter().for_each(|x| println!("{}", x));
     }
@@ -118,7 +288,7 @@ mod tests {
 
     #[test]
     fn new_test() {
-        let l = Lexer::new("hello".to_owned());
+        let _l = Lexer::new("hello".to_owned());
     }
 
     #[test]
@@ -130,8 +300,57 @@ mod tests {
     #[test]
     fn scan_test() {
         let l = Lexer::new(" 123  422".to_owned());
-        while let Some(token) = l.scan() {
+        while let Some(token) = l.scan().unwrap() {
             println!("{:?}", token);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn span_test() {
+        let l = Lexer::new(" 123\n  422".to_owned());
+        let first = l.scan().unwrap().unwrap();
+        assert_eq!(first.span().to_string(), "1:2-1:5");
+        let second = l.scan().unwrap().unwrap();
+        assert_eq!(second.span().start.line, 2);
+        assert_eq!(second.span().start.column, 3);
+        assert_eq!(second.span().text(" 123\n  422"), "422");
+    }
+
+    #[test]
+    fn tags_test() {
+        let l = Lexer::new("if x1 == -2 and y != 3 then { print x1; } else z = (a + b) * c / 4 < 5; // comment\nfor".to_owned());
+        let (tokens, errors) = l.scan_all();
+        assert!(errors.is_empty());
+        let tags: Vec<Tag> = tokens.iter().map(|x| x.tag().0).collect();
+        assert_eq!(tags, vec![
+            Tag::IF, Tag::ID, Tag::EQ, Tag::MINUS, Tag::NUMBER, Tag::AND, Tag::ID, Tag::NE, Tag::NUMBER, Tag::THEN,
+            Tag::LBRACE, Tag::PRINT, Tag::ID, Tag::SEMICOLON, Tag::RBRACE, Tag::ELSE, Tag::ID, Tag::ASSIGN,
+            Tag::LPAREN, Tag::ID, Tag::PLUS, Tag::ID, Tag::RPAREN, Tag::STAR, Tag::ID, Tag::SLASH, Tag::NUMBER,
+            Tag::LT, Tag::NUMBER, Tag::SEMICOLON, Tag::FOR,
+        ]);
+        assert_eq!(tokens[1].tag().1, Some("x1".to_owned()));
+        assert_eq!(tokens[0].tag().1, None);
+        assert_eq!(tokens.last().unwrap().span().start.line, 2);
+    }
+
+    #[test]
+    fn scan_all_test() {
+        let input = "12 a@#b 34\n99999999999999999999999 ?";
+        let map = SourceMap::new(input);
+        let l = Lexer::new(input.to_owned());
+        let (tokens, errors) = l.scan_all();
+        let tags: Vec<_> = tokens.iter().map(|x| x.tag()).collect();
+        assert_eq!(tags, vec![
+            (Tag::NUMBER, Some("12".to_owned())),
+            (Tag::ID, Some("a".to_owned())),
+            (Tag::ID, Some("b".to_owned())),
+            (Tag::NUMBER, Some("34".to_owned())),
+        ]);
+        assert_eq!(errors, vec![
+            LexError::InvalidCharacter { character: '@', span: map.span(4, 6) },
+            LexError::NumberOverflow { literal: "99999999999999999999999".to_owned(), span: map.span(11, 34) },
+            LexError::InvalidCharacter { character: '?', span: map.span(35, 36) },
+        ]);
+        assert_eq!(l.end_span().start.offset, input.len());
+    }
+}
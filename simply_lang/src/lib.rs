@@ -0,0 +1,34 @@
+pub mod ast;
+pub mod c_generator;
+pub mod check;
+pub mod cli;
+pub mod interpreter;
+pub mod ir;
+pub mod lexer;
+pub mod parser;
+pub mod repl;
+
+use std::io::Write;
+
+use aho_compilers::diagnostics::Diagnostic;
+
+use interpreter::Interpreter;
+
+/// Разбор, проверка и выполнение программы; ошибки уже в виде сообщений
+pub fn run<W: Write>(source: &str, mut out: W) -> Result<(), Vec<Diagnostic>> {
+    let program = parser::parse(source)?;
+    check::check(&program)?;
+    Interpreter::new().run(&program, &mut out).map_err(|error| vec![Diagnostic::from(&error)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run;
+
+    #[test]
+    fn example_test() {
+        let mut out = Vec::new();
+        run(include_str!("../examples/sum.sl"), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "3\n6\n55\ntrue\n");
+    }
+}
@@ -1,5 +1,9 @@
-mod lexer;
+use std::io::{stderr, stdin, stdout, Write};
+use std::process::exit;
 
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let code = simply_lang::cli::main(&args, stdin().lock(), &mut stdout(), &mut stderr());
+    let _ = stdout().flush();
+    exit(code);
 }
@@ -0,0 +1,327 @@
+use std::fmt;
+
+use aho_compilers::diagnostics::Diagnostic;
+use aho_compilers::span::Span;
+
+use crate::ast::{BinaryOp, Expr, Program, Stmt, UnaryOp};
+use crate::lexer::{Lexer, Tag, Token};
+
+//Рекурсивный спуск по грамматике:
+//
+// program  -> stmt*
+// stmt     -> assign ; | if expr then stmt (else stmt)? | for ( assign ; expr ; assign ) stmt
+//           | { stmt* } | print expr ;
+// assign   -> id = expr
+// expr     -> equality (and equality)*
+// equality -> relation ((== | !=) relation)*
+// relation -> additive (< additive)?
+// additive -> term ((+ | -) term)*
+// term     -> unary ((* | /) unary)*
+// unary    -> - unary | primary
+// primary  -> number | true | false | id | ( expr )
+//
+// else относится к ближайшему if.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub expected: String,
+    /// Текст найденного токена или "end of input"
+    pub found: String,
+    pub span: Span,
+}
+
+impl ParseError {
+    /// Вход оборвался; для многострочного ввода это повод дочитать ещё строку
+    pub fn at_end(&self) -> bool {
+        self.found == END_OF_INPUT
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Expected {}, found {} at {}", self.expected, self.found, self.span.start)
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        Diagnostic::error(&format!("Expected {}, found {}", error.expected, error.found))
+            .with_label(error.span, &format!("expected {}", error.expected))
+    }
+}
+
+const END_OF_INPUT: &str = "end of input";
+
+pub struct Parser {
+    tokens: Vec<Box<dyn Token>>,
+    position: usize,
+    end: Span,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Box<dyn Token>>, end: Span) -> Self {
+        Parser { tokens, position: 0, end }
+    }
+
+    fn peek(&self) -> Option<Tag> {
+        self.tokens.get(self.position).map(|x| x.tag().0)
+    }
+
+    fn span(&self) -> Span {
+        self.tokens.get(self.position).map_or(self.end, |x| x.span())
+    }
+
+    // конец предыдущего токена - конец разобранной конструкции
+    fn previous_end(&self) -> Span {
+        self.tokens[self.position - 1].span()
+    }
+
+    fn error(&self, expected: &str) -> ParseError {
+        let found = match self.tokens.get(self.position) {
+            None => END_OF_INPUT.to_owned(),
+            Some(token) => match token.tag() {
+                (_, Some(attribute)) => format!("'{}'", attribute),
+                (tag, None) => format!("'{}'", tag.text()),
+            },
+        };
+        ParseError { expected: expected.to_owned(), found, span: self.span() }
+    }
+
+    fn eat(&mut self, tag: Tag) -> bool {
+        if self.peek() == Some(tag) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, tag: Tag) -> Result<Span, ParseError> {
+        let span = self.span();
+        if self.eat(tag) {
+            Ok(span)
+        } else {
+            Err(self.error(&format!("'{}'", tag.text())))
+        }
+    }
+
+    fn identifier(&mut self) -> Result<(String, Span), ParseError> {
+        match self.tokens.get(self.position).map(|x| x.tag()) {
+            Some((Tag::ID, Some(name))) => {
+                self.position += 1;
+                Ok((name, self.previous_end()))
+            }
+            _ => Err(self.error("identifier")),
+        }
+    }
+
+    pub fn parse_program(&mut self) -> Result<Program, ParseError> {
+        let mut statements = Vec::new();
+        while self.peek().is_some() {
+            statements.push(self.statement()?);
+        }
+        Ok(Program { statements })
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.span();
+        match self.peek() {
+            Some(Tag::ID) => {
+                let statement = self.assign()?;
+                self.expect(Tag::SEMICOLON)?;
+                Ok(statement)
+            }
+            Some(Tag::IF) => {
+                self.position += 1;
+                let condition = self.expr()?;
+                self.expect(Tag::THEN)?;
+                let then_branch = Box::new(self.statement()?);
+                let else_branch = if self.eat(Tag::ELSE) { Some(Box::new(self.statement()?)) } else { None };
+                Ok(Stmt::If { condition, then_branch, else_branch, span: start.merge(&self.previous_end()) })
+            }
+            Some(Tag::FOR) => {
+                self.position += 1;
+                self.expect(Tag::LPAREN)?;
+                let init = Box::new(self.assign()?);
+                self.expect(Tag::SEMICOLON)?;
+                let condition = self.expr()?;
+                self.expect(Tag::SEMICOLON)?;
+                let step = Box::new(self.assign()?);
+                self.expect(Tag::RPAREN)?;
+                let body = Box::new(self.statement()?);
+                Ok(Stmt::For { init, condition, step, body, span: start.merge(&self.previous_end()) })
+            }
+            Some(Tag::LBRACE) => {
+                self.position += 1;
+                let mut statements = Vec::new();
+                while self.peek() != Some(Tag::RBRACE) {
+                    if self.peek().is_none() {
+                        return Err(self.error("'}'"));
+                    }
+                    statements.push(self.statement()?);
+                }
+                let end = self.expect(Tag::RBRACE)?;
+                Ok(Stmt::Block { statements, span: start.merge(&end) })
+            }
+            Some(Tag::PRINT) => {
+                self.position += 1;
+                let value = self.expr()?;
+                let end = self.expect(Tag::SEMICOLON)?;
+                Ok(Stmt::Print { value, span: start.merge(&end) })
+            }
+            _ => Err(self.error("statement")),
+        }
+    }
+
+    fn assign(&mut self) -> Result<Stmt, ParseError> {
+        let (name, start) = self.identifier()?;
+        self.expect(Tag::ASSIGN)?;
+        let value = self.expr()?;
+        let span = start.merge(&value.span());
+        Ok(Stmt::Assign { name, value, span })
+    }
+
+    // левоассоциативная цепочка operand (op operand)*
+    fn chain(&mut self, operators: &[(Tag, BinaryOp)], operand: fn(&mut Self) -> Result<Expr, ParseError>)
+             -> Result<Expr, ParseError> {
+        let mut left = operand(self)?;
+        while let Some(&(_, op)) = operators.iter().find(|(tag, _)| self.peek() == Some(*tag)) {
+            self.position += 1;
+            let right = operand(self)?;
+            let span = left.span().merge(&right.span());
+            left = Expr::Binary { op, left: Box::new(left), right: Box::new(right), span };
+        }
+        Ok(left)
+    }
+
+    pub fn expr(&mut self) -> Result<Expr, ParseError> {
+        self.chain(&[(Tag::AND, BinaryOp::And)], Self::equality)
+    }
+
+    fn equality(&mut self) -> Result<Expr, ParseError> {
+        self.chain(&[(Tag::EQ, BinaryOp::Eq), (Tag::NE, BinaryOp::Ne)], Self::relation)
+    }
+
+    fn relation(&mut self) -> Result<Expr, ParseError> {
+        let left = self.additive()?;
+        if !self.eat(Tag::LT) {
+            return Ok(left);
+        }
+        let right = self.additive()?;
+        let span = left.span().merge(&right.span());
+        Ok(Expr::Binary { op: BinaryOp::Lt, left: Box::new(left), right: Box::new(right), span })
+    }
+
+    fn additive(&mut self) -> Result<Expr, ParseError> {
+        self.chain(&[(Tag::PLUS, BinaryOp::Add), (Tag::MINUS, BinaryOp::Sub)], Self::term)
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        self.chain(&[(Tag::STAR, BinaryOp::Mul), (Tag::SLASH, BinaryOp::Div)], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        let start = self.span();
+        if self.eat(Tag::MINUS) {
+            let operand = self.unary()?;
+            let span = start.merge(&operand.span());
+            return Ok(Expr::Unary { op: UnaryOp::Neg, operand: Box::new(operand), span });
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let span = self.span();
+        let token = self.tokens.get(self.position).map(|x| x.tag());
+        let expr = match token {
+            Some((Tag::NUMBER, Some(value))) => Expr::Number { value: value.parse().unwrap_or_default(), span },
+            Some((Tag::TRUE, _)) => Expr::Bool { value: true, span },
+            Some((Tag::FALSE, _)) => Expr::Bool { value: false, span },
+            Some((Tag::ID, Some(name))) => Expr::Variable { name, span },
+            Some((Tag::LPAREN, _)) => {
+                self.position += 1;
+                let expr = self.expr()?;
+                self.expect(Tag::RPAREN)?;
+                return Ok(expr);
+            }
+            _ => return Err(self.error("expression")),
+        };
+        self.position += 1;
+        Ok(expr)
+    }
+}
+
+/// Лексический и синтаксический разбор; все лексические ошибки или первая синтаксическая
+pub fn parse(source: &str) -> Result<Program, Vec<Diagnostic>> {
+    let lexer = Lexer::new(source.to_owned());
+    let (tokens, errors) = lexer.scan_all();
+    if !errors.is_empty() {
+        return Err(errors.iter().map(Diagnostic::from).collect());
+    }
+    Parser::new(tokens, lexer.end_span()).parse_program().map_err(|error| vec![Diagnostic::from(&error)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aho_compilers::span::SourceMap;
+
+    fn parse_ok(source: &str) -> String {
+        parse(source).unwrap().to_string()
+    }
+
+    #[test]
+    fn expr_test() {
+        assert_eq!(parse_ok("x = 1 + 2 * 3 - -y / (4 - 5);"), "(= x (- (+ 1 (* 2 3)) (/ (- y) (- 4 5))))\n");
+        assert_eq!(parse_ok("b = a < 1 == true and c != d;"), "(= b (and (== (< a 1) true) (!= c d)))\n");
+    }
+
+    #[test]
+    fn statements_test() {
+        let source = "
+            for (i = 0; i < 3; i = i + 1) {
+                if i == 1 then print i; else if i == 2 then print 0;
+            }
+            print false;
+        ";
+        assert_eq!(parse_ok(source),
+                   "(for (= i 0) (< i 3) (= i (+ i 1)) (block (if (== i 1) (print i) (if (== i 2) (print 0)))))\n(print false)\n");
+        assert_eq!(parse_ok(""), "");
+    }
+
+    #[test]
+    fn spans_test() {
+        let source = "x = 1;\nif x == 1 then {\n  print x;\n}";
+        let map = SourceMap::new(source);
+        let program = parse(source).unwrap();
+        assert_eq!(program.statements[0].span(), map.span(0, 5));
+        assert_eq!(program.statements[1].span(), map.span(7, source.len()));
+        match &program.statements[1] {
+            Stmt::If { condition, .. } => assert_eq!(condition.span().text(source), "x == 1"),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn errors_test() {
+        let lexer = Lexer::new("x = (1 + ;".to_owned());
+        let error = Parser::new(lexer.scan_all().0, lexer.end_span()).parse_program().unwrap_err();
+        assert_eq!(error.to_string(), "Expected expression, found ';' at 1:10");
+        assert!(!error.at_end());
+
+        let lexer = Lexer::new("if x then {".to_owned());
+        let error = Parser::new(lexer.scan_all().0, lexer.end_span()).parse_program().unwrap_err();
+        assert_eq!(error.to_string(), "Expected '}', found end of input at 1:12");
+        assert!(error.at_end());
+
+        let errors = parse("x = 1\ny = 2;").unwrap_err();
+        assert_eq!(errors[0].render(&SourceMap::new("x = 1\ny = 2;")), "\
+error: Expected ';', found 'y'
+ --> 2:1
+  |
+2 | y = 2;
+  | ^ expected ';'
+");
+        assert_eq!(parse("x = 1 # 2;").unwrap_err()[0].to_string(), "error: Invalid character '#' at 1:7");
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+
+use aho_compilers::diagnostics::{render_all, Diagnostic};
+use aho_compilers::span::SourceMap;
+
+use crate::ast::Program;
+use crate::check::{check_with, Type, Types};
+use crate::interpreter::Interpreter;
+use crate::ir;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+pub const PROMPT: &str = "> ";
+/// Приглашение для продолжения незаконченной конструкции
+pub const CONTINUATION: &str = "... ";
+
+pub const HELP: &str = "\
+Statements are executed as soon as they are complete; variables persist between inputs.
+  :tokens [input]  show tokens of the input (or of the last executed one)
+  :ast [input]     show the syntax tree
+  :ir [input]      show three-address code
+  :env             show variables and their values
+  :help            show this message
+  :quit            exit
+An empty line ends an incomplete construct.
+";
+
+///Сеанс REPL: переменные глобальной области живут между вводами
+pub struct Repl {
+    interpreter: Interpreter,
+    /// Глобальные переменные, как их видит проверка; интерпретатор хранит только их
+    globals: BTreeMap<String, Type>,
+    /// Незаконченный ввод, ждущий продолжения
+    pending: String,
+    last: String,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Repl::new()
+    }
+}
+
+enum Parsed {
+    Complete(Program),
+    Incomplete,
+    Errors(Vec<Diagnostic>),
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Repl {
+            interpreter: Interpreter::new(),
+            globals: BTreeMap::new(),
+            pending: String::new(),
+            last: String::new(),
+        }
+    }
+
+    pub fn prompt(&self) -> &'static str {
+        if self.pending.is_empty() { PROMPT } else { CONTINUATION }
+    }
+
+    // incomplete_allowed - конструкцию, оборванную концом ввода, можно дочитать
+    fn parse(source: &str, incomplete_allowed: bool) -> Parsed {
+        let lexer = Lexer::new(source.to_owned());
+        let (tokens, errors) = lexer.scan_all();
+        if !errors.is_empty() {
+            return Parsed::Errors(errors.iter().map(Diagnostic::from).collect());
+        }
+        match Parser::new(tokens, lexer.end_span()).parse_program() {
+            Ok(program) => Parsed::Complete(program),
+            Err(error) if error.at_end() && incomplete_allowed => Parsed::Incomplete,
+            Err(error) => Parsed::Errors(vec![Diagnostic::from(&error)]),
+        }
+    }
+
+    fn checked(&self, source: &str) -> Result<(Program, Types), Vec<Diagnostic>> {
+        let program = match Repl::parse(source, false) {
+            Parsed::Complete(program) => program,
+            Parsed::Errors(errors) => return Err(errors),
+            Parsed::Incomplete => unreachable!(),
+        };
+        let (types, _) = check_with(&program, self.globals.clone())?;
+        Ok((program, types))
+    }
+
+    /// Обработка одной строки; false - сеанс закончен
+    pub fn line(&mut self, line: &str, out: &mut dyn Write) -> bool {
+        let trimmed = line.trim();
+        if self.pending.is_empty() && trimmed.starts_with(':') {
+            return self.meta(trimmed, out);
+        }
+        let force = trimmed.is_empty();
+        if force && self.pending.is_empty() {
+            return true;
+        }
+        self.pending.push_str(line);
+        self.pending.push('\n');
+        let source = self.pending.to_owned();
+        let program = match Repl::parse(&source, !force) {
+            Parsed::Incomplete => return true,
+            Parsed::Complete(program) => program,
+            Parsed::Errors(errors) => {
+                self.pending.clear();
+                report(&errors, &source, out);
+                return true;
+            }
+        };
+        self.pending.clear();
+        self.last = source.to_owned();
+        let globals = match check_with(&program, self.globals.clone()) {
+            Ok((_, globals)) => globals,
+            Err(errors) => {
+                report(&errors, &source, out);
+                return true;
+            }
+        };
+        // print выводит сразу, не дожидаясь конца ввода: длинный цикл виден по ходу
+        let result = self.interpreter.run(&program, out);
+        // переменную из одной ветви if проверка не считает объявленной, а после
+        // ошибки выполнения часть объявленных так и не получила значения
+        self.interpreter.retain_globals(|name| globals.contains_key(name));
+        let scope = &self.interpreter.scopes()[0];
+        self.globals = globals.into_iter().filter(|(name, _)| scope.contains_key(name)).collect();
+        if let Err(error) = result {
+            report(&[Diagnostic::from(&error)], &source, out);
+        }
+        true
+    }
+
+    fn meta(&mut self, command: &str, out: &mut dyn Write) -> bool {
+        let (name, argument) = match command.find(char::is_whitespace) {
+            Some(i) => (&command[..i], command[i..].trim()),
+            None => (command, ""),
+        };
+        let source = if argument.is_empty() { self.last.to_owned() } else { argument.to_owned() };
+        let _ = match name {
+            ":quit" | ":q" => return false,
+            ":help" => write!(out, "{}", HELP),
+            ":env" => {
+                for (name, value) in &self.interpreter.scopes()[0] {
+                    let _ = writeln!(out, "{}: {} = {}", name, value.value_type(), value);
+                }
+                Ok(())
+            }
+            ":tokens" => {
+                let lexer = Lexer::new(source.to_owned());
+                let (tokens, errors) = lexer.scan_all();
+                for token in tokens {
+                    let _ = match token.tag() {
+                        (tag, Some(attribute)) => writeln!(out, "{}\t{:?}\t{}", token.span(), tag, attribute),
+                        (tag, None) => writeln!(out, "{}\t{:?}", token.span(), tag),
+                    };
+                }
+                report(&errors.iter().map(Diagnostic::from).collect::<Vec<_>>(), &source, out);
+                Ok(())
+            }
+            ":ast" => match Repl::parse(&source, false) {
+                Parsed::Complete(program) => write!(out, "{}", program),
+                Parsed::Errors(errors) => {
+                    report(&errors, &source, out);
+                    Ok(())
+                }
+                Parsed::Incomplete => unreachable!(),
+            },
+            ":ir" => match self.checked(&source) {
+                Ok((program, types)) => write!(out, "{}", ir::generate(&program, &types)),
+                Err(errors) => {
+                    report(&errors, &source, out);
+                    Ok(())
+                }
+            },
+            _ => writeln!(out, "Unknown command {}; try :help", name),
+        };
+        true
+    }
+
+    /// Цикл чтения до конца ввода или :quit
+    pub fn run<R: BufRead>(&mut self, input: R, out: &mut dyn Write) {
+        let _ = write!(out, "{}", self.prompt());
+        let _ = out.flush();
+        for line in input.lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            if !self.line(&line, out) {
+                return;
+            }
+            let _ = write!(out, "{}", self.prompt());
+            let _ = out.flush();
+        }
+        // незаконченный ввод в конце файла
+        if !self.pending.is_empty() {
+            self.line("", out);
+        }
+        let _ = writeln!(out);
+    }
+}
+
+fn report(diagnostics: &[Diagnostic], source: &str, out: &mut dyn Write) {
+    let _ = write!(out, "{}", render_all(diagnostics, &SourceMap::new(source)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // вывод сеанса без приглашений
+    fn session(lines: &[&str]) -> Vec<String> {
+        let mut repl = Repl::new();
+        let mut result = Vec::new();
+        for line in lines {
+            let mut out = Vec::new();
+            repl.line(line, &mut out);
+            result.push(String::from_utf8(out).unwrap());
+        }
+        result
+    }
+
+    #[test]
+    fn persistent_env_test() {
+        let out = session(&["x = 2;", "y = x * 3; print y;", "{ z = 1; x = x + z; }", "print x + y;", ":env"]);
+        assert_eq!(out, vec!["", "6\n", "", "9\n", "x: int = 3\ny: int = 6\n"]);
+    }
+
+    #[test]
+    fn one_branch_assignment_test() {
+        let out = session(&["if true then a = 1;", "print a;", ":env", "if true then b = 1; else b = 2;", ":env"]);
+        assert_eq!(out[0], "");
+        assert!(out[1].starts_with("error: Undefined variable 'a'"));
+        assert_eq!(out[2], "");
+        assert_eq!(out[4], "b: int = 1\n");
+        // после ошибки выполнения остаются только получившие значение переменные
+        let out = session(&["x = 1; print 1 / 0; y = 2;", "print y;", ":env"]);
+        assert!(out[1].starts_with("error: Undefined variable 'y'"));
+        assert_eq!(out[2], "x: int = 1\n");
+    }
+
+    // принимает три строки, дальше сообщает об ошибке записи
+    struct ThreeLines(Vec<u8>);
+
+    impl Write for ThreeLines {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if self.0.iter().filter(|x| **x == b'\n').count() >= 3 {
+                return Err(std::io::Error::other("output is full"));
+            }
+            self.0.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn streaming_output_test() {
+        // бесконечный цикл останавливает только ошибка записи, значит print пишет сразу
+        let mut out = ThreeLines(Vec::new());
+        Repl::new().line("for (i = 0; true; i = i + 1) print i;", &mut out);
+        assert_eq!(String::from_utf8(out.0).unwrap(), "0\n1\n2\n");
+    }
+
+    #[test]
+    fn multiline_test() {
+        let mut repl = Repl::new();
+        let mut out = Vec::new();
+        assert!(repl.line("for (i = 0; i < 2;", &mut out));
+        assert_eq!(repl.prompt(), CONTINUATION);
+        repl.line("     i = i + 1) {", &mut out);
+        repl.line("  print i;", &mut out);
+        assert!(out.is_empty());
+        repl.line("}", &mut out);
+        assert_eq!(String::from_utf8(out).unwrap(), "0\n1\n");
+        assert_eq!(repl.prompt(), PROMPT);
+
+        // пустая строка завершает незаконченный ввод с ошибкой
+        let out = session(&["if true then", ""]);
+        assert_eq!(out[0], "");
+        assert!(out[1].starts_with("error: Expected statement, found end of input"));
+    }
+
+    #[test]
+    fn errors_test() {
+        let out = session(&["x = 1;", "x = true;", "print 1 / 0; ", "print x;", "y = ;"]);
+        assert!(out[1].starts_with("error: Mismatched types: 'x' is int, assigned bool"));
+        assert!(out[2].starts_with("error: Division by zero"));
+        assert_eq!(out[3], "1\n");
+        assert!(out[4].starts_with("error: Expected expression, found ';'"));
+    }
+
+    #[test]
+    fn meta_commands_test() {
+        let out = session(&[":tokens a = 1;", "b = 2; print b;", ":ast", ":ir print b + 1;", ":ast x = ;", ":nope"]);
+        assert_eq!(out[0], "1:1-1:2\tID\ta\n1:3-1:4\tASSIGN\n1:5-1:6\tNUMBER\t1\n1:6-1:7\tSEMICOLON\n");
+        assert_eq!(out[2], "(= b 2)\n(print b)\n");
+        assert_eq!(out[3], "    t1 = b + 1\n    print t1\n");
+        assert!(out[4].starts_with("error: Expected expression"));
+        assert_eq!(out[5], "Unknown command :nope; try :help\n");
+        assert!(session(&[":ir print c;"])[0].starts_with("error: Undefined variable 'c'"));
+    }
+
+    #[test]
+    fn run_test() {
+        let input = "x = 40;\nif x == 40 then {\nprint x + 2;\n}\n:quit\nprint 0;\n";
+        let mut out = Vec::new();
+        Repl::new().run(input.as_bytes(), &mut out);
+        assert_eq!(String::from_utf8(out).unwrap(), "> > ... ... 42\n> ");
+        let mut out = Vec::new();
+        Repl::new().run("print (1 +".as_bytes(), &mut out);
+        assert!(String::from_utf8(out).unwrap().starts_with("> ... error: Expected expression, found end of input"));
+    }
+}
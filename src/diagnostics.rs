@@ -0,0 +1,305 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fmt::Write;
+
+use crate::span::{Position, SourceMap, Span};
+
+///Важность сообщения
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+            Severity::Help => "help",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+///Подпись к участку текста; основной участок подчёркивается ^, дополнительный - -
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    pub primary: bool,
+}
+
+///Предлагаемая замена участка текста
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub span: Span,
+    pub replacement: String,
+    pub message: String,
+}
+
+///Сообщение об ошибке или предупреждение с привязкой к исходному тексту
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: &str) -> Self {
+        Diagnostic { severity, message: message.to_owned(), labels: Vec::new(), notes: Vec::new(), suggestions: Vec::new() }
+    }
+
+    pub fn error(message: &str) -> Self {
+        Diagnostic::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: &str) -> Self {
+        Diagnostic::new(Severity::Warning, message)
+    }
+
+    pub fn with_label(mut self, span: Span, message: &str) -> Self {
+        self.labels.push(Label { span, message: message.to_owned(), primary: true });
+        self
+    }
+
+    pub fn with_secondary_label(mut self, span: Span, message: &str) -> Self {
+        self.labels.push(Label { span, message: message.to_owned(), primary: false });
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.notes.push(note.to_owned());
+        self
+    }
+
+    pub fn with_suggestion(mut self, span: Span, replacement: &str, message: &str) -> Self {
+        self.suggestions.push(Suggestion { span, replacement: replacement.to_owned(), message: message.to_owned() });
+        self
+    }
+
+    /// Участок первой основной подписи, по нему сообщение сортируется и показывается в заголовке
+    pub fn primary_span(&self) -> Option<Span> {
+        self.labels.iter().find(|x| x.primary).or_else(|| self.labels.first()).map(|x| x.span)
+    }
+
+    /// Текстовый вид в духе rustc:
+    ///
+    /// ```text
+    /// error: Expected digit
+    ///  --> 1:5
+    ///   |
+    /// 1 | 2+3--4
+    ///   |     ^ found '-'
+    ///   |
+    ///   = note: ...
+    /// ```
+    pub fn render(&self, map: &SourceMap) -> String {
+        let mut result = format!("{}: {}\n", self.severity, self.message);
+        let lines: BTreeSet<usize> = self.labels.iter()
+            .flat_map(|x| x.span.start.line..=x.span.end.line)
+            .collect();
+        let width = lines.iter().chain(self.suggestions.iter().map(|x| &x.span.start.line))
+            .max().map_or(0, |x| x.to_string().len());
+        let gutter = " ".repeat(width);
+        if let Some(span) = self.primary_span() {
+            writeln!(result, "{}--> {}", gutter, span.start).unwrap();
+        }
+        if !lines.is_empty() {
+            writeln!(result, "{} |", gutter).unwrap();
+        }
+        let mut previous = None;
+        for &line in &lines {
+            if previous.is_some_and(|x| x + 1 < line) {
+                writeln!(result, "...").unwrap();
+            }
+            previous = Some(line);
+            let text = map.line(line).unwrap_or("");
+            writeln!(result, "{:>width$} | {}", line, text, width = width).unwrap();
+            for label in self.labels.iter().filter(|x| x.span.start.line <= line && line <= x.span.end.line) {
+                let (from, to) = columns(&label.span, line, text);
+                let mark = if label.primary { "^" } else { "-" };
+                let mut underline = format!("{} | {}{}", gutter, " ".repeat(from - 1), mark.repeat(to - from));
+                if line == label.span.end.line && !label.message.is_empty() {
+                    write!(underline, " {}", label.message).unwrap();
+                }
+                writeln!(result, "{}", underline).unwrap();
+            }
+        }
+        if !self.notes.is_empty() || !self.suggestions.is_empty() {
+            if !lines.is_empty() {
+                writeln!(result, "{} |", gutter).unwrap();
+            }
+            for note in &self.notes {
+                writeln!(result, "{} = note: {}", gutter, note).unwrap();
+            }
+            for suggestion in &self.suggestions {
+                writeln!(result, "{} = help: {}: `{}`", gutter, suggestion.message, apply(suggestion, map)).unwrap();
+            }
+        }
+        result
+    }
+
+    /// Однострочный JSON без внешних зависимостей
+    pub fn render_json(&self, map: &SourceMap) -> String {
+        let labels: Vec<String> = self.labels.iter()
+            .map(|x| format!("{{\"span\":{},\"message\":{},\"primary\":{}}}", span_json(&x.span), string_json(&x.message), x.primary))
+            .collect();
+        let notes: Vec<String> = self.notes.iter().map(|x| string_json(x)).collect();
+        let suggestions: Vec<String> = self.suggestions.iter()
+            .map(|x| format!("{{\"span\":{},\"replacement\":{},\"message\":{},\"line\":{}}}",
+                             span_json(&x.span), string_json(&x.replacement), string_json(&x.message), string_json(&apply(x, map))))
+            .collect();
+        format!("{{\"severity\":{},\"message\":{},\"labels\":[{}],\"notes\":[{}],\"suggestions\":[{}]}}",
+                string_json(&self.severity.to_string()), string_json(&self.message),
+                labels.join(","), notes.join(","), suggestions.join(","))
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.primary_span() {
+            Some(span) => write!(f, "{}: {} at {}", self.severity, self.message, span.start),
+            None => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+/// Все сообщения подряд в порядке позиций
+pub fn render_all(diagnostics: &[Diagnostic], map: &SourceMap) -> String {
+    let mut sorted: Vec<&Diagnostic> = diagnostics.iter().collect();
+    sorted.sort_by_key(|x| x.primary_span().map(|x| x.start));
+    sorted.iter().map(|x| x.render(map)).collect::<Vec<_>>().join("\n")
+}
+
+/// JSON-массив сообщений
+pub fn render_all_json(diagnostics: &[Diagnostic], map: &SourceMap) -> String {
+    let items: Vec<String> = diagnostics.iter().map(|x| x.render_json(map)).collect();
+    format!("[{}]", items.join(","))
+}
+
+// столбцы [from, to) подчёркивания участка в строке line; пустой участок подчёркивается одним символом
+fn columns(span: &Span, line: usize, text: &str) -> (usize, usize) {
+    let from = if span.start.line == line { span.start.column } else { 1 };
+    let to = if span.end.line == line { span.end.column } else { text.chars().count() + 1 };
+    (from, to.max(from + 1))
+}
+
+// строка с первой строкой участка после замены
+fn apply(suggestion: &Suggestion, map: &SourceMap) -> String {
+    let text = map.line(suggestion.span.start.line).unwrap_or("");
+    let start = suggestion.span.start.column - 1;
+    let end = if suggestion.span.end.line == suggestion.span.start.line {
+        suggestion.span.end.column - 1
+    } else {
+        text.chars().count()
+    };
+    let before: String = text.chars().take(start).collect();
+    let after: String = text.chars().skip(end).collect();
+    format!("{}{}{}", before, suggestion.replacement, after)
+}
+
+fn position_json(position: &Position) -> String {
+    format!("{{\"line\":{},\"column\":{},\"offset\":{}}}", position.line, position.column, position.offset)
+}
+
+fn span_json(span: &Span) -> String {
+    format!("{{\"start\":{},\"end\":{}}}", position_json(&span.start), position_json(&span.end))
+}
+
+fn string_json(value: &str) -> String {
+    let mut result = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => result += "\\\"",
+            '\\' => result += "\\\\",
+            '\n' => result += "\\n",
+            '\r' => result += "\\r",
+            '\t' => result += "\\t",
+            c if (c as u32) < 0x20 => write!(result, "\\u{:04x}", c as u32).unwrap(),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_test() {
+        let map = SourceMap::new("a = 1\nb = a +* 2\n");
+        let diagnostic = Diagnostic::error("Unexpected '*'")
+            .with_label(map.span(13, 14), "expected operand")
+            .with_secondary_label(map.span(12, 13), "after this operator")
+            .with_note("operators must be separated by operands")
+            .with_suggestion(map.span(13, 14), "", "remove the extra operator");
+        let expected = "\
+error: Unexpected '*'
+ --> 2:8
+  |
+2 | b = a +* 2
+  |        ^ expected operand
+  |       - after this operator
+  |
+  = note: operators must be separated by operands
+  = help: remove the extra operator: `b = a + 2`
+";
+        assert_eq!(diagnostic.render(&map), expected);
+        assert_eq!(diagnostic.to_string(), "error: Unexpected '*' at 2:8");
+    }
+
+    #[test]
+    fn multiline_test() {
+        let source = (1..=12).map(|x| format!("line{}", x)).collect::<Vec<_>>().join("\n");
+        let map = SourceMap::new(&source);
+        // line9: 48..53, line10: 54..60
+        let diagnostic = Diagnostic::warning("Long block")
+            .with_label(map.span(50, 57), "")
+            .with_secondary_label(map.span(0, 0), "starts here");
+        let expected = "\
+warning: Long block
+  --> 9:3
+   |
+ 1 | line1
+   | - starts here
+...
+ 9 | line9
+   |   ^^^
+10 | line10
+   | ^^^
+";
+        assert_eq!(diagnostic.render(&map), expected);
+        assert_eq!(Diagnostic::error("No source").render(&map), "error: No source\n");
+    }
+
+    #[test]
+    fn json_test() {
+        let map = SourceMap::new("x = \"y\"");
+        let diagnostic = Diagnostic::error("Bad \"quote\"")
+            .with_label(map.span(4, 7), "here")
+            .with_note("line\nbreak");
+        assert_eq!(diagnostic.render_json(&map), concat!(
+            r#"{"severity":"error","message":"Bad \"quote\"","labels":[{"span":{"start":{"line":1,"column":5,"offset":4},"#,
+            r#""end":{"line":1,"column":8,"offset":7}},"message":"here","primary":true}],"notes":["line\nbreak"],"suggestions":[]}"#));
+        assert_eq!(render_all_json(&[], &map), "[]");
+    }
+
+    #[test]
+    fn render_all_test() {
+        let map = SourceMap::new("ab");
+        let second = Diagnostic::error("second").with_label(map.span(1, 2), "");
+        let first = Diagnostic::warning("first").with_label(map.span(0, 1), "");
+        let rendered = render_all(&[second, first], &map);
+        assert!(rendered.starts_with("warning: first"));
+        assert!(rendered.contains("\n\nerror: second"));
+    }
+}
@@ -0,0 +1,95 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::diagnostics::Diagnostic;
+use crate::span::Span;
+
+///Лексическая ошибка; после неё лексер продолжает разбор со следующего допустимого токена
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexError {
+    UnterminatedComment { span: Span },
+    UnterminatedString { span: Span },
+    /// span покрывает всю пропущенную цепочку недопустимых символов
+    InvalidCharacter { character: char, span: Span },
+    NumberOverflow { literal: String, span: Span },
+    /// Лексема не помещается в половину буфера
+    LexemeTooLong { span: Span },
+}
+
+impl LexError {
+    pub fn span(&self) -> Span {
+        match self {
+            LexError::UnterminatedComment { span }
+            | LexError::UnterminatedString { span }
+            | LexError::InvalidCharacter { span, .. }
+            | LexError::NumberOverflow { span, .. }
+            | LexError::LexemeTooLong { span } => *span,
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexError::UnterminatedComment { span } => write!(f, "Unterminated comment at {}", span.start),
+            LexError::UnterminatedString { span } => write!(f, "Unterminated string at {}", span.start),
+            LexError::InvalidCharacter { character, span } => write!(f, "Invalid character {:?} at {}", character, span.start),
+            LexError::NumberOverflow { literal, span } => write!(f, "Number {} is too large at {}", literal, span.start),
+            LexError::LexemeTooLong { span } => write!(f, "Lexeme at {} is longer than buffer", span.start),
+        }
+    }
+}
+
+impl Error for LexError {}
+
+impl From<&LexError> for Diagnostic {
+    fn from(error: &LexError) -> Self {
+        match error {
+            LexError::UnterminatedComment { span } => Diagnostic::error("Unterminated comment")
+                .with_label(*span, "comment is never closed")
+                .with_suggestion(Span::point(span.end), "*/", "close the comment"),
+            LexError::UnterminatedString { span } => Diagnostic::error("Unterminated string")
+                .with_label(*span, "string is never closed")
+                .with_suggestion(Span::point(span.end), "\"", "close the string"),
+            LexError::InvalidCharacter { character, span } => Diagnostic::error(&format!("Invalid character {:?}", character))
+                .with_label(*span, "skipped"),
+            LexError::NumberOverflow { literal, span } => Diagnostic::error(&format!("Number {} is too large", literal))
+                .with_label(*span, "does not fit into an integer"),
+            LexError::LexemeTooLong { span } => Diagnostic::error("Lexeme is longer than buffer")
+                .with_label(*span, "discarded")
+                .with_note("a lexeme must fit into a half of the input buffer"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::SourceMap;
+
+    #[test]
+    fn display_test() {
+        let map = SourceMap::new("a = 1\n  ?? b");
+        let error = LexError::InvalidCharacter { character: '?', span: map.span(8, 10) };
+        assert_eq!(error.to_string(), "Invalid character '?' at 2:3");
+        assert_eq!(error.span().text(map.source()), "??");
+        let error = LexError::NumberOverflow { literal: "99999999999".to_owned(), span: map.span(0, 11) };
+        assert_eq!(error.to_string(), "Number 99999999999 is too large at 1:1");
+    }
+
+    #[test]
+    fn diagnostic_test() {
+        let map = SourceMap::new("s = \"abc");
+        let error = LexError::UnterminatedString { span: map.span(4, 8) };
+        let expected = "\
+error: Unterminated string
+ --> 1:5
+  |
+1 | s = \"abc
+  |     ^^^^ string is never closed
+  |
+  = help: close the string: `s = \"abc\"`
+";
+        assert_eq!(Diagnostic::from(&error).render(&map), expected);
+    }
+}
@@ -1,6 +1,6 @@
-mod part2;
-mod part3;
-mod part4;
-fn main() {
-    println!("Hello, world!");
-}
+pub mod part2;
+pub mod part3;
+pub mod part4;
+pub mod span;
+pub mod lex_error;
+pub mod diagnostics;
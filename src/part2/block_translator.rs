@@ -0,0 +1,338 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::diagnostics::Diagnostic;
+use crate::span::{Position, Span};
+
+use super::scope_analysis::{ScopeAnalyzer, SymbolWarning};
+use super::symbols_table::{RedeclarationError, Symbol, SymbolKind};
+
+//Блочный язык из раздела 2.7:
+//  program -> block
+//  block   -> { decls stmts }
+//  decls   -> decls decl | ε
+//  decl    -> type id ;
+//  stmts   -> stmts stmt | ε
+//  stmt    -> block | id ;
+//Транслятор убирает объявления и заменяет каждое использование на name:type
+//по ближайшему объявлению: { int x; char y; { bool y; x; y; } x; y; }
+//переводится в { { x:int; y:bool; } x:int; y:char; }
+
+/// Базовые типы и их размеры
+pub const TYPES: [(&str, usize); 4] = [("int", 4), ("char", 1), ("bool", 1), ("float", 8)];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockError {
+    InvalidCharacter { character: char, span: Span },
+    /// found - "end of input" в конце входа
+    UnexpectedToken { expected: String, found: String, span: Span },
+    DeclarationAfterStatement { span: Span },
+    Undeclared { name: String, span: Span },
+    Redeclared(RedeclarationError),
+}
+
+impl BlockError {
+    pub fn span(&self) -> Span {
+        match self {
+            BlockError::InvalidCharacter { span, .. }
+            | BlockError::UnexpectedToken { span, .. }
+            | BlockError::DeclarationAfterStatement { span }
+            | BlockError::Undeclared { span, .. } => *span,
+            BlockError::Redeclared(error) => error.span,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            BlockError::InvalidCharacter { character, .. } => format!("Invalid character {:?}", character),
+            BlockError::UnexpectedToken { expected, found, .. } => format!("Expected {}, found {}", expected, found),
+            BlockError::DeclarationAfterStatement { .. } => "Declarations must precede statements".to_owned(),
+            BlockError::Undeclared { name, .. } => format!("'{}' is not declared", name),
+            BlockError::Redeclared(error) => format!("'{}' is already declared in this scope", error.name),
+        }
+    }
+}
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message(), self.span().start)
+    }
+}
+
+impl Error for BlockError {}
+
+impl From<&BlockError> for Diagnostic {
+    fn from(error: &BlockError) -> Self {
+        if let BlockError::Redeclared(error) = error {
+            return Diagnostic::from(error);
+        }
+        let diagnostic = Diagnostic::error(&error.message());
+        match error {
+            BlockError::DeclarationAfterStatement { span } => diagnostic.with_label(*span, "")
+                .with_note("move the declaration to the start of the block"),
+            BlockError::Undeclared { span, .. } => diagnostic.with_label(*span, "not found in this scope"),
+            _ => diagnostic.with_label(error.span(), ""),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decl {
+    pub symbol_type: String,
+    pub name: String,
+    /// Участок имени
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stmt {
+    Block(Block),
+    Use { name: String, span: Span },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub decls: Vec<Decl>,
+    pub stmts: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Lexeme {
+    LeftBrace,
+    RightBrace,
+    Semicolon,
+    Type(String),
+    Id(String),
+    End,
+}
+
+impl fmt::Display for Lexeme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lexeme::LeftBrace => write!(f, "'{{'"),
+            Lexeme::RightBrace => write!(f, "'}}'"),
+            Lexeme::Semicolon => write!(f, "';'"),
+            Lexeme::Type(name) => write!(f, "type '{}'", name),
+            Lexeme::Id(name) => write!(f, "identifier '{}'", name),
+            Lexeme::End => write!(f, "end of input"),
+        }
+    }
+}
+
+fn scan(input: &str) -> Result<Vec<(Lexeme, Span)>, BlockError> {
+    let mut result = Vec::new();
+    let mut position = Position::default();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        let start = position;
+        position.advance(c);
+        let lexeme = match c {
+            c if c.is_whitespace() => continue,
+            '{' => Lexeme::LeftBrace,
+            '}' => Lexeme::RightBrace,
+            ';' => Lexeme::Semicolon,
+            c if c.is_alphabetic() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|x| x.is_alphanumeric() || *x == '_') {
+                    position.advance(c);
+                    word.push(c);
+                }
+                if TYPES.iter().any(|x| x.0 == word) { Lexeme::Type(word) } else { Lexeme::Id(word) }
+            }
+            _ => return Err(BlockError::InvalidCharacter { character: c, span: Span::new(start, position) }),
+        };
+        result.push((lexeme, Span::new(start, position)));
+    }
+    result.push((Lexeme::End, Span::point(position)));
+    Ok(result)
+}
+
+///Разбор рекурсивным спуском по грамматике выше
+struct Parser {
+    lexemes: Vec<(Lexeme, Span)>,
+    index: usize,
+}
+
+impl Parser {
+    fn lookahead(&self) -> &(Lexeme, Span) {
+        &self.lexemes[self.index]
+    }
+
+    fn error(&self, expected: &str) -> BlockError {
+        let (found, span) = self.lookahead();
+        BlockError::UnexpectedToken { expected: expected.to_owned(), found: found.to_string(), span: *span }
+    }
+
+    fn matcher(&mut self, lexeme: Lexeme) -> Result<(), BlockError> {
+        if self.lookahead().0 == lexeme {
+            self.index += 1;
+            Ok(())
+        } else {
+            Err(self.error(&lexeme.to_string()))
+        }
+    }
+
+    fn id(&mut self) -> Result<(String, Span), BlockError> {
+        match self.lookahead().clone() {
+            (Lexeme::Id(name), span) => {
+                self.index += 1;
+                Ok((name, span))
+            }
+            _ => Err(self.error("identifier")),
+        }
+    }
+
+    fn program(&mut self) -> Result<Block, BlockError> {
+        let block = self.block()?;
+        self.matcher(Lexeme::End)?;
+        Ok(block)
+    }
+
+    fn block(&mut self) -> Result<Block, BlockError> {
+        self.matcher(Lexeme::LeftBrace)?;
+        let mut decls = Vec::new();
+        while let (Lexeme::Type(symbol_type), _) = self.lookahead().clone() {
+            self.index += 1;
+            let (name, span) = self.id()?;
+            self.matcher(Lexeme::Semicolon)?;
+            decls.push(Decl { symbol_type, name, span });
+        }
+        let mut stmts = Vec::new();
+        loop {
+            match self.lookahead().clone() {
+                (Lexeme::LeftBrace, _) => stmts.push(Stmt::Block(self.block()?)),
+                (Lexeme::Id(_), _) => {
+                    let (name, span) = self.id()?;
+                    self.matcher(Lexeme::Semicolon)?;
+                    stmts.push(Stmt::Use { name, span });
+                }
+                (Lexeme::Type(_), span) => return Err(BlockError::DeclarationAfterStatement { span }),
+                _ => break,
+            }
+        }
+        self.matcher(Lexeme::RightBrace)?;
+        Ok(Block { decls, stmts })
+    }
+}
+
+pub fn parse(input: &str) -> Result<Block, BlockError> {
+    Parser { lexemes: scan(input)?, index: 0 }.program()
+}
+
+///Результат трансляции и предупреждения таблицы символов
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Translation {
+    pub output: String,
+    pub warnings: Vec<SymbolWarning>,
+}
+
+/// Каждый блок - новая область Env, как top = new Env(top) в книге
+pub fn translate(block: &Block) -> Result<Translation, BlockError> {
+    let mut analyzer = ScopeAnalyzer::new();
+    let output = translate_block(block, &mut analyzer)?;
+    Ok(Translation { output, warnings: analyzer.finish() })
+}
+
+pub fn translate_source(input: &str) -> Result<Translation, BlockError> {
+    translate(&parse(input)?)
+}
+
+fn translate_block(block: &Block, analyzer: &mut ScopeAnalyzer) -> Result<String, BlockError> {
+    analyzer.enter_scope();
+    for decl in &block.decls {
+        let width = TYPES.iter().find(|x| x.0 == decl.symbol_type).map_or(0, |x| x.1);
+        let symbol = Symbol::new(&decl.name, SymbolKind::Variable, &decl.symbol_type, width, decl.span);
+        analyzer.declare(symbol).map_err(BlockError::Redeclared)?;
+    }
+    let mut result = "{".to_owned();
+    for stmt in &block.stmts {
+        let translated = match stmt {
+            Stmt::Block(inner) => translate_block(inner, analyzer)?,
+            Stmt::Use { name, span } => match analyzer.use_symbol(name, *span) {
+                Some(symbol) => format!("{}:{};", name, symbol.symbol_type),
+                None => return Err(BlockError::Undeclared { name: name.to_owned(), span: *span }),
+            },
+        };
+        result.push(' ');
+        result += &translated;
+    }
+    result += " }";
+    analyzer.exit_scope();
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::SourceMap;
+
+    fn output(input: &str) -> Result<String, String> {
+        translate_source(input).map(|x| x.output).map_err(|x| x.to_string())
+    }
+
+    #[test]
+    fn book_example_test() {
+        let translation = translate_source("{ int x; char y; { bool y; x; y; } x; y; }").unwrap();
+        assert_eq!(translation.output, "{ { x:int; y:bool; } x:int; y:char; }");
+        let warnings: Vec<String> = translation.warnings.iter().map(|x| x.to_string()).collect();
+        assert_eq!(warnings, vec!["'y' shadows an outer declaration at 1:25"]);
+    }
+
+    #[test]
+    fn nested_blocks_test() {
+        let input = "\
+{ int a; float b;
+  { char a; a; b;
+    { bool b; a; b; { a; } }
+    b;
+  }
+  { a; }
+  a; b;
+}";
+        assert_eq!(output(input), Ok("{ { a:char; b:float; { a:char; b:bool; { a:char; } } b:float; } { a:int; } a:int; b:float; }".to_owned()));
+        assert_eq!(output("{ }"), Ok("{ }".to_owned()));
+        assert_eq!(output("{{{}}{}}"), Ok("{ { { } } { } }".to_owned()));
+        // объявление из соседнего блока не видно
+        assert_eq!(output("{ { int x; x; } { x; } }"), Err("'x' is not declared at 1:19".to_owned()));
+    }
+
+    #[test]
+    fn parse_test() {
+        let block = parse("{ int x; { x; } }").unwrap();
+        assert_eq!(block.decls.iter().map(|x| (&x.symbol_type[..], &x.name[..])).collect::<Vec<_>>(), vec![("int", "x")]);
+        match &block.stmts[..] {
+            [Stmt::Block(inner)] => assert!(inner.decls.is_empty() && inner.stmts.len() == 1),
+            stmts => panic!("unexpected statements {:?}", stmts),
+        }
+    }
+
+    #[test]
+    fn errors_test() {
+        assert_eq!(output("{ int x; x }"), Err("Expected ';', found '}' at 1:12".to_owned()));
+        assert_eq!(output("{ int; }"), Err("Expected identifier, found ';' at 1:6".to_owned()));
+        assert_eq!(output("{ int char; }"), Err("Expected identifier, found type 'char' at 1:7".to_owned()));
+        assert_eq!(output("{ x; "), Err("Expected '}', found end of input at 1:6".to_owned()));
+        assert_eq!(output("{ } }"), Err("Expected end of input, found '}' at 1:5".to_owned()));
+        assert_eq!(output("x;"), Err("Expected '{', found identifier 'x' at 1:1".to_owned()));
+        assert_eq!(output("{ x = 1; }"), Err("Invalid character '=' at 1:5".to_owned()));
+        assert_eq!(output("{ int x; x; int y; }"), Err("Declarations must precede statements at 1:13".to_owned()));
+        assert_eq!(output("{ int x; char x; }"), Err("'x' is already declared in this scope at 1:15".to_owned()));
+
+        let input = "{ int x;\n  y; }";
+        let error = translate_source(input).unwrap_err();
+        assert_eq!(Diagnostic::from(&error).render(&SourceMap::new(input)), "\
+error: 'y' is not declared
+ --> 2:3
+  |
+2 |   y; }
+  |   ^ not found in this scope
+");
+    }
+
+    #[test]
+    fn warnings_test() {
+        let translation = translate_source("{ int x; int unused; { x; } }").unwrap();
+        let warnings: Vec<String> = translation.warnings.iter().map(|x| x.to_string()).collect();
+        assert_eq!(warnings, vec!["Unused variable 'unused' at 1:14"]);
+    }
+}
@@ -1,18 +1,13 @@
 use std::collections::HashMap;
-use std::ops::Deref;
 use core::fmt;
 use std::fmt::Debug;
 use std::str::Chars;
 
-#[derive(Debug, Ord, PartialOrd, PartialEq, Eq, Hash, Clone)]
-enum Tag {
-    NUM,
-    ID,
-    FALSE,
-    TRUE,
-    OP,
-    EOF
-}
+use crate::lex_error::LexError;
+use crate::span::{Position, Span};
+
+// Tag генерируется part3::lex_generator из lexer.l
+include!("lexer_tags.rs");
 
 impl PartialEq for dyn Token {
     fn eq(&self, other: &Self) -> bool {
@@ -20,9 +15,10 @@ impl PartialEq for dyn Token {
     }
 }
 
-trait Token {
+pub trait Token {
     fn tag(&self) -> &Tag;
     fn value(&self) -> String;
+    fn span(&self) -> Span;
 }
 
 impl Debug for dyn Token {
@@ -32,13 +28,16 @@ impl Debug for dyn Token {
 }
 
 #[derive(Debug, Ord, PartialOrd, PartialEq, Eq, Hash, Clone)]
-struct Word {
+pub struct Word {
     tag: Tag,
     word: String,
+    span: Span,
 }
 
 impl Word {
-    pub fn new(tag: Tag, word: String) -> Self { Word { tag, word } }
+    pub fn new(tag: Tag, word: String) -> Self { Word { tag, word, span: Span::default() } }
+
+    pub fn with_span(self, span: Span) -> Self { Word { span, ..self } }
 }
 
 impl Token for Word {
@@ -49,16 +48,23 @@ impl Token for Word {
     fn value(&self) -> String {
         self.word.to_owned()
     }
+
+    fn span(&self) -> Span {
+        self.span
+    }
 }
 
-#[derive(Debug, Ord, PartialOrd, PartialEq, Eq, Hash)]
-struct Num {
+#[derive(Debug, Ord, PartialOrd, PartialEq, Eq, Hash, Clone)]
+pub struct Num {
     tag: Tag,
     num: i32,
+    span: Span,
 }
 
 impl Num {
-    pub fn new(value: i32) -> Self { Num { tag: Tag::NUM, num: value } }
+    pub fn new(value: i32) -> Self { Num { tag: Tag::NUM, num: value, span: Span::default() } }
+
+    pub fn with_span(self, span: Span) -> Self { Num { span, ..self } }
 }
 
 impl Token for Num {
@@ -66,20 +72,32 @@ impl Token for Num {
         &self.tag
     }
     fn value(&self) -> String {
-        (self.num.to_string())
+        self.num.to_string()
+    }
+    fn span(&self) -> Span {
+        self.span
     }
 }
 
 
 type Words = HashMap<String, Word>;
-type Lines = HashMap<usize, Box<Vec<Box<dyn Token>>>>;
+type Lines = HashMap<usize, Vec<Box<dyn Token>>>;
 
-struct Lexer {
+pub struct Lexer {
+    /// Смещение в байтах следующего непрочитанного символа
     index: usize,
-    line: i32,
+    line: usize,
+    /// Смещение начала текущей строки - для номеров столбцов
+    line_start: usize,
     peek: char,
     words: Box<Words>,
-    lines: HashMap<usize, Box<Vec<Box<dyn Token>>>>,
+    lines: Lines,
+}
+
+impl Default for Lexer {
+    fn default() -> Self {
+        Lexer::new()
+    }
 }
 
 // ***********************************************
@@ -90,6 +108,7 @@ impl Lexer {
         let mut lexer = Lexer {
             index: 0,
             line: 1,
+            line_start: 0,
             peek: ' ',
             words: Default::default(),
             lines: Default::default(),
@@ -97,11 +116,11 @@ impl Lexer {
         lexer.words.insert("TRUE".to_owned(), Word::new(Tag::TRUE, "TRUE".to_owned()));
         lexer.words.insert("FALSE".to_owned(), Word::new(Tag::FALSE, "FALSE".to_owned()));
         lexer.words.insert("EOF".to_owned(), Word::new(Tag::EOF, "EOF".to_owned()));
-        lexer.lines.insert(1_usize, Box::new(Vec::new()));
+        lexer.lines.insert(1_usize, Vec::new());
         lexer
     }
 
-    fn reserve(&mut self, word: Word) {
+    pub fn reserve(&mut self, word: Word) {
         self.words.insert(word.value(), word);
     }
 
@@ -109,95 +128,131 @@ impl Lexer {
         if self.peek != '\0' {
             self.peek = input.next().unwrap_or('\0');
             if self.peek != '\0' {
-                self.index += 1;
+                self.index += self.peek.len_utf8();
             }
         }
         self.peek
     }
 
-    fn return_ptr(&mut self, input: &mut Chars) {
-        self.peek = input.next_back().unwrap_or('\0');
-        self.index -= 1;
+    //вернуть прочитанный символ; scan перечитает его с позиции index
+    fn return_ptr(&mut self) {
+        if self.peek != '\0' {
+            self.index -= self.peek.len_utf8();
+            self.peek = ' ';
+        }
     }
 
-    pub fn full_scan(&mut self, input: &str) -> &Lines {
-        while self.line != -1 {
-            let a = self.scan(input);
-            if &a.value()[..] == "EOF" {
-                break;
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.line_start = self.index;
+        self.lines.insert(self.line, Vec::new());
+    }
+
+    fn position(&self, input: &str, offset: usize) -> Position {
+        let column = input[self.line_start..offset].chars().count() + 1;
+        Position::new(self.line, column, offset)
+    }
+
+    // участок от start до index
+    fn span(&self, input: &str, start: Position) -> Span {
+        Span::new(start, self.position(input, self.index))
+    }
+
+    fn push<T: Token + Clone + 'static>(&mut self, token: T) -> Result<Box<dyn Token>, LexError> {
+        self.lines.entry(self.line).or_default().push(Box::new(token.clone()));
+        Ok(Box::new(token))
+    }
+
+    // символы, с которых может начинаться токен, пробел или комментарий
+    fn starts_token(c: char) -> bool {
+        c.is_whitespace() || c.is_alphanumeric() || "+-*();<>=!/".contains(c)
+    }
+
+    /// Ошибки не прерывают разбор: full_scan собирает их все
+    pub fn full_scan(&mut self, input: &str) -> Result<&Lines, Vec<LexError>> {
+        let (_, errors) = self.scan_all(input);
+        if errors.is_empty() {
+            Ok(&self.lines)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Все токены до EOF включительно и все лексические ошибки по пути
+    pub fn scan_all(&mut self, input: &str) -> (Vec<Box<dyn Token>>, Vec<LexError>) {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        loop {
+            match self.scan(input) {
+                Ok(token) => {
+                    let is_eof = token.tag() == &Tag::EOF;
+                    tokens.push(token);
+                    if is_eof {
+                        return (tokens, errors);
+                    }
+                }
+                Err(error) => errors.push(error),
             }
         }
-        &self.lines
     }
 
-    pub fn scan(&mut self, input: &str) -> Box<dyn Token> {
+    /// После ошибки лексер уже стоит за ошибочным участком (режим паники),
+    /// так что следующий вызов scan вернёт следующий токен
+    pub fn scan(&mut self, input: &str) -> Result<Box<dyn Token>, LexError> {
         let mut for_scan = input.split_at(self.index).1.chars();
-        //убрать пробелы и табуляции
+        //убрать пробелы, табуляции и комментарии
         loop {
             self.read_char_or_return_eof(&mut for_scan);
             match self.peek {
-                ' ' | '\t' => {}
+                ' ' | '\t' | '\r' => continue,
                 '\n' => {
-                    self.line += 1;
-                    self.lines.insert(self.line as usize, Box::new(Vec::new()));
+                    self.new_line();
+                    continue;
                 }
                 '\0' => {
-                    self.line = -1;
-                    self.lines.entry(self.line as usize).and_modify(|x|
-                        x.push(Box::new(Word::new(Tag::EOF, "EOF".to_string()))));
-                    return Box::new(Word::new(Tag::EOF, "EOF".to_string()));
+                    let span = Span::point(self.position(input, self.index));
+                    return self.push(Word::new(Tag::EOF, "EOF".to_string()).with_span(span));
                 }
+                '/' => {}
                 _ => break
             }
-        }
-        //Комментарии
-        if self.peek == '/' {
+            let start = self.position(input, self.index - 1);
             self.read_char_or_return_eof(&mut for_scan);
-            if self.peek == '/' {
-                loop {
-                    self.read_char_or_return_eof(&mut for_scan);
-                    if self.peek == '\0' {
-                        break;
-                    }
-                    if self.peek == '\n' {
-                        self.line += 1;
-                        self.lines.insert(self.line as usize, Box::new(Vec::new()));
-                        self.read_char_or_return_eof(&mut for_scan);
-                        break;
-                    }
-                }
-            }
-            if self.peek == '*' {
-                loop {
-                    self.read_char_or_return_eof(&mut for_scan);
-                    if self.peek == '\0' {
-                        panic!("Unclosed comment");
+            match self.peek {
+                '/' => loop {
+                    match self.read_char_or_return_eof(&mut for_scan) {
+                        '\0' => break,
+                        '\n' => {
+                            self.new_line();
+                            break;
+                        }
+                        _ => {}
                     }
-                    if self.peek == '*' {
-                        self.read_char_or_return_eof(&mut for_scan);
-                        match self.peek {
-                            '\0' => panic!("Unclosed comment"),
-                            '\\' => break,
+                },
+                '*' => {
+                    let mut star = false;
+                    loop {
+                        match self.read_char_or_return_eof(&mut for_scan) {
+                            '\0' => return Err(LexError::UnterminatedComment { span: self.span(input, start) }),
+                            '/' if star => break,
+                            '\n' => self.new_line(),
                             _ => {}
                         }
+                        star = self.peek == '*';
                     }
                 }
+                _ => {
+                    self.return_ptr();
+                    return Err(LexError::InvalidCharacter { character: '/', span: self.span(input, start) });
+                }
             }
         }
 
-
+        let start = self.position(input, self.index - self.peek.len_utf8());
         match self.peek{
-            '+' =>{
-                let word = Word::new(Tag::OP, "+".to_owned());
-                self.lines.entry(self.line as usize).and_modify(|x|
-                    x.push(Box::new(word.clone())));
-                return Box::new(word);
-            }
-            '-' =>{
-                let word = Word::new(Tag::OP, "-".to_owned());
-                self.lines.entry(self.line as usize).and_modify(|x|
-                    x.push(Box::new(word.clone())));
-                return Box::new(word);
+            '+' | '-' | '*' | '(' | ')' | ';' =>{
+                let word = Word::new(Tag::OP, self.peek.to_string()).with_span(self.span(input, start));
+                return self.push(word);
             }
             _ => {}
         }
@@ -207,72 +262,63 @@ impl Lexer {
             '<' => {
                 self.read_char_or_return_eof(&mut for_scan);
                 if self.peek == '=' {
-                    let word = Word::new(Tag::OP, "<=".to_owned());
-                    self.lines.entry(self.line as usize).and_modify(|x|
-                        x.push(Box::new(word.clone())));
-                    return Box::new(word);
+                    let word = Word::new(Tag::OP, "<=".to_owned()).with_span(self.span(input, start));
+                    return self.push(word);
                 } else {
-                    self.return_ptr(&mut for_scan);
-                    let word = Word::new(Tag::OP, "<".to_owned());
-                    self.lines.entry(self.line as usize).and_modify(|x|
-                        x.push(Box::new(word.clone())));
-                    return Box::new(word);
+                    self.return_ptr();
+                    let word = Word::new(Tag::OP, "<".to_owned()).with_span(self.span(input, start));
+                    return self.push(word);
                 }
             }
             '!' => {
                 self.read_char_or_return_eof(&mut for_scan);
                 if self.peek == '=' {
-                    self.return_ptr(&mut for_scan);
-                    let word = Word::new(Tag::OP, "!=".to_owned());
-                    self.lines.entry(self.line as usize).and_modify(|x|
-                        x.push(Box::new(word.clone())));
-                    return Box::new(word);
+                    let word = Word::new(Tag::OP, "!=".to_owned()).with_span(self.span(input, start));
+                    return self.push(word);
+                } else {
+                    self.return_ptr();
+                    return Err(LexError::InvalidCharacter { character: '!', span: self.span(input, start) });
                 }
             }
             '>' => {
                 self.read_char_or_return_eof(&mut for_scan);
                 if self.peek == '=' {
-                    let word = Word::new(Tag::OP, ">=".to_owned());
-                    self.lines.entry(self.line as usize).and_modify(|x|
-                        x.push(Box::new(word.clone())));
-                    return Box::new(word);
+                    let word = Word::new(Tag::OP, ">=".to_owned()).with_span(self.span(input, start));
+                    return self.push(word);
                 } else {
-                    self.return_ptr(&mut for_scan);
-                    let word = Word::new(Tag::OP, ">".to_owned());
-                    self.lines.entry(self.line as usize).and_modify(|x|
-                        x.push(Box::new(word.clone())));
-                    return Box::new(word);
+                    self.return_ptr();
+                    let word = Word::new(Tag::OP, ">".to_owned()).with_span(self.span(input, start));
+                    return self.push(word);
                 }
             }
             '=' => {
                 self.read_char_or_return_eof(&mut for_scan);
                 if self.peek == '=' {
-                    let word = Word::new(Tag::OP, "==".to_owned());
-                    self.lines.entry(self.line as usize).and_modify(|x|
-                        x.push(Box::new(word.clone())));
-                    return Box::new(word);
+                    let word = Word::new(Tag::OP, "==".to_owned()).with_span(self.span(input, start));
+                    return self.push(word);
                 } else {
-                    self.return_ptr(&mut for_scan);
-                    let word = Word::new(Tag::OP, "=".to_owned());
-                    self.lines.entry(self.line as usize).and_modify(|x|
-                        x.push(Box::new(word.clone())));
-                    return Box::new(word);
+                    self.return_ptr();
+                    let word = Word::new(Tag::OP, "=".to_owned()).with_span(self.span(input, start));
+                    return self.push(word);
                 }
             }
             _ => {}
         }
 
         //проверка на число
-        if char::is_digit(self.peek, 10) {
-            let mut result = 0;
+        if self.peek.is_ascii_digit() {
+            let mut result: Option<i32> = Some(0);
             loop {
-                result = 10 * result + char::to_digit(self.peek, 10).unwrap();
+                let digit = char::to_digit(self.peek, 10).unwrap_or(0) as i32;
+                result = result.and_then(|x| x.checked_mul(10)).and_then(|x| x.checked_add(digit));
                 self.read_char_or_return_eof(&mut for_scan);
-                if !char::is_digit(self.peek, 10) {
-                    self.return_ptr(&mut for_scan);
-                    self.lines.entry(self.line as usize).and_modify(|x|
-                        x.push(Box::new(Num::new(result as i32))));
-                    return Box::new(Num::new(result as i32));
+                if !self.peek.is_ascii_digit() {
+                    self.return_ptr();
+                    let span = self.span(input, start);
+                    return match result {
+                        Some(result) => self.push(Num::new(result).with_span(span)),
+                        None => Err(LexError::NumberOverflow { literal: span.text(input).to_owned(), span }),
+                    };
                 }
             }
         }
@@ -283,23 +329,32 @@ impl Lexer {
                 result.push(self.peek);
                 self.read_char_or_return_eof(&mut for_scan);
                 if !char::is_alphabetic(self.peek) {
-                    self.return_ptr(&mut for_scan);
-                    let word = Word::new(Tag::ID, result.clone());
-                    if self.words.get(&result).is_none() {
-                        self.words.insert(result.clone(), word.clone());
-                    }
-                    self.lines.entry(self.line as usize).and_modify(|x|
-                        x.push(Box::new(word.clone())));
-                    return Box::new(word);
+                    self.return_ptr();
+                    //зарезервированные слова (TRUE, FALSE) сохраняют свой тэг
+                    let word = self.words.entry(result.clone())
+                        .or_insert_with(|| Word::new(Tag::ID, result.clone()))
+                        .clone()
+                        .with_span(self.span(input, start));
+                    return self.push(word);
                 }
             }
         }
-        return Box::new(Word::new(Tag::EOF, "EOF".to_string()))
+        //недопустимые символы пропускаются до начала следующего токена
+        let character = self.peek;
+        loop {
+            self.read_char_or_return_eof(&mut for_scan);
+            if self.peek == '\0' || Lexer::starts_token(self.peek) {
+                self.return_ptr();
+                return Err(LexError::InvalidCharacter { character, span: self.span(input, start) });
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::ops::Deref;
+
     #[test]
     fn init_lexer_test() {
         use super::*;
@@ -318,8 +373,8 @@ mod tests {
         let mut l = Lexer::new();
 
         let input = String::from("a = 3\n") + "b =23\n" + "a + b\n" + "a >= 4\n" + "c!=a";
-        while l.line != -1 {
-            let a = l.scan(&input);
+        loop {
+            let a = l.scan(&input).unwrap();
             println!("get token: {:?}", a.deref());
             if &a.value()[..] == "EOF" {
                 break;
@@ -329,22 +384,80 @@ mod tests {
         let w1: Box<dyn Token> = Box::new(Word::new(Tag::ID, "b".to_owned()));
         let w2: Box<dyn Token> = Box::new(Word::new(Tag::ID, "=".to_owned()));
         let w3: Box<dyn Token> = Box::new(Word::new(Tag::ID, "23".to_owned()));
-        assert_eq!(l.lines.get(&2_usize).unwrap(),
-                   &Box::new(vec![w1, w2, w3]));
+        assert_eq!(l.lines.get(&2_usize).unwrap(), &vec![w1, w2, w3]);
         l.lines.iter().for_each(|x| println!("{:?}", x));
     }
 
     #[test]
-    #[should_panic(expected = "Unclosed comment")]
-    fn scan_string_panic_test() {
+    fn scan_unclosed_comment_test() {
         use super::*;
+        use crate::span::SourceMap;
         let mut l = Lexer::new();
-        while l.line != -1 {
-            let a = l.scan("123 234  hello\n now be //    asdasdfasd\nas\n let 23 /*vasya");
-            ;
-            if &a.value()[..] == "EOF" {
+        let input = "123 234  hello\n now be //    asdasdfasd\nas\n let 23 /*vasya\n";
+        let (tokens, errors) = l.scan_all(input);
+        let values: Vec<String> = tokens.iter().map(|x| x.value()).collect();
+        assert_eq!(values, vec!["123", "234", "hello", "now", "be", "as", "let", "23", "EOF"]);
+        let map = SourceMap::new(input);
+        assert_eq!(errors, vec![LexError::UnterminatedComment { span: map.span(51, 59) }]);
+        assert_eq!(errors[0].to_string(), "Unterminated comment at 4:9");
+    }
+
+    #[test]
+    fn scan_comments_test() {
+        use super::*;
+        let mut l = Lexer::new();
+        let (tokens, errors) = l.scan_all("a /* b ** c\n */ + // d\n  /**/c");
+        let values: Vec<String> = tokens.iter().map(|x| x.value()).collect();
+        assert_eq!(values, vec!["a", "+", "c", "EOF"]);
+        assert!(errors.is_empty());
+        assert_eq!(tokens[2].span().start, Position::new(3, 7, 29));
+    }
+
+    #[test]
+    fn scan_recovery_test() {
+        use super::*;
+        let mut l = Lexer::new();
+        let input = "a = 99999999999 + b\n@#$ c ! d / e";
+        let (tokens, errors) = l.scan_all(input);
+        let values: Vec<String> = tokens.iter().map(|x| x.value()).collect();
+        assert_eq!(values, vec!["a", "=", "+", "b", "c", "d", "e", "EOF"]);
+        let messages: Vec<String> = errors.iter().map(|x| x.to_string()).collect();
+        assert_eq!(messages, vec![
+            "Number 99999999999 is too large at 1:5",
+            "Invalid character '@' at 2:1",
+            "Invalid character '!' at 2:7",
+            "Invalid character '/' at 2:11",
+        ]);
+        assert_eq!(errors[1].span().text(input), "@#$");
+        assert!(Lexer::new().full_scan(input).is_err());
+        assert_eq!(Lexer::new().full_scan("a + b").map(|x| x.len()), Ok(1));
+    }
+
+    #[test]
+    fn spans_test() {
+        use super::*;
+        use crate::span::SourceMap;
+        let input = "ab = 3\n  счёт >= 23 // c\nx";
+        let map = SourceMap::new(input);
+        let mut l = Lexer::new();
+        let mut tokens = Vec::new();
+        loop {
+            let token = l.scan(input).unwrap();
+            tokens.push((token.value(), token.span()));
+            if token.tag() == &Tag::EOF {
                 break;
             }
         }
+        let values: Vec<&str> = tokens.iter().map(|(value, _)| &value[..]).collect();
+        assert_eq!(values, vec!["ab", "=", "3", "счёт", ">=", "23", "x", "EOF"]);
+        for (value, span) in &tokens[..tokens.len() - 1] {
+            assert_eq!(span.text(input), value);
+            assert_eq!(*span, map.span(span.start.offset, span.end.offset));
+        }
+        assert_eq!(tokens[3].1, Span::new(Position::new(2, 3, 9), Position::new(2, 7, 17)));
+        assert_eq!(tokens[4].1.start, Position::new(2, 8, 18));
+        assert_eq!(tokens[6].1.start, Position::new(3, 1, 29));
+        assert_eq!(tokens[7].1, Span::point(Position::new(3, 2, 30)));
+        assert_eq!(l.line, 3);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,12 @@
+// Сгенерировано part3::lex_generator из lexer.l, не редактировать вручную.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Tag {
+    NUM,
+    TRUE,
+    FALSE,
+    ID,
+    OP,
+    EOF,
+}
@@ -0,0 +1,371 @@
+use std::fmt;
+
+use crate::span::{Position, Span};
+
+use super::simple_translator::{SimpleTranslator, TranslateError};
+
+//Одно дерево выражения в разных записях (упражнения к главе 2):
+//постфиксной, префиксной (польской), инфиксной с минимумом скобок,
+//полностью скобочной инфиксной и S-выражениях.
+//Разбор любой из записей возвращает то же дерево.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    pub fn from_symbol(symbol: char) -> Option<BinaryOp> {
+        match symbol {
+            '+' => Some(BinaryOp::Add),
+            '-' => Some(BinaryOp::Sub),
+            '*' => Some(BinaryOp::Mul),
+            '/' => Some(BinaryOp::Div),
+            '%' => Some(BinaryOp::Rem),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(self) -> char {
+        match self {
+            BinaryOp::Add => '+',
+            BinaryOp::Sub => '-',
+            BinaryOp::Mul => '*',
+            BinaryOp::Div => '/',
+            BinaryOp::Rem => '%',
+        }
+    }
+
+    /// Все операторы левоассоциативны
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Add | BinaryOp::Sub => 1,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    /// Число хранится так, как записано
+    Number(String),
+    Id(String),
+    Neg(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notation {
+    /// 2 3 + 4 neg *
+    Postfix,
+    /// * + 2 3 neg 4
+    Prefix,
+    /// (2 + 3) * -4
+    Infix,
+    /// ((2 + 3) * (-4))
+    FullyParenthesized,
+    /// (* (+ 2 3) (- 4))
+    SExpression,
+}
+
+impl Notation {
+    pub const ALL: [Notation; 5] =
+        [Notation::Postfix, Notation::Prefix, Notation::Infix, Notation::FullyParenthesized, Notation::SExpression];
+
+    pub fn render(self, expr: &Expr) -> String {
+        match self {
+            Notation::Postfix => expr.postfix(),
+            Notation::Prefix => expr.prefix(),
+            Notation::Infix => expr.infix(),
+            Notation::FullyParenthesized => expr.fully_parenthesized(),
+            Notation::SExpression => expr.s_expression(),
+        }
+    }
+
+    pub fn parse(self, input: &str) -> Result<Expr, TranslateError> {
+        match self {
+            Notation::Infix | Notation::FullyParenthesized => SimpleTranslator::from_string(input).tree(),
+            Notation::Postfix => Reader::new(input).postfix(),
+            Notation::Prefix => Reader::new(input).whole(Reader::prefix),
+            Notation::SExpression => Reader::new(input).whole(Reader::s_expression),
+        }
+    }
+}
+
+impl Expr {
+    pub fn binary(op: BinaryOp, left: Expr, right: Expr) -> Self {
+        Expr::Binary(op, Box::new(left), Box::new(right))
+    }
+
+    pub fn postfix(&self) -> String {
+        match self {
+            Expr::Number(x) | Expr::Id(x) => x.to_owned(),
+            Expr::Neg(x) => format!("{} neg", x.postfix()),
+            Expr::Binary(op, left, right) => format!("{} {} {}", left.postfix(), right.postfix(), op.symbol()),
+        }
+    }
+
+    pub fn prefix(&self) -> String {
+        match self {
+            Expr::Number(x) | Expr::Id(x) => x.to_owned(),
+            Expr::Neg(x) => format!("neg {}", x.prefix()),
+            Expr::Binary(op, left, right) => format!("{} {} {}", op.symbol(), left.prefix(), right.prefix()),
+        }
+    }
+
+    /// Скобки только там, где без них дерево было бы другим
+    pub fn infix(&self) -> String {
+        match self {
+            Expr::Number(x) | Expr::Id(x) => x.to_owned(),
+            Expr::Neg(x) => match **x {
+                Expr::Binary(..) => format!("-({})", x.infix()),
+                _ => format!("-{}", x.infix()),
+            },
+            Expr::Binary(op, left, right) => {
+                // левый операнд в скобках при меньшем приоритете, правый - и при равном
+                let wrap = |x: &Expr, right: bool| match x {
+                    Expr::Binary(inner, ..) if inner.precedence() < op.precedence()
+                        || right && inner.precedence() == op.precedence() => format!("({})", x.infix()),
+                    _ => x.infix(),
+                };
+                format!("{} {} {}", wrap(left, false), op.symbol(), wrap(right, true))
+            }
+        }
+    }
+
+    pub fn fully_parenthesized(&self) -> String {
+        match self {
+            Expr::Number(x) | Expr::Id(x) => x.to_owned(),
+            Expr::Neg(x) => format!("(-{})", x.fully_parenthesized()),
+            Expr::Binary(op, left, right) =>
+                format!("({} {} {})", left.fully_parenthesized(), op.symbol(), right.fully_parenthesized()),
+        }
+    }
+
+    /// Унарный минус - это (- x), как в Лиспе
+    pub fn s_expression(&self) -> String {
+        match self {
+            Expr::Number(x) | Expr::Id(x) => x.to_owned(),
+            Expr::Neg(x) => format!("(- {})", x.s_expression()),
+            Expr::Binary(op, left, right) =>
+                format!("({} {} {})", op.symbol(), left.s_expression(), right.s_expression()),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.infix())
+    }
+}
+
+fn is_operand(token: &str) -> bool {
+    let mut chars = token.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_digit() => chars.all(|x| x.is_ascii_digit()),
+        Some(c) if c.is_alphabetic() || c == '_' => token != "neg" && chars.all(|x| x.is_alphanumeric() || x == '_'),
+        _ => false,
+    }
+}
+
+fn operand(token: &str) -> Expr {
+    if token.starts_with(|x: char| x.is_ascii_digit()) {
+        Expr::Number(token.to_owned())
+    } else {
+        Expr::Id(token.to_owned())
+    }
+}
+
+fn binary_op(token: &str) -> Option<BinaryOp> {
+    let mut chars = token.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => BinaryOp::from_symbol(c),
+        _ => None,
+    }
+}
+
+///Разбор записей из лексем, разделённых пробелами; скобки - отдельные лексемы
+struct Reader {
+    tokens: Vec<(String, Span)>,
+    index: usize,
+    /// Позиция конца входа
+    end: Position,
+}
+
+impl Reader {
+    fn new(input: &str) -> Self {
+        let mut tokens: Vec<(String, Span)> = Vec::new();
+        let mut position = Position::default();
+        let mut current: Option<(String, Position)> = None;
+        for c in input.chars() {
+            let next = { let mut x = position; x.advance(c); x };
+            if c.is_whitespace() || c == '(' || c == ')' {
+                if let Some((text, start)) = current.take() {
+                    tokens.push((text, Span::new(start, position)));
+                }
+                if !c.is_whitespace() {
+                    tokens.push((c.to_string(), Span::new(position, next)));
+                }
+            } else {
+                current.get_or_insert_with(|| (String::new(), position)).0.push(c);
+            }
+            position = next;
+        }
+        if let Some((text, start)) = current.take() {
+            tokens.push((text, Span::new(start, position)));
+        }
+        Reader { tokens, index: 0, end: position }
+    }
+
+    fn next(&mut self) -> Option<(String, Span)> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn error(&self, message: &str, token: Option<&(String, Span)>) -> TranslateError {
+        let message = message.to_owned();
+        match token {
+            Some((text, span)) => TranslateError { message, span: *span, found: text.chars().next() },
+            None => TranslateError { message, span: Span::new(self.end, self.end), found: None },
+        }
+    }
+
+    // всё выражение; после него вход должен кончиться
+    fn whole(mut self, f: fn(&mut Reader) -> Result<Expr, TranslateError>) -> Result<Expr, TranslateError> {
+        let tree = f(&mut self)?;
+        match self.tokens.get(self.index) {
+            Some(token) => Err(self.error("Expected end of input", Some(token))),
+            None => Ok(tree),
+        }
+    }
+
+    fn postfix(mut self) -> Result<Expr, TranslateError> {
+        let mut stack = Vec::new();
+        while let Some(token) = self.next() {
+            let missing = |this: &Self| this.error(&format!("Missing operand for '{}'", token.0), Some(&token));
+            if is_operand(&token.0) {
+                stack.push(operand(&token.0));
+            } else if token.0 == "neg" {
+                let x = stack.pop().ok_or_else(|| missing(&self))?;
+                stack.push(Expr::Neg(Box::new(x)));
+            } else if let Some(op) = binary_op(&token.0) {
+                let right = stack.pop().ok_or_else(|| missing(&self))?;
+                let left = stack.pop().ok_or_else(|| missing(&self))?;
+                stack.push(Expr::binary(op, left, right));
+            } else {
+                return Err(self.error(&format!("Unexpected '{}'", token.0), Some(&token)));
+            }
+        }
+        match stack.len() {
+            0 => Err(self.error("Expected operand", None)),
+            1 => Ok(stack.pop().unwrap()),
+            _ => Err(self.error("Expected operator", None)),
+        }
+    }
+
+    fn prefix(&mut self) -> Result<Expr, TranslateError> {
+        let token = self.next();
+        match &token {
+            None => Err(self.error("Expected operand", None)),
+            Some((text, _)) if is_operand(text) => Ok(operand(text)),
+            Some((text, _)) if text == "neg" => Ok(Expr::Neg(Box::new(self.prefix()?))),
+            Some((text, _)) => match binary_op(text) {
+                Some(op) => {
+                    let left = self.prefix()?;
+                    Ok(Expr::binary(op, left, self.prefix()?))
+                }
+                None => Err(self.error(&format!("Unexpected '{}'", text), token.as_ref())),
+            },
+        }
+    }
+
+    fn s_expression(&mut self) -> Result<Expr, TranslateError> {
+        let token = self.next();
+        match &token {
+            None => Err(self.error("Expected operand", None)),
+            Some((text, _)) if is_operand(text) => Ok(operand(text)),
+            Some((text, _)) if text == "(" => {
+                let op = self.next();
+                let symbol = match &op {
+                    Some((text, _)) if binary_op(text).is_some() => text.to_owned(),
+                    _ => return Err(self.error("Expected operator", op.as_ref())),
+                };
+                let mut arguments = Vec::new();
+                loop {
+                    match self.tokens.get(self.index) {
+                        Some((text, _)) if text == ")" => break,
+                        None => return Err(self.error("Expected ')'", None)),
+                        _ => arguments.push(self.s_expression()?),
+                    }
+                }
+                self.index += 1;
+                match (binary_op(&symbol).unwrap(), arguments.len()) {
+                    (BinaryOp::Sub, 1) => Ok(Expr::Neg(Box::new(arguments.pop().unwrap()))),
+                    (op, 2) => {
+                        let right = arguments.pop().unwrap();
+                        Ok(Expr::binary(op, arguments.pop().unwrap(), right))
+                    }
+                    (_, count) => Err(self.error(
+                        &format!("Wrong number of arguments for '{}': {}", symbol, count), op.as_ref())),
+                }
+            }
+            Some((text, _)) => Err(self.error(&format!("Unexpected '{}'", text), token.as_ref())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(input: &str) -> Expr {
+        SimpleTranslator::from_string(input).tree().unwrap()
+    }
+
+    fn parse(notation: Notation, input: &str) -> Result<Expr, String> {
+        notation.parse(input).map_err(|x| x.to_string())
+    }
+
+    #[test]
+    fn render_test() {
+        let expr = tree("(2 + 3) * -4");
+        let rendered: Vec<String> = Notation::ALL.iter().map(|x| x.render(&expr)).collect();
+        assert_eq!(rendered, vec!["2 3 + 4 neg *", "* + 2 3 neg 4", "(2 + 3) * -4", "((2 + 3) * (-4))", "(* (+ 2 3) (- 4))"]);
+        assert_eq!(tree("a - (b - c) - (d * e) % f").to_string(), "a - (b - c) - d * e % f");
+        assert_eq!(tree("a / (b * c) + -(x + 1) - --y").to_string(), "a / (b * c) + -(x + 1) - --y");
+        assert_eq!(tree("((a))").fully_parenthesized(), "a");
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let inputs = ["7", "x", "-x", "1 - 2 - 3", "1 - (2 - 3)", "a * (b + c) % -(d / 2)", "---0", "(1 + 2) * (3 + 4) - x1 * y_2"];
+        for input in &inputs {
+            let expr = tree(input);
+            assert_eq!(expr.postfix(), SimpleTranslator::from_string(input).expr().unwrap());
+            for notation in &Notation::ALL {
+                let text = notation.render(&expr);
+                assert_eq!(notation.parse(&text).as_ref(), Ok(&expr), "{:?}: {}", notation, text);
+            }
+        }
+    }
+
+    #[test]
+    fn errors_test() {
+        assert_eq!(parse(Notation::Postfix, "1 +"), Err("Missing operand for '+' at 1:3".to_owned()));
+        assert_eq!(parse(Notation::Postfix, "1 2"), Err("Expected operator at 1:4".to_owned()));
+        assert_eq!(parse(Notation::Postfix, ""), Err("Expected operand at 1:1".to_owned()));
+        assert_eq!(parse(Notation::Postfix, "1 2 ^"), Err("Unexpected '^' at 1:5".to_owned()));
+        assert_eq!(parse(Notation::Prefix, "+ 1"), Err("Expected operand at 1:4".to_owned()));
+        assert_eq!(parse(Notation::Prefix, "neg 1 2"), Err("Expected end of input at 1:7".to_owned()));
+        assert_eq!(parse(Notation::SExpression, "(+ 1 (* 2 3)"), Err("Expected ')' at 1:13".to_owned()));
+        assert_eq!(parse(Notation::SExpression, "(* 1)"), Err("Wrong number of arguments for '*': 1 at 1:2".to_owned()));
+        assert_eq!(parse(Notation::SExpression, "(1 2)"), Err("Expected operator at 1:2".to_owned()));
+        assert_eq!(parse(Notation::SExpression, "neg"), Err("Unexpected 'neg' at 1:1".to_owned()));
+        assert_eq!(parse(Notation::FullyParenthesized, "((1 + 2)"), Err("Expected ')' at 1:9".to_owned()));
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+use crate::diagnostics::Diagnostic;
+use crate::span::{SourceMap, Span};
+
+//Стековая машина для постфиксной записи (раздел 2.8): операнд кладётся на стек,
+//оператор снимает свои аргументы и кладёт результат. Лексемы разделены пробелами.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    /// Оператору не хватило аргументов на стеке
+    StackUnderflow { operator: String, span: Span },
+    /// span покрывает все оставшиеся на стеке значения
+    LeftoverOperands { count: usize, span: Span },
+    EmptyExpression { span: Span },
+    DivisionByZero { span: Span },
+    Overflow { span: Span },
+    NegativeExponent { span: Span },
+    UndefinedVariable { name: String, span: Span },
+    InvalidToken { token: String, span: Span },
+}
+
+impl EvalError {
+    pub fn span(&self) -> Span {
+        match self {
+            EvalError::StackUnderflow { span, .. }
+            | EvalError::LeftoverOperands { span, .. }
+            | EvalError::EmptyExpression { span }
+            | EvalError::DivisionByZero { span }
+            | EvalError::Overflow { span }
+            | EvalError::NegativeExponent { span }
+            | EvalError::UndefinedVariable { span, .. }
+            | EvalError::InvalidToken { span, .. } => *span,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            EvalError::StackUnderflow { operator, .. } => format!("Not enough operands for '{}'", operator),
+            EvalError::LeftoverOperands { count, .. } => format!("{} operands left on the stack", count),
+            EvalError::EmptyExpression { .. } => "Empty expression".to_owned(),
+            EvalError::DivisionByZero { .. } => "Division by zero".to_owned(),
+            EvalError::Overflow { .. } => "Integer overflow".to_owned(),
+            EvalError::NegativeExponent { .. } => "Negative exponent".to_owned(),
+            EvalError::UndefinedVariable { name, .. } => format!("Undefined variable '{}'", name),
+            EvalError::InvalidToken { token, .. } => format!("Invalid token '{}'", token),
+        }
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message(), self.span().start)
+    }
+}
+
+impl Error for EvalError {}
+
+impl From<&EvalError> for Diagnostic {
+    fn from(error: &EvalError) -> Self {
+        let diagnostic = Diagnostic::error(&error.message());
+        match error {
+            EvalError::StackUnderflow { .. } => diagnostic.with_label(error.span(), "the stack has too few values"),
+            EvalError::LeftoverOperands { .. } => diagnostic.with_label(error.span(), "values left on the stack")
+                .with_note("a postfix expression must leave exactly one value"),
+            EvalError::InvalidToken { .. } => diagnostic.with_label(error.span(), "expected a number, a variable or an operator"),
+            _ => diagnostic.with_label(error.span(), ""),
+        }
+    }
+}
+
+fn unary(operator: &str) -> Option<fn(i64) -> Option<i64>> {
+    match operator {
+        "neg" => Some(i64::checked_neg),
+        "abs" => Some(i64::checked_abs),
+        _ => None,
+    }
+}
+
+fn is_binary(operator: &str) -> bool {
+    matches!(operator, "+" | "-" | "*" | "/" | "%" | "^")
+}
+
+fn binary(operator: &str, a: i64, b: i64, span: Span) -> Result<i64, EvalError> {
+    let result = match operator {
+        "+" => a.checked_add(b),
+        "-" => a.checked_sub(b),
+        "*" => a.checked_mul(b),
+        "/" | "%" if b == 0 => return Err(EvalError::DivisionByZero { span }),
+        "/" => a.checked_div(b),
+        "%" => a.checked_rem(b),
+        _ if b < 0 => return Err(EvalError::NegativeExponent { span }),
+        _ => u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+    };
+    result.ok_or(EvalError::Overflow { span })
+}
+
+fn is_number(token: &str) -> bool {
+    let digits = token.strip_prefix('-').unwrap_or(token);
+    !digits.is_empty() && digits.chars().all(|x| x.is_ascii_digit())
+}
+
+fn is_identifier(token: &str) -> bool {
+    token.starts_with(|x: char| x.is_alphabetic() || x == '_') && token.chars().all(|x| x.is_alphanumeric() || x == '_')
+}
+
+/// Значение постфиксного выражения вроде "12 x + 4 neg *"; переменные берутся из env.
+/// Операторы: + - * / % ^ и унарные neg, abs
+pub fn evaluate(input: &str, env: &BTreeMap<String, i64>) -> Result<i64, EvalError> {
+    let map = SourceMap::new(input);
+    // значение и участок входа, из которого оно получено
+    let mut stack: Vec<(i64, Span)> = Vec::new();
+    let mut offset = 0;
+    for token in input.split_whitespace() {
+        let start = offset + input[offset..].find(token).unwrap();
+        offset = start + token.len();
+        let span = map.span(start, offset);
+        let underflow = || EvalError::StackUnderflow { operator: token.to_owned(), span };
+        let value = if is_number(token) {
+            (token.parse().map_err(|_| EvalError::Overflow { span })?, span)
+        } else if let Some(f) = unary(token) {
+            let (x, x_span) = stack.pop().ok_or_else(underflow)?;
+            (f(x).ok_or(EvalError::Overflow { span })?, x_span.merge(&span))
+        } else if is_binary(token) {
+            let (b, _) = stack.pop().ok_or_else(underflow)?;
+            let (a, a_span) = stack.pop().ok_or_else(underflow)?;
+            (binary(token, a, b, span)?, a_span.merge(&span))
+        } else if is_identifier(token) {
+            match env.get(token) {
+                Some(value) => (*value, span),
+                None => return Err(EvalError::UndefinedVariable { name: token.to_owned(), span }),
+            }
+        } else {
+            return Err(EvalError::InvalidToken { token: token.to_owned(), span });
+        };
+        stack.push(value);
+    }
+    match stack.len() {
+        0 => Err(EvalError::EmptyExpression { span: map.span(input.len(), input.len()) }),
+        1 => Ok(stack[0].0),
+        count => Err(EvalError::LeftoverOperands { count, span: stack[0].1.merge(&stack[count - 1].1) }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part2::simple_translator::SimpleTranslator;
+
+    fn eval(input: &str) -> Result<i64, String> {
+        evaluate(input, &BTreeMap::new()).map_err(|x| x.to_string())
+    }
+
+    #[test]
+    fn evaluate_test() {
+        assert_eq!(eval("2 6 3 - +"), Ok(5));
+        assert_eq!(eval("2 6 3 7 8 - + + -"), Ok(2 - (6 + (3 + (7 - 8)))));
+        assert_eq!(eval("12 3 + 4 +"), Ok(19));
+        assert_eq!(eval(" -7 2 / -7 2 % \n 2 10 ^ "), Err("3 operands left on the stack at 1:2".to_owned()));
+        assert_eq!(eval("-7 2 / -7 2 % * 2 10 ^ +"), Ok(3 + 1024));
+        assert_eq!(eval("5 neg abs 3 neg +"), Ok(2));
+        assert_eq!(eval("7 0 ^"), Ok(1));
+
+        let env: BTreeMap<String, i64> = vec![("x".to_owned(), 4), ("y_1".to_owned(), -3)].into_iter().collect();
+        assert_eq!(evaluate("x y_1 * x +", &env), Ok(-8));
+        // вывод транслятора из инфиксной записи исполняется машиной
+        let postfix = SimpleTranslator::from_string("(x + 6) * -y_1 % 7 - 10 / x").expr().unwrap();
+        assert_eq!(evaluate(&postfix, &env), Ok((4 + 6) * 3 % 7 - 10 / 4));
+    }
+
+    #[test]
+    fn errors_test() {
+        assert_eq!(eval("1 +"), Err("Not enough operands for '+' at 1:3".to_owned()));
+        assert_eq!(eval("neg"), Err("Not enough operands for 'neg' at 1:1".to_owned()));
+        assert_eq!(eval("1 2 3 +"), Err("2 operands left on the stack at 1:1".to_owned()));
+        assert_eq!(eval("  "), Err("Empty expression at 1:3".to_owned()));
+        assert_eq!(eval("1 1 1 - /"), Err("Division by zero at 1:9".to_owned()));
+        assert_eq!(eval("1 0 %"), Err("Division by zero at 1:5".to_owned()));
+        assert_eq!(eval("9223372036854775807 1 +"), Err("Integer overflow at 1:23".to_owned()));
+        assert_eq!(eval("99999999999999999999"), Err("Integer overflow at 1:1".to_owned()));
+        assert_eq!(eval("-9223372036854775808 neg"), Err("Integer overflow at 1:22".to_owned()));
+        assert_eq!(eval("2 64 ^"), Err("Integer overflow at 1:6".to_owned()));
+        assert_eq!(eval("2 -1 ^"), Err("Negative exponent at 1:6".to_owned()));
+        assert_eq!(eval("1 x +"), Err("Undefined variable 'x' at 1:3".to_owned()));
+        assert_eq!(eval("26a - +"), Err("Invalid token '26a' at 1:1".to_owned()));
+    }
+
+    #[test]
+    fn diagnostic_test() {
+        let input = "1 2 3 +";
+        let error = evaluate(input, &BTreeMap::new()).unwrap_err();
+        assert_eq!(error.span().text(input), "1 2 3 +");
+        let input = "4 1 2 + 3 *";
+        let error = evaluate(input, &BTreeMap::new()).unwrap_err();
+        // 4 и результат 1 2 + 3 *
+        assert_eq!(error.span().text(input), "4 1 2 + 3 *");
+        assert_eq!(Diagnostic::from(&error).render(&SourceMap::new(input)), "\
+error: 2 operands left on the stack
+ --> 1:1
+  |
+1 | 4 1 2 + 3 *
+  | ^^^^^^^^^^^ values left on the stack
+  |
+  = note: a postfix expression must leave exactly one value
+");
+    }
+}
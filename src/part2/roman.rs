@@ -0,0 +1,278 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::diagnostics::Diagnostic;
+use crate::span::{Position, Span};
+
+//Римские числа (упражнение 2.3.3). Обычная запись покрывает 1..=3999;
+//с винкулумом (черта над цифрой умножает её на 1000) - до 3 999 999.
+//Черта записывается комбинируемым символом U+0305 после цифры: I̅V̅ = 4000.
+
+pub const MAX: u32 = 3999;
+pub const MAX_VINCULUM: u32 = 3_999_999;
+pub const VINCULUM: char = '\u{305}';
+
+const NUMERALS: [(u32, &str); 13] = [
+    (1000, "M"), (900, "CM"), (500, "D"), (400, "CD"),
+    (100, "C"), (90, "XC"), (50, "L"), (40, "XL"),
+    (10, "X"), (9, "IX"), (5, "V"), (4, "IV"), (1, "I"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RomanError {
+    OutOfRange { value: u32, max: u32 },
+    Empty,
+    InvalidCharacter { character: char, span: Span },
+    /// V, L, D не повторяются никогда, остальные - не больше трёх раз подряд
+    NotRepeatable { numeral: String, span: Span },
+    RepeatedTooOften { numeral: String, span: Span },
+    /// Вычитать можно только I, X, C (и они же с чертой) из двух ближайших больших цифр
+    InvalidSubtraction { numeral: String, from: String, span: Span },
+    /// Сумма цифр больше MAX_VINCULUM; span - от начала до цифры, на которой это случилось
+    TooLarge { max: u32, span: Span },
+    /// Число прочитано, но записывается иначе
+    NonCanonical { value: u32, expected: String, span: Span },
+}
+
+impl RomanError {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            RomanError::OutOfRange { .. } | RomanError::Empty => None,
+            RomanError::InvalidCharacter { span, .. }
+            | RomanError::NotRepeatable { span, .. }
+            | RomanError::RepeatedTooOften { span, .. }
+            | RomanError::InvalidSubtraction { span, .. }
+            | RomanError::TooLarge { span, .. }
+            | RomanError::NonCanonical { span, .. } => Some(*span),
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            RomanError::OutOfRange { value, max } => format!("{} is out of range 1..={}", value, max),
+            RomanError::Empty => "Empty numeral".to_owned(),
+            RomanError::InvalidCharacter { character, .. } => format!("Invalid character {:?}", character),
+            RomanError::NotRepeatable { numeral, .. } => format!("'{}' cannot be repeated", numeral),
+            RomanError::RepeatedTooOften { numeral, .. } => format!("'{}' is repeated more than three times", numeral),
+            RomanError::InvalidSubtraction { numeral, from, .. } =>
+                format!("'{}' cannot be subtracted from '{}'", numeral, from),
+            RomanError::TooLarge { max, .. } => format!("Numeral is greater than {}", max),
+            RomanError::NonCanonical { value, expected, .. } => format!("Non-canonical numeral for {}, expected '{}'", value, expected),
+        }
+    }
+}
+
+impl fmt::Display for RomanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span() {
+            Some(span) => write!(f, "{} at {}", self.message(), span.start),
+            None => write!(f, "{}", self.message()),
+        }
+    }
+}
+
+impl Error for RomanError {}
+
+impl From<&RomanError> for Diagnostic {
+    fn from(error: &RomanError) -> Self {
+        let diagnostic = Diagnostic::error(&error.message());
+        match error {
+            RomanError::NonCanonical { expected, span, .. } => diagnostic.with_label(*span, "")
+                .with_suggestion(*span, expected, "write it as"),
+            RomanError::RepeatedTooOften { span, .. } => diagnostic.with_label(*span, "")
+                .with_note("use subtraction instead: IV, XL, CD"),
+            _ => match error.span() {
+                Some(span) => diagnostic.with_label(span, ""),
+                None => diagnostic,
+            },
+        }
+    }
+}
+
+fn write(mut number: u32, result: &mut String) {
+    for (value, text) in NUMERALS.iter() {
+        while number >= *value {
+            *result += text;
+            number -= value;
+        }
+    }
+}
+
+/// Обычная запись: 1994 -> MCMXCIV
+pub fn to_roman(number: u32) -> Result<String, RomanError> {
+    if number == 0 || number > MAX {
+        return Err(RomanError::OutOfRange { value: number, max: MAX });
+    }
+    let mut result = String::new();
+    write(number, &mut result);
+    Ok(result)
+}
+
+/// Запись с винкулумом; числа до 3999 записываются как обычно
+pub fn to_roman_vinculum(number: u32) -> Result<String, RomanError> {
+    if number <= MAX {
+        return to_roman(number);
+    }
+    if number > MAX_VINCULUM {
+        return Err(RomanError::OutOfRange { value: number, max: MAX_VINCULUM });
+    }
+    let mut thousands = String::new();
+    write(number / 1000, &mut thousands);
+    let mut result: String = thousands.chars().flat_map(|x| vec![x, VINCULUM]).collect();
+    write(number % 1000, &mut result);
+    Ok(result)
+}
+
+// 5, 50, 500, ...
+fn is_five(mut value: u32) -> bool {
+    while value.is_multiple_of(10) {
+        value /= 10;
+    }
+    value == 5
+}
+
+/// Разбор только канонической записи, в том числе с винкулумом
+pub fn from_roman(input: &str) -> Result<u32, RomanError> {
+    // значение, запись и участок каждой цифры
+    let mut digits: Vec<(u32, String, Span)> = Vec::new();
+    let mut position = Position::default();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        let start = position;
+        position.advance(c);
+        let mut value = match c {
+            'I' => 1,
+            'V' => 5,
+            'X' => 10,
+            'L' => 50,
+            'C' => 100,
+            'D' => 500,
+            'M' => 1000,
+            _ => return Err(RomanError::InvalidCharacter { character: c, span: Span::new(start, position) }),
+        };
+        let mut text = c.to_string();
+        if chars.next_if_eq(&VINCULUM).is_some() {
+            position.advance(VINCULUM);
+            text.push(VINCULUM);
+            value *= 1000;
+        }
+        digits.push((value, text, Span::new(start, position)));
+    }
+    if digits.is_empty() {
+        return Err(RomanError::Empty);
+    }
+    let mut total: u32 = 0;
+    let mut i = 0;
+    while i < digits.len() {
+        let (value, text, span) = &digits[i];
+        let repeats = digits[i..].iter().take_while(|x| x.0 == *value).count();
+        let repeated = span.merge(&digits[i + repeats - 1].2);
+        if repeats > 1 && is_five(*value) {
+            return Err(RomanError::NotRepeatable { numeral: text.to_owned(), span: repeated });
+        }
+        if repeats > 3 {
+            return Err(RomanError::RepeatedTooOften { numeral: text.to_owned(), span: repeated });
+        }
+        let (added, count) = match digits.get(i + 1) {
+            Some((next, from, next_span)) if next > value => {
+                if is_five(*value) || *next != 5 * value && *next != 10 * value {
+                    let (numeral, from, span) = (text.to_owned(), from.to_owned(), span.merge(next_span));
+                    return Err(RomanError::InvalidSubtraction { numeral, from, span });
+                }
+                (next - value, 2)
+            }
+            _ => (*value, 1),
+        };
+        i += count;
+        total = match total.checked_add(added).filter(|x| *x <= MAX_VINCULUM) {
+            Some(total) => total,
+            None => {
+                let span = Span::new(Position::default(), digits[i - 1].2.end);
+                return Err(RomanError::TooLarge { max: MAX_VINCULUM, span });
+            }
+        };
+    }
+    let expected = to_roman_vinculum(total)?;
+    if expected != input {
+        return Err(RomanError::NonCanonical { value: total, expected, span: Span::new(Position::default(), position) });
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::SourceMap;
+
+    fn parse(input: &str) -> Result<u32, String> {
+        from_roman(input).map_err(|x| x.to_string())
+    }
+
+    #[test]
+    fn to_roman_test() {
+        assert_eq!(to_roman(25), Ok("XXV".to_owned()));
+        assert_eq!(to_roman(78), Ok("LXXVIII".to_owned()));
+        assert_eq!(to_roman(1994), Ok("MCMXCIV".to_owned()));
+        assert_eq!(to_roman(3999), Ok("MMMCMXCIX".to_owned()));
+        assert_eq!(to_roman(0), Err(RomanError::OutOfRange { value: 0, max: 3999 }));
+        assert_eq!(to_roman(4000).unwrap_err().to_string(), "4000 is out of range 1..=3999");
+    }
+
+    #[test]
+    fn vinculum_test() {
+        assert_eq!(to_roman_vinculum(3999), Ok("MMMCMXCIX".to_owned()));
+        assert_eq!(to_roman_vinculum(4000), Ok("I\u{305}V\u{305}".to_owned()));
+        assert_eq!(to_roman_vinculum(12_345), Ok("X\u{305}I\u{305}I\u{305}CCCXLV".to_owned()));
+        assert_eq!(to_roman_vinculum(4_000_000).unwrap_err().to_string(), "4000000 is out of range 1..=3999999");
+        assert_eq!(from_roman("X\u{305}I\u{305}I\u{305}CCCXLV"), Ok(12_345));
+        assert_eq!(parse("I\u{305}"), Err("Non-canonical numeral for 1000, expected 'M' at 1:1".to_owned()));
+        for number in (4000..=MAX_VINCULUM).step_by(997) {
+            assert_eq!(from_roman(&to_roman_vinculum(number).unwrap()), Ok(number));
+        }
+    }
+
+    #[test]
+    fn round_trip_test() {
+        for number in 1..=MAX {
+            assert_eq!(from_roman(&to_roman(number).unwrap()), Ok(number));
+        }
+    }
+
+    #[test]
+    fn errors_test() {
+        assert_eq!(parse("IIII"), Err("'I' is repeated more than three times at 1:1".to_owned()));
+        assert_eq!(parse("VX"), Err("'V' cannot be subtracted from 'X' at 1:1".to_owned()));
+        assert_eq!(parse("MIC"), Err("'I' cannot be subtracted from 'C' at 1:2".to_owned()));
+        assert_eq!(parse("XVV"), Err("'V' cannot be repeated at 1:2".to_owned()));
+        assert_eq!(parse("IXI"), Err("Non-canonical numeral for 10, expected 'X' at 1:1".to_owned()));
+        assert_eq!(parse("IIV"), Err("Non-canonical numeral for 5, expected 'V' at 1:1".to_owned()));
+        assert_eq!(parse("XIV|"), Err("Invalid character '|' at 1:4".to_owned()));
+        assert_eq!(parse("xiv"), Err("Invalid character 'x' at 1:1".to_owned()));
+        assert_eq!(parse(""), Err("Empty numeral".to_owned()));
+
+        // сумма больше MAX_VINCULUM - ошибка разбора, а не OutOfRange
+        let input = "M\u{305}M\u{305}M\u{305}C\u{305}M\u{305}C\u{305}M\u{305}";
+        assert_eq!(parse(input), Err("Numeral is greater than 3999999 at 1:1".to_owned()));
+        assert_eq!(from_roman(input).unwrap_err().span().unwrap().text(input), input);
+        let input = "C\u{305}M\u{305}".repeat(5000);
+        assert_eq!(from_roman(&input), Err(RomanError::TooLarge { max: MAX_VINCULUM, span: {
+            let mut end = Position::default();
+            input.chars().take(20).for_each(|x| end.advance(x));
+            Span::new(Position::default(), end)
+        } }));
+
+        let error = from_roman("MCCCCX").unwrap_err();
+        assert_eq!(error.span().unwrap().text("MCCCCX"), "CCCC");
+        let input = "XIIX";
+        let error = from_roman(input).unwrap_err();
+        assert_eq!(Diagnostic::from(&error).render(&SourceMap::new(input)), "\
+error: Non-canonical numeral for 20, expected 'XX'
+ --> 1:1
+  |
+1 | XIIX
+  | ^^^^
+  |
+  = help: write it as: `XX`
+");
+    }
+}
@@ -0,0 +1,233 @@
+use std::fmt;
+
+use crate::diagnostics::Diagnostic;
+use crate::span::Span;
+
+use super::symbols_table::{Env, RedeclarationError, Symbol, SymbolKind};
+
+//Предупреждения по таблице символов: неиспользуемые символы, объявления,
+//скрывающие внешние, и использования до объявления. Анализатор ведёт Env
+//вместо транслятора и замечает всё это при входе в области и выходе из них.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolWarning {
+    Unused { name: String, kind: SymbolKind, span: Span },
+    /// outer - скрытое объявление
+    Shadowed { name: String, span: Span, outer: Span },
+    /// span - использование, declaration - объявление после него
+    UsedBeforeDeclaration { name: String, span: Span, declaration: Span },
+}
+
+impl SymbolWarning {
+    pub fn span(&self) -> Span {
+        match self {
+            SymbolWarning::Unused { span, .. }
+            | SymbolWarning::Shadowed { span, .. }
+            | SymbolWarning::UsedBeforeDeclaration { span, .. } => *span,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            SymbolWarning::Unused { name, kind, .. } => format!("Unused {} '{}'", kind, name),
+            SymbolWarning::Shadowed { name, .. } => format!("'{}' shadows an outer declaration", name),
+            SymbolWarning::UsedBeforeDeclaration { name, .. } => format!("'{}' is used before its declaration", name),
+        }
+    }
+}
+
+impl fmt::Display for SymbolWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message(), self.span().start)
+    }
+}
+
+impl From<&SymbolWarning> for Diagnostic {
+    fn from(warning: &SymbolWarning) -> Self {
+        let diagnostic = Diagnostic::warning(&warning.message());
+        match warning {
+            SymbolWarning::Unused { span, .. } => diagnostic.with_label(*span, "declared here but never used"),
+            SymbolWarning::Shadowed { span, outer, .. } => diagnostic.with_label(*span, "")
+                .with_secondary_label(*outer, "this declaration is hidden"),
+            SymbolWarning::UsedBeforeDeclaration { span, declaration, .. } => diagnostic.with_label(*span, "used here")
+                .with_secondary_label(*declaration, "declared here"),
+        }
+    }
+}
+
+///Env с предупреждениями; используется так же, как Env
+#[derive(Debug)]
+pub struct ScopeAnalyzer {
+    env: Env,
+    /// Неразрешённые использования для каждой открытой области;
+    /// при выходе из области переходят во внешнюю
+    unresolved: Vec<Vec<(String, Span)>>,
+    warnings: Vec<SymbolWarning>,
+}
+
+impl Default for ScopeAnalyzer {
+    fn default() -> Self {
+        ScopeAnalyzer::new()
+    }
+}
+
+impl ScopeAnalyzer {
+    pub fn new() -> Self {
+        ScopeAnalyzer { env: Env::new(), unresolved: vec![Vec::new()], warnings: Vec::new() }
+    }
+
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+
+    pub fn enter_scope(&mut self) {
+        self.env.enter_scope();
+        self.unresolved.push(Vec::new());
+    }
+
+    pub fn exit_scope(&mut self) -> Option<Vec<Symbol>> {
+        let symbols = self.env.exit_scope()?;
+        let unresolved = self.unresolved.pop().unwrap();
+        self.unresolved.last_mut().unwrap().extend(unresolved);
+        self.unused(&symbols.iter().collect::<Vec<_>>());
+        Some(symbols)
+    }
+
+    fn unused(&mut self, symbols: &[&Symbol]) {
+        let unused = symbols.iter().filter(|x| x.uses == 0)
+            .map(|x| SymbolWarning::Unused { name: x.name.to_owned(), kind: x.kind, span: x.span });
+        self.warnings.extend(unused);
+    }
+
+    pub fn declare(&mut self, symbol: Symbol) -> Result<&Symbol, RedeclarationError> {
+        let (name, span) = (symbol.name.to_owned(), symbol.span);
+        let outer = self.env.get(&name).filter(|_| !self.env.is_local(&name)).map(|x| x.span);
+        self.env.declare(symbol)?;
+        if let Some(outer) = outer {
+            self.warnings.push(SymbolWarning::Shadowed { name: name.to_owned(), span, outer });
+        }
+        let unresolved = self.unresolved.last_mut().unwrap();
+        let (used, rest) = std::mem::take(unresolved).into_iter().partition(|x| x.0 == name);
+        *unresolved = rest;
+        // ранние использования - всё же использования, символ не считается лишним
+        for (_, used) in used {
+            self.env.use_symbol(&name);
+            self.warnings.push(SymbolWarning::UsedBeforeDeclaration { name: name.to_owned(), span: used, declaration: span });
+        }
+        Ok(self.env.get(&name).unwrap())
+    }
+
+    /// None - символ не объявлен; запоминается на случай объявления ниже
+    pub fn use_symbol(&mut self, key: &str, span: Span) -> Option<&Symbol> {
+        if self.env.get(key).is_none() {
+            self.unresolved.last_mut().unwrap().push((key.to_owned(), span));
+        }
+        self.env.use_symbol(key)
+    }
+
+    /// Закрывает все области; предупреждения упорядочены по месту
+    pub fn finish(mut self) -> Vec<SymbolWarning> {
+        while self.exit_scope().is_some() {}
+        let globals: Vec<Symbol> = self.env.symbols().into_iter().cloned().collect();
+        self.unused(&globals.iter().collect::<Vec<_>>());
+        self.warnings.sort_by_key(|x| x.span());
+        self.warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::SourceMap;
+
+    //Объявления - буквы в [], использования - буквы без скобок, { } - области:
+    //так проще следить за позициями в тестах
+    fn analyze(source: &str) -> Vec<String> {
+        let map = SourceMap::new(source);
+        let mut analyzer = ScopeAnalyzer::new();
+        let mut chars = source.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '{' => analyzer.enter_scope(),
+                '}' => {
+                    analyzer.exit_scope();
+                }
+                '[' => {
+                    let (j, name) = chars.next().unwrap();
+                    let symbol = Symbol::new(&name.to_string(), SymbolKind::Variable, "int", 4, map.span(j, j + 1));
+                    if let Err(error) = analyzer.declare(symbol) {
+                        return vec![error.to_string()];
+                    }
+                    chars.next();
+                }
+                c if c.is_alphabetic() => {
+                    analyzer.use_symbol(&c.to_string(), map.span(i, i + 1));
+                }
+                _ => {}
+            }
+        }
+        analyzer.finish().iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn unused_test() {
+        assert_eq!(analyze("[x] [y] x { [z] }"), vec!["Unused variable 'y' at 1:6", "Unused variable 'z' at 1:14"]);
+        assert!(analyze("[x] { x } x").is_empty());
+    }
+
+    #[test]
+    fn shadowed_test() {
+        assert_eq!(analyze("[x] x { [x] x { [x] x } }"),
+                   vec!["'x' shadows an outer declaration at 1:10", "'x' shadows an outer declaration at 1:18"]);
+        // в соседних областях - не скрытие
+        assert!(analyze("{ [x] x } { [x] x }").is_empty());
+        assert_eq!(analyze("[x] x [x]"), vec!["'x' is already declared in this scope at 1:8"]);
+    }
+
+    #[test]
+    fn used_before_declaration_test() {
+        assert_eq!(analyze("x [x] x"), vec!["'x' is used before its declaration at 1:1"]);
+        // единственное использование - до объявления: x не считается неиспользуемым
+        assert_eq!(analyze("x [x]"), vec!["'x' is used before its declaration at 1:1"]);
+        assert_eq!(analyze("{ { y } [y] y } { y }"), vec!["'y' is used before its declaration at 1:5"]);
+        // использование до объявления во вложенной области относится к внешнему x
+        assert_eq!(analyze("[x] { x [x] x } x"), vec!["'x' shadows an outer declaration at 1:10"]);
+        // так и не объявленный символ - дело транслятора, не предупреждение
+        assert!(analyze("q").is_empty());
+    }
+
+    #[test]
+    fn default_test() {
+        let mut analyzer = ScopeAnalyzer::default();
+        assert_eq!(analyzer.use_symbol("x", Span::default()), None);
+        analyzer.declare(Symbol::new("x", SymbolKind::Variable, "int", 4, Span::default())).unwrap();
+        assert_eq!(analyzer.env().depth(), 1);
+        assert_eq!(analyzer.env().get("x").map(|x| x.uses), Some(1));
+        let warnings: Vec<String> = analyzer.finish().iter().map(|x| x.to_string()).collect();
+        assert_eq!(warnings, vec!["'x' is used before its declaration at 1:1"]);
+    }
+
+    #[test]
+    fn diagnostic_test() {
+        let source = "[a]\n{ a [a] }";
+        let warnings: Vec<Diagnostic> = {
+            let map = SourceMap::new(source);
+            let mut analyzer = ScopeAnalyzer::new();
+            analyzer.declare(Symbol::new("a", SymbolKind::Constant, "int", 4, map.span(1, 2))).unwrap();
+            analyzer.enter_scope();
+            analyzer.use_symbol("a", map.span(6, 7));
+            analyzer.declare(Symbol::new("a", SymbolKind::Variable, "int", 4, map.span(9, 10))).unwrap();
+            analyzer.finish().iter().map(Diagnostic::from).collect()
+        };
+        assert_eq!(warnings[0].render(&SourceMap::new(source)), "\
+warning: 'a' shadows an outer declaration
+ --> 2:6
+  |
+1 | [a]
+  |  - this declaration is hidden
+2 | { a [a] }
+  |      ^
+");
+        assert_eq!(warnings[1].to_string(), "warning: Unused variable 'a' at 2:6");
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::diagnostics::Diagnostic;
+use crate::span::{Position, Span};
+
+//Перевод инфиксной записи в постфиксную алгоритмом сортировочной станции (Дейкстра):
+//операнды сразу идут на выход, операторы ждут на стеке, пока не придёт оператор
+//с меньшим приоритетом или закрывающая скобка. Унарный минус выводится как neg,
+//вызов функции - её именем после аргументов.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxError {
+    InvalidCharacter { character: char, span: Span },
+    /// Лексема там, где ожидался операнд или оператор другого рода
+    UnexpectedToken { token: String, span: Span },
+    UnexpectedEnd { span: Span },
+    /// span указывает на незакрытую скобку
+    UnclosedParen { span: Span },
+    UnmatchedParen { span: Span },
+    UnknownFunction { name: String, span: Span },
+    WrongArgumentCount { name: String, expected: usize, found: usize, span: Span },
+}
+
+impl SyntaxError {
+    pub fn span(&self) -> Span {
+        match self {
+            SyntaxError::InvalidCharacter { span, .. }
+            | SyntaxError::UnexpectedToken { span, .. }
+            | SyntaxError::UnexpectedEnd { span }
+            | SyntaxError::UnclosedParen { span }
+            | SyntaxError::UnmatchedParen { span }
+            | SyntaxError::UnknownFunction { span, .. }
+            | SyntaxError::WrongArgumentCount { span, .. } => *span,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            SyntaxError::InvalidCharacter { character, .. } => format!("Invalid character {:?}", character),
+            SyntaxError::UnexpectedToken { token, .. } => format!("Unexpected '{}'", token),
+            SyntaxError::UnexpectedEnd { .. } => "Unexpected end of input".to_owned(),
+            SyntaxError::UnclosedParen { .. } => "Unclosed '('".to_owned(),
+            SyntaxError::UnmatchedParen { .. } => "Unmatched ')'".to_owned(),
+            SyntaxError::UnknownFunction { name, .. } => format!("Unknown function '{}'", name),
+            SyntaxError::WrongArgumentCount { name, expected, found, .. } =>
+                format!("Function '{}' takes {} argument{}, found {}", name, expected,
+                        if *expected == 1 { "" } else { "s" }, found),
+        }
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message(), self.span().start)
+    }
+}
+
+impl Error for SyntaxError {}
+
+impl From<&SyntaxError> for Diagnostic {
+    fn from(error: &SyntaxError) -> Self {
+        let diagnostic = Diagnostic::error(&error.message());
+        match error {
+            SyntaxError::UnclosedParen { span } => diagnostic.with_label(*span, "never closed"),
+            SyntaxError::UnexpectedEnd { span } => diagnostic.with_label(*span, "expected an operand"),
+            _ => diagnostic.with_label(error.span(), ""),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(String),
+    Id(String),
+    Operator(char),
+    Open,
+    Close,
+    Comma,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(x) | Token::Id(x) => write!(f, "{}", x),
+            Token::Operator(x) => write!(f, "{}", x),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+            Token::Comma => write!(f, ","),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<(Vec<(Token, Span)>, Position), SyntaxError> {
+    let mut result = Vec::new();
+    let mut position = Position::default();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        let start = position;
+        position.advance(c);
+        let token = match c {
+            _ if c.is_whitespace() => continue,
+            '+' | '-' | '*' | '/' | '%' | '^' => Token::Operator(c),
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            _ if c.is_ascii_digit() || c.is_alphabetic() || c == '_' => {
+                let mut text = c.to_string();
+                let part = |x: char| if c.is_ascii_digit() { x.is_ascii_digit() } else { x.is_alphanumeric() || x == '_' };
+                while let Some(x) = chars.next_if(|x| part(*x)) {
+                    position.advance(x);
+                    text.push(x);
+                }
+                if c.is_ascii_digit() { Token::Number(text) } else { Token::Id(text) }
+            }
+            _ => return Err(SyntaxError::InvalidCharacter { character: c, span: Span::new(start, position) }),
+        };
+        result.push((token, Span::new(start, position)));
+    }
+    Ok((result, position))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Binary(char),
+    Neg,
+}
+
+impl Operator {
+    fn precedence(self) -> u8 {
+        match self {
+            Operator::Binary('+') | Operator::Binary('-') => 1,
+            Operator::Binary('^') => 4,
+            Operator::Binary(_) => 2,
+            // -2^2 = -(2^2), но 2*-3 = 2*(-3)
+            Operator::Neg => 3,
+        }
+    }
+
+    fn right_associative(self) -> bool {
+        matches!(self, Operator::Binary('^') | Operator::Neg)
+    }
+
+    fn text(self) -> String {
+        match self {
+            Operator::Binary(c) => c.to_string(),
+            Operator::Neg => "neg".to_owned(),
+        }
+    }
+}
+
+enum Entry {
+    Operator(Operator),
+    /// Открывающая скобка; для вызова - имя функции и число запятых внутри
+    Paren { span: Span, function: Option<(String, Span)>, commas: usize },
+}
+
+///Транслятор с таблицей функций: имя -> число аргументов
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShuntingYard {
+    functions: BTreeMap<String, usize>,
+}
+
+impl Default for ShuntingYard {
+    /// Функции abs, max и min
+    fn default() -> Self {
+        ShuntingYard::new().with_function("abs", 1).with_function("max", 2).with_function("min", 2)
+    }
+}
+
+impl ShuntingYard {
+    /// Без функций
+    pub fn new() -> Self {
+        ShuntingYard { functions: BTreeMap::new() }
+    }
+
+    pub fn with_function(mut self, name: &str, arity: usize) -> Self {
+        self.functions.insert(name.to_owned(), arity);
+        self
+    }
+
+    /// Постфиксная запись с лексемами через пробел: "2+3*x" -> "2 3 x * +"
+    pub fn translate(&self, input: &str) -> Result<String, SyntaxError> {
+        let (tokens, end) = tokenize(input)?;
+        let mut output: Vec<String> = Vec::new();
+        let mut stack: Vec<Entry> = Vec::new();
+        // ожидается операнд: в начале, после оператора, '(' и ','
+        let mut expect_operand = true;
+        let mut tokens = tokens.into_iter().peekable();
+        while let Some((token, span)) = tokens.next() {
+            let unexpected = || SyntaxError::UnexpectedToken { token: token.to_string(), span };
+            match &token {
+                Token::Number(_) | Token::Id(_) if !expect_operand => return Err(unexpected()),
+                Token::Id(name) if tokens.peek().map(|x| &x.0) == Some(&Token::Open) => {
+                    if !self.functions.contains_key(name) {
+                        return Err(SyntaxError::UnknownFunction { name: name.to_owned(), span });
+                    }
+                    let (_, open) = tokens.next().unwrap();
+                    stack.push(Entry::Paren { span: open, function: Some((name.to_owned(), span)), commas: 0 });
+                    // вызов без аргументов
+                    if self.functions[name] == 0 {
+                        if let Some((Token::Close, _)) = tokens.peek() {
+                            tokens.next();
+                            stack.pop();
+                            output.push(name.to_owned());
+                            expect_operand = false;
+                        }
+                    }
+                }
+                Token::Number(x) | Token::Id(x) => {
+                    output.push(x.to_owned());
+                    expect_operand = false;
+                }
+                Token::Open if !expect_operand => return Err(unexpected()),
+                Token::Open => stack.push(Entry::Paren { span, function: None, commas: 0 }),
+                Token::Operator('-') if expect_operand => stack.push(Entry::Operator(Operator::Neg)),
+                Token::Operator(_) | Token::Comma | Token::Close if expect_operand => return Err(unexpected()),
+                Token::Operator(c) => {
+                    let operator = Operator::Binary(*c);
+                    while let Some(Entry::Operator(top)) = stack.last() {
+                        if top.precedence() < operator.precedence()
+                            || top.precedence() == operator.precedence() && operator.right_associative() {
+                            break;
+                        }
+                        output.push(top.text());
+                        stack.pop();
+                    }
+                    stack.push(Entry::Operator(operator));
+                    expect_operand = true;
+                }
+                Token::Comma | Token::Close => {
+                    while let Some(Entry::Operator(top)) = stack.last() {
+                        output.push(top.text());
+                        stack.pop();
+                    }
+                    match (stack.last_mut(), &token) {
+                        (Some(Entry::Paren { function: Some(_), commas, .. }), Token::Comma) => {
+                            *commas += 1;
+                            expect_operand = true;
+                        }
+                        (_, Token::Comma) => return Err(unexpected()),
+                        (None, _) => return Err(SyntaxError::UnmatchedParen { span }),
+                        (Some(_), _) => {
+                            if let Some(Entry::Paren { function: Some((name, name_span)), commas, .. }) = stack.pop() {
+                                let expected = self.functions[&name];
+                                if commas + 1 != expected {
+                                    let found = commas + 1;
+                                    return Err(SyntaxError::WrongArgumentCount { name, expected, found, span: name_span });
+                                }
+                                output.push(name);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if expect_operand {
+            return Err(SyntaxError::UnexpectedEnd { span: Span::point(end) });
+        }
+        while let Some(entry) = stack.pop() {
+            match entry {
+                Entry::Operator(operator) => output.push(operator.text()),
+                Entry::Paren { span, .. } => return Err(SyntaxError::UnclosedParen { span }),
+            }
+        }
+        Ok(output.join(" "))
+    }
+}
+
+/// Перевод с функциями по умолчанию
+pub fn translate_to_postfix(input: &str) -> Result<String, SyntaxError> {
+    ShuntingYard::default().translate(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part2::postfix::evaluate;
+    use crate::span::SourceMap;
+
+    fn translate(input: &str) -> Result<String, String> {
+        translate_to_postfix(input).map_err(|x| x.to_string())
+    }
+
+    #[test]
+    fn translate_to_postfix_test() {
+        assert_eq!(translate_to_postfix("22+4"), Ok(String::from("22 4 +")));
+        assert_eq!(translate_to_postfix("22+4-14"), Ok(String::from("22 4 + 14 -")));
+        assert_eq!(translate_to_postfix("22+4-14+24"), Ok(String::from("22 4 + 14 - 24 +")));
+    }
+
+    #[test]
+    fn precedence_test() {
+        assert_eq!(translate("1 + 2 * 3 - 4 / 5 % 6"), Ok("1 2 3 * + 4 5 / 6 % -".to_owned()));
+        assert_eq!(translate("(1 + 2) * (3 - x)"), Ok("1 2 + 3 x - *".to_owned()));
+        assert_eq!(translate("2 ^ 3 ^ 2"), Ok("2 3 2 ^ ^".to_owned()));
+        assert_eq!(translate("-2 ^ 2 * -x"), Ok("2 2 ^ neg x neg *".to_owned()));
+        assert_eq!(translate("2 ^ -3 - --4"), Ok("2 3 neg ^ 4 neg neg -".to_owned()));
+        assert_eq!(translate("((7))"), Ok("7".to_owned()));
+        // вывод исполняется стековой машиной
+        let env = vec![("x".to_owned(), 5)].into_iter().collect();
+        let postfix = translate_to_postfix("-(x - 8) ^ 2 * abs(1 - x) % 7").unwrap();
+        assert_eq!(evaluate(&postfix, &env), Ok(-9 * 4 % 7));
+    }
+
+    #[test]
+    fn functions_test() {
+        assert_eq!(translate("max(1, min(x, 2 + 3)) * abs(-y)"), Ok("1 x 2 3 + min max y neg abs *".to_owned()));
+        assert_eq!(translate("max(1)"), Err("Function 'max' takes 2 arguments, found 1 at 1:1".to_owned()));
+        assert_eq!(translate("1 + abs(1, 2)"), Err("Function 'abs' takes 1 argument, found 2 at 1:5".to_owned()));
+        assert_eq!(translate("sin(1)"), Err("Unknown function 'sin' at 1:1".to_owned()));
+        assert_eq!(translate("abs()"), Err("Unexpected ')' at 1:5".to_owned()));
+        assert_eq!(translate("(1, 2)"), Err("Unexpected ',' at 1:3".to_owned()));
+        let translator = ShuntingYard::new().with_function("rand", 0).with_function("clamp", 3);
+        assert_eq!(translator.translate("clamp(rand(), 0, 10 - rand())"), Ok("rand 0 10 rand - clamp".to_owned()));
+        assert!(translator.translate("abs(1)").is_err());
+    }
+
+    #[test]
+    fn errors_test() {
+        assert_eq!(translate("(1 + 2"), Err("Unclosed '(' at 1:1".to_owned()));
+        assert_eq!(translate("max((1, 2)"), Err("Unexpected ',' at 1:7".to_owned()));
+        assert_eq!(translate("1 + 2)"), Err("Unmatched ')' at 1:6".to_owned()));
+        assert_eq!(translate("1 2"), Err("Unexpected '2' at 1:3".to_owned()));
+        assert_eq!(translate("1 * / 2"), Err("Unexpected '/' at 1:5".to_owned()));
+        assert_eq!(translate("2 (1)"), Err("Unexpected '(' at 1:3".to_owned()));
+        assert_eq!(translate("()"), Err("Unexpected ')' at 1:2".to_owned()));
+        assert_eq!(translate("1 +"), Err("Unexpected end of input at 1:4".to_owned()));
+        assert_eq!(translate(""), Err("Unexpected end of input at 1:1".to_owned()));
+        assert_eq!(translate("1 $ 2"), Err("Invalid character '$' at 1:3".to_owned()));
+
+        let input = "2 * (3 +\n  (4 - 1)";
+        let error = translate_to_postfix(input).unwrap_err();
+        assert_eq!(Diagnostic::from(&error).render(&SourceMap::new(input)), "\
+error: Unclosed '('
+ --> 1:5
+  |
+1 | 2 * (3 +
+  |     ^ never closed
+");
+    }
+}
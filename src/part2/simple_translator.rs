@@ -1,10 +1,48 @@
 use std::char;
+use std::fmt;
 use std::str::Chars;
 
-struct SimpleTranslator<'a> {
+use crate::diagnostics::Diagnostic;
+use crate::span::{Position, Span};
+
+use super::notation::{BinaryOp, Expr};
+
+///Ошибка трансляции: что ожидалось и где стоит символ, на котором разбор остановился
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranslateError {
+    pub message: String,
+    /// Пустой участок, если ошибка в конце входа
+    pub span: Span,
+    pub found: Option<char>,
+}
+
+impl fmt::Display for TranslateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.span.start)
+    }
+}
+
+impl From<&TranslateError> for Diagnostic {
+    fn from(error: &TranslateError) -> Self {
+        let label = match error.found {
+            Some(c) => format!("found {:?}", c),
+            None => "found end of input".to_owned(),
+        };
+        Diagnostic::error(&error.message).with_label(error.span, &label)
+    }
+}
+
+///Транслятор из инфиксной записи в постфиксную (раздел 2.5):
+///синтаксически управляемая схема, переписанная в рекурсивный спуск.
+///Лексемы результата разделены пробелами, унарный минус записывается как neg.
+///Попутно строится дерево выражения для вывода в других записях (notation)
+pub struct SimpleTranslator<'a> {
     lookahead: char,
     string: Chars<'a>,
     result: String,
+    /// Позиция lookahead во входе
+    position: Position,
+    length: usize,
 }
 
 impl<'a> SimpleTranslator<'a> {
@@ -13,66 +51,141 @@ impl<'a> SimpleTranslator<'a> {
             string: input.chars(),
             lookahead: '\0',
             result: "".to_owned(),
+            position: Position::default(),
+            length: input.len(),
         }
     }
 
-    pub fn expr(&mut self) -> Result<String, String> {
-        self.lookahead = self.string.next().unwrap_or_else(|| '\n');
-        match self.term() {
-            Ok(_) => {}
-            Err(e) => return Err(e)
-        };
-        loop {
-            if self.lookahead == '-' {
-                if let Err(e) = self.matcher('-'){
-                    return Err(e)
-                }
-                if let Err(e) = self.term(){
-                    return Err(e)
-                }
-                self.result += "-";
-            } else if self.lookahead == '+' {
-                if let Err(e) = self.matcher('+'){
-                    return Err(e)
-                }
-                if let Err(e) = self.term(){
-                    return Err(e)
-                }
-                self.result += "+";
-            } else if self.lookahead == '\n'{
-                break;
-            } else {
-                return Err("Expected + or -".to_owned())
-            }
-        }
+    pub fn expr(&mut self) -> Result<String, TranslateError> {
+        self.tree()?;
         Ok(self.result.to_owned())
     }
 
+    pub fn tree(&mut self) -> Result<Expr, TranslateError> {
+        self.lookahead = self.string.next().unwrap_or('\0');
+        self.skip_whitespace();
+        let tree = self.sum()?;
+        if !self.at_end() {
+            return Err(self.error("Expected operator"));
+        }
+        Ok(tree)
+    }
+
     #[allow(dead_code)]
     pub fn show_result(&self) -> String {
         self.result.to_owned()
     }
 
-    fn term(&mut self) -> Result<char, String> {
-        if self.lookahead.is_digit(10) {
-            self.result += &self.lookahead.to_string();
-            self.matcher(self.lookahead).unwrap();
-            Ok(self.lookahead)
-        } else {
-            Err("Expected digit".to_owned())
+    fn error(&self, message: &str) -> TranslateError {
+        let found = Some(self.lookahead).filter(|_| !self.at_end());
+        let mut end = self.position;
+        if let Some(c) = found {
+            end.advance(c);
+        }
+        TranslateError { message: message.to_owned(), span: Span::new(self.position, end), found }
+    }
+
+    fn at_end(&self) -> bool {
+        self.position.offset >= self.length
+    }
+
+    fn emit(&mut self, lexeme: &str) {
+        if !self.result.is_empty() {
+            self.result.push(' ');
+        }
+        self.result += lexeme;
+    }
+
+    // expr -> expr + term {print('+')} | expr - term {print('-')} | term
+    fn sum(&mut self) -> Result<Expr, TranslateError> {
+        let mut tree = self.term()?;
+        while let op @ ('+' | '-') = self.lookahead {
+            self.matcher(op)?;
+            let right = self.term()?;
+            self.emit(&op.to_string());
+            tree = Expr::binary(BinaryOp::from_symbol(op).unwrap(), tree, right);
         }
+        Ok(tree)
     }
 
+    // term -> term * factor {print('*')} | term / factor {print('/')} | term % factor {print('%')} | factor
+    fn term(&mut self) -> Result<Expr, TranslateError> {
+        let mut tree = self.factor()?;
+        while let op @ ('*' | '/' | '%') = self.lookahead {
+            self.matcher(op)?;
+            let right = self.factor()?;
+            self.emit(&op.to_string());
+            tree = Expr::binary(BinaryOp::from_symbol(op).unwrap(), tree, right);
+        }
+        Ok(tree)
+    }
 
-    fn matcher(&mut self, t: char) -> Result<(), String> {
-        if self.lookahead == t {
-            self.lookahead = match self.string.next() {
-                Some(x) => x,
-                None => '\n'
-            };
+    // factor -> - factor {print('neg')} | ( expr ) | num {print(num)} | id {print(id)}
+    fn factor(&mut self) -> Result<Expr, TranslateError> {
+        match self.lookahead {
+            '-' => {
+                self.matcher('-')?;
+                let operand = self.factor()?;
+                self.emit("neg");
+                Ok(Expr::Neg(Box::new(operand)))
+            }
+            '(' => {
+                self.matcher('(')?;
+                let tree = self.sum()?;
+                self.matcher(')')?;
+                Ok(tree)
+            }
+            c if c.is_ascii_digit() && !self.at_end() => {
+                let number = self.lexeme(|x| x.is_ascii_digit());
+                self.emit(&number);
+                Ok(Expr::Number(number))
+            }
+            c if (c.is_alphabetic() || c == '_') && !self.at_end() => {
+                let start = self.position;
+                let id = self.lexeme(|x| x.is_alphanumeric() || x == '_');
+                // иначе постфиксная запись стала бы неоднозначной
+                if id == "neg" {
+                    let mut end = start;
+                    id.chars().for_each(|x| end.advance(x));
+                    let message = "Identifier 'neg' is reserved for unary minus".to_owned();
+                    return Err(TranslateError { message, span: Span::new(start, end), found: Some('n') });
+                }
+                self.emit(&id);
+                Ok(Expr::Id(id))
+            }
+            _ => Err(self.error("Expected number, identifier or '('")),
+        }
+    }
+
+    // самая длинная цепочка символов, подходящих под f, начиная с lookahead
+    fn lexeme<F: Fn(char) -> bool>(&mut self, f: F) -> String {
+        let mut result = String::new();
+        while f(self.lookahead) && !self.at_end() {
+            result.push(self.lookahead);
+            self.advance();
+        }
+        self.skip_whitespace();
+        result
+    }
+
+    fn advance(&mut self) {
+        self.position.advance(self.lookahead);
+        self.lookahead = self.string.next().unwrap_or('\0');
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.lookahead.is_whitespace() && !self.at_end() {
+            self.advance();
+        }
+    }
+
+    fn matcher(&mut self, t: char) -> Result<(), TranslateError> {
+        if self.lookahead == t && !self.at_end() {
+            self.advance();
+            self.skip_whitespace();
             Ok(())
         } else {
-            Err("Expected + or -".to_owned())
+            Err(self.error(&format!("Expected '{}'", t)))
         }
     }
 }
@@ -82,13 +195,47 @@ mod tests {
     #[test]
     fn simple_translator_test() {
         use super::SimpleTranslator;
-        let mut st = SimpleTranslator::from_string("2+3-4");
-        assert_eq!(st.expr(), Ok("23+4-".to_owned()));
-        st = SimpleTranslator::from_string("2+3-4-9");
-        assert_eq!(st.expr(), Ok("23+4-9-".to_owned()));
-        st = SimpleTranslator::from_string("2+3--4-9");
-        assert_eq!(st.expr(), Err("Expected digit".to_owned()));
-        st = SimpleTranslator::from_string("2+3-54-9");
-        assert_eq!(st.expr(), Err("Expected + or -".to_owned()));
-    }
-}
\ No newline at end of file
+        let translate = |x: &str| SimpleTranslator::from_string(x).expr().map_err(|e| e.to_string());
+        assert_eq!(translate("2+3-4"), Ok("2 3 + 4 -".to_owned()));
+        assert_eq!(translate("2+3-4-9"), Ok("2 3 + 4 - 9 -".to_owned()));
+        assert_eq!(translate("2+3-54-9"), Ok("2 3 + 54 - 9 -".to_owned()));
+        assert_eq!(translate("2+3--4-9"), Ok("2 3 + 4 neg - 9 -".to_owned()));
+        assert_eq!(translate(" count * (x1 - 10) % -(-y_2) / 4 \n"), Ok("count x1 10 - * y_2 neg neg % 4 /".to_owned()));
+        assert_eq!(translate("1 + 2 * 3"), Ok("1 2 3 * +".to_owned()));
+        assert_eq!(translate("(((7)))"), Ok("7".to_owned()));
+    }
+
+    #[test]
+    fn simple_translator_errors_test() {
+        use super::SimpleTranslator;
+        let translate = |x: &str| SimpleTranslator::from_string(x).expr().map_err(|e| e.to_string());
+        assert_eq!(translate("2 3"), Err("Expected operator at 1:3".to_owned()));
+        assert_eq!(translate("(1 + 2"), Err("Expected ')' at 1:7".to_owned()));
+        assert_eq!(translate("1 + 2)"), Err("Expected operator at 1:6".to_owned()));
+        assert_eq!(translate("2 * / 3"), Err("Expected number, identifier or '(' at 1:5".to_owned()));
+        assert_eq!(translate(""), Err("Expected number, identifier or '(' at 1:1".to_owned()));
+        assert_eq!(translate("a\n+ $"), Err("Expected number, identifier or '(' at 2:3".to_owned()));
+        assert_eq!(translate("1 + neg"), Err("Identifier 'neg' is reserved for unary minus at 1:5".to_owned()));
+    }
+
+    #[test]
+    fn simple_translator_diagnostic_test() {
+        use super::SimpleTranslator;
+        use crate::diagnostics::Diagnostic;
+        use crate::span::SourceMap;
+        let input = "1+2+3+4+5+6+7+";
+        let error = SimpleTranslator::from_string(input).expr().unwrap_err();
+        assert_eq!(error.found, None);
+        assert!(error.span.is_empty());
+        assert_eq!(Diagnostic::from(&error).render(&SourceMap::new(input)), "\
+error: Expected number, identifier or '('
+ --> 1:15
+  |
+1 | 1+2+3+4+5+6+7+
+  |               ^ found end of input
+");
+        let error = SimpleTranslator::from_string("1+2$3").expr().unwrap_err();
+        assert_eq!(error.found, Some('$'));
+        assert_eq!(error.span.text("1+2$3"), "$");
+    }
+}
@@ -1,65 +1,235 @@
-use std::collections::HashMap;
-use std::ops::Deref;
-use std::ops::DerefMut;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
 
+use crate::diagnostics::Diagnostic;
+use crate::span::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SymbolKind {
+    Variable,
+    Constant,
+    Parameter,
+    Function,
+    Type,
+}
+
+impl fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SymbolKind::Variable => "variable",
+            SymbolKind::Constant => "constant",
+            SymbolKind::Parameter => "parameter",
+            SymbolKind::Function => "function",
+            SymbolKind::Type => "type",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+///Запись таблицы символов
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub symbol_type: String,
+    /// Место объявления
+    pub span: Span,
+    /// Сколько памяти занимает; offset назначает Env при объявлении
+    pub width: usize,
+    pub offset: usize,
+    pub uses: usize,
+}
+
+impl Symbol {
+    pub fn new(name: &str, kind: SymbolKind, symbol_type: &str, width: usize, span: Span) -> Self {
+        Symbol { name: name.to_owned(), kind, symbol_type: symbol_type.to_owned(), span, width, offset: 0, uses: 0 }
+    }
+}
+
+///Повторное объявление в той же области; previous - первое объявление
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedeclarationError {
+    pub name: String,
+    pub span: Span,
+    pub previous: Span,
+}
+
+impl fmt::Display for RedeclarationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' is already declared in this scope at {}", self.name, self.span.start)
+    }
+}
+
+impl Error for RedeclarationError {}
+
+impl From<&RedeclarationError> for Diagnostic {
+    fn from(error: &RedeclarationError) -> Self {
+        Diagnostic::error(&format!("'{}' is already declared in this scope", error.name))
+            .with_label(error.span, "redeclared here")
+            .with_secondary_label(error.previous, "previously declared here")
+    }
+}
+
+///Таблица символов с цепочкой вложенных областей (раздел 2.7):
+///поиск идёт от текущей области к внешним через previous
 #[derive(Debug, Default, Eq, PartialEq)]
-struct Env {
-    table: HashMap<String, String>,
+pub struct Env {
+    table: BTreeMap<String, Symbol>,
     previous: Box<Option<Env>>,
+    /// Первый свободный адрес; вложенная область продолжает адреса внешней
+    next_offset: usize,
 }
 
-
 impl Env {
     pub fn new() -> Self {
         Env::default()
     }
 
-    pub fn insert(&mut self, key: &str, sym: String) {
-        if self.table.get(key).is_none() {
-            self.table.insert(key.to_owned(), sym);
-        } else {
-            panic!("This symbol already exists in current namespace");
-        };
+    pub fn enter_scope(&mut self) {
+        let next_offset = self.next_offset;
+        let outer = std::mem::take(self);
+        *self.previous = Some(outer);
+        self.next_offset = next_offset;
     }
 
-    pub fn get(&self, key: &str) -> Option<String> {
+    /// Символы закрытой области в порядке объявления; None, если область внешняя
+    pub fn exit_scope(&mut self) -> Option<Vec<Symbol>> {
+        let outer = self.previous.take()?;
+        let inner = std::mem::replace(self, outer);
+        let mut symbols: Vec<Symbol> = inner.table.into_values().collect();
+        symbols.sort_by_key(|x| x.span);
+        Some(symbols)
+    }
+
+    /// Число областей; у внешней - 1
+    pub fn depth(&self) -> usize {
+        1 + self.previous.as_ref().as_ref().map_or(0, |x| x.depth())
+    }
+
+    pub fn next_offset(&self) -> usize {
+        self.next_offset
+    }
+
+    /// Символы текущей области в порядке объявления
+    pub fn symbols(&self) -> Vec<&Symbol> {
+        let mut symbols: Vec<&Symbol> = self.table.values().collect();
+        symbols.sort_by_key(|x| x.span);
+        symbols
+    }
+
+    pub fn declare(&mut self, mut symbol: Symbol) -> Result<&Symbol, RedeclarationError> {
+        if let Some(previous) = self.table.get(&symbol.name) {
+            return Err(RedeclarationError { name: symbol.name, span: symbol.span, previous: previous.span });
+        }
+        symbol.offset = self.next_offset;
+        self.next_offset += symbol.width;
+        let name = symbol.name.to_owned();
+        Ok(self.table.entry(name).or_insert(symbol))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Symbol> {
         match self.table.get(key) {
-            Some(x) => Some(x.to_owned()),
-            None => match self.previous.deref() {
+            Some(x) => Some(x),
+            None => match self.previous.as_ref() {
                 Some(env) => env.get(key),
                 None => None
             }
         }
     }
+
+    /// То же, что get, но считает использование
+    pub fn use_symbol(&mut self, key: &str) -> Option<&Symbol> {
+        match self.table.get_mut(key) {
+            Some(x) => {
+                x.uses += 1;
+                Some(x)
+            }
+            None => match self.previous.as_mut() {
+                Some(env) => env.use_symbol(key),
+                None => None
+            }
+        }
+    }
+
+    /// Объявлен ли символ в текущей области, не во внешних
+    pub fn is_local(&self, key: &str) -> bool {
+        self.table.contains_key(key)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::span::SourceMap;
+
+    fn variable(name: &str, symbol_type: &str, width: usize) -> Symbol {
+        Symbol::new(name, SymbolKind::Variable, symbol_type, width, Span::default())
+    }
+
     #[test]
     fn new_env_test() {
-        use super::Env;
         let e = Env::new();
-        let prev = e.previous;
-        let expected: Option<Env> = None;
-        assert_eq!((*prev), None::<Env>);
+        assert_eq!(*e.previous, None::<Env>);
         assert_eq!(e.table.len(), 0);
+        assert_eq!(e.depth(), 1);
     }
 
     #[test]
     fn insert_get_test() {
-        use super::Env;
         let mut e = Env::new();
-        e.insert("x", "Char".to_owned());
-        e.insert("y", "Int".to_owned());
-        assert_eq!(e.get("x").unwrap(), "Char".to_owned());
-        assert_eq!(e.get("s"), None::<String>);
+        e.declare(variable("x", "Char", 1)).unwrap();
+        e.declare(variable("y", "Int", 4)).unwrap();
+        assert_eq!(e.get("x").map(|x| &x.symbol_type[..]), Some("Char"));
+        assert_eq!(e.get("y").map(|x| (x.offset, x.kind)), Some((1, SymbolKind::Variable)));
+        assert_eq!(e.get("s"), None);
     }
+
+    #[test]
+    fn redeclaration_test() {
+        let map = SourceMap::new("int x;\nchar x;");
+        let mut e = Env::new();
+        e.declare(Symbol::new("x", SymbolKind::Variable, "int", 4, map.span(4, 5))).unwrap();
+        let error = e.declare(Symbol::new("x", SymbolKind::Variable, "char", 1, map.span(12, 13))).unwrap_err();
+        assert_eq!(error.to_string(), "'x' is already declared in this scope at 2:6");
+        assert_eq!(error.previous, map.span(4, 5));
+        // неудачное объявление ничего не меняет
+        assert_eq!(e.get("x").map(|x| &x.symbol_type[..]), Some("int"));
+        assert_eq!(e.next_offset(), 4);
+        assert_eq!(Diagnostic::from(&error).render(&map), "\
+error: 'x' is already declared in this scope
+ --> 2:6
+  |
+1 | int x;
+  |     - previously declared here
+2 | char x;
+  |      ^ redeclared here
+");
+    }
+
     #[test]
-    #[should_panic(expected = "This symbol already exists in current namespace")]
-    fn get_test_panic() {
-        use super::Env;
+    fn scopes_test() {
         let mut e = Env::new();
-        e.insert("x", "Char".to_owned());
-        e.insert("x", "Int".to_owned());
+        e.declare(variable("x", "int", 4)).unwrap();
+        e.declare(variable("y", "int", 4)).unwrap();
+        e.enter_scope();
+        assert_eq!(e.depth(), 2);
+        // внутреннее объявление скрывает внешнее
+        e.declare(variable("x", "char", 1)).unwrap();
+        assert!(e.is_local("x") && !e.is_local("y"));
+        assert_eq!(e.get("x").map(|x| (&x.symbol_type[..], x.offset)), Some(("char", 8)));
+        assert_eq!(e.get("y").map(|x| &x.symbol_type[..]), Some("int"));
+        e.use_symbol("x");
+        e.use_symbol("y");
+        e.use_symbol("y");
+        let closed = e.exit_scope().unwrap();
+        assert_eq!(closed.iter().map(|x| (&x.name[..], x.uses)).collect::<Vec<_>>(), vec![("x", 1)]);
+        assert_eq!(e.get("x").map(|x| (&x.symbol_type[..], x.uses)), Some(("int", 0)));
+        assert_eq!(e.get("y").map(|x| x.uses), Some(2));
+        // адреса закрытой области используются снова
+        assert_eq!(e.next_offset(), 8);
+        assert_eq!(e.exit_scope(), None);
+        assert_eq!(e.symbols().len(), 2);
+        assert_eq!(e.use_symbol("z"), None);
     }
-}
\ No newline at end of file
+}
@@ -1,29 +1,16 @@
-use std::collections::BTreeMap;
-
-mod extended_lexer;
-
-fn aho_corasick_failure(input: &str) -> String{
-    let input_as_chars: Vec<char> = input.chars().collect();
-    let mut result = BTreeMap::new();
-    let mut t = 0;
-    result.insert(1, 0);
-
-    for s in 1..input.len() {
-        while t > 0 && input_as_chars[s] != input_as_chars[t] {
-            t = *(result.get(&t).unwrap());
-        }
-        if input_as_chars[s] == input_as_chars[t] {
-            t += 1;
-            result.insert(s + 1, t);
-        } else {
-            result.insert(s + 1, 0);
-        }
-    };
-    result.iter().fold("".to_string(),|accum, next|{
-        accum+&next.1.to_string()
-    })
-
+pub mod aho_corasick;
+pub mod extended_lexer;
+pub mod regex_syntax;
+pub mod nfa;
+pub mod dfa;
+pub mod direct_dfa;
+pub mod lex_generator;
+pub mod extended_lexer_gen;
+pub mod kmp;
 
+/// Функция отказов ключевого слова: f(s) для s = 1..=n
+pub fn aho_corasick_failure(input: &str) -> Vec<usize> {
+    kmp::failure(&input.chars().collect::<Vec<char>>())
 }
 
 #[cfg(test)]
@@ -31,9 +18,12 @@ mod tests {
     #[test]
     fn aho_corasick_failure_test() {
         use super::aho_corasick_failure;
-        assert_eq!(aho_corasick_failure("ababaa"),"001231");
-        assert_eq!(aho_corasick_failure("abababaab"),"001234512");
-        assert_eq!(aho_corasick_failure("aaaaaa"),"012345");
-        assert_eq!(aho_corasick_failure("abbaabb"),"0001123");
+        assert_eq!(aho_corasick_failure("ababaa"), vec![0, 0, 1, 2, 3, 1]);
+        assert_eq!(aho_corasick_failure("abababaab"), vec![0, 0, 1, 2, 3, 4, 5, 1, 2]);
+        assert_eq!(aho_corasick_failure("aaaaaa"), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(aho_corasick_failure("abbaabb"), vec![0, 0, 0, 1, 1, 2, 3]);
+        // значения от 10 и больше
+        assert_eq!(aho_corasick_failure("aaaaaaaaaaaa"), (0..12).collect::<Vec<usize>>());
+        assert_eq!(aho_corasick_failure("жёжёж"), vec![0, 0, 1, 2, 3]);
     }
 }
\ No newline at end of file
@@ -0,0 +1,191 @@
+use std::collections::{BTreeMap, VecDeque};
+
+//Алгоритм Ахо-Корасик (упражнения 3.4.10-3.4.12): бор ключевых слов с функциями
+//goto, failure и output находит все вхождения всех слов за один проход по тексту.
+//Для одного слова функция failure совпадает с функцией отказов КМП.
+
+///Вхождение слова с номером pattern; start и end - смещения в байтах, end не включается
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Match {
+    pub pattern: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AhoCorasick {
+    patterns: Vec<String>,
+    /// Переходы бора; состояние 0 - корень
+    goto: Vec<BTreeMap<char, usize>>,
+    failure: Vec<usize>,
+    /// Слова, оканчивающиеся в состоянии, включая найденные по цепочке failure; длинные первыми
+    output: Vec<Vec<usize>>,
+}
+
+impl AhoCorasick {
+    /// Пустые слова пропускаются: они ничего не находят
+    pub fn new<I, S>(patterns: I) -> Self
+        where I: IntoIterator<Item=S>, S: AsRef<str> {
+        let patterns: Vec<String> = patterns.into_iter().map(|x| x.as_ref().to_owned()).collect();
+        let mut goto = vec![BTreeMap::new()];
+        let mut output = vec![Vec::new()];
+        for (id, pattern) in patterns.iter().enumerate().filter(|(_, x)| !x.is_empty()) {
+            let mut state = 0;
+            for c in pattern.chars() {
+                state = match goto[state].get(&c) {
+                    Some(next) => *next,
+                    None => {
+                        goto.push(BTreeMap::new());
+                        output.push(Vec::new());
+                        let next = goto.len() - 1;
+                        goto[state].insert(c, next);
+                        next
+                    }
+                };
+            }
+            output[state].push(id);
+        }
+
+        // обход в ширину: failure вычисляется по уже известным значениям для меньших глубин
+        let mut failure = vec![0; goto.len()];
+        let mut queue: VecDeque<usize> = goto[0].values().copied().collect();
+        while let Some(state) = queue.pop_front() {
+            for (c, next) in goto[state].clone() {
+                queue.push_back(next);
+                let mut fallback = failure[state];
+                while fallback != 0 && !goto[fallback].contains_key(&c) {
+                    fallback = failure[fallback];
+                }
+                failure[next] = goto[fallback].get(&c).copied().unwrap_or(0);
+                let inherited = output[failure[next]].clone();
+                output[next].extend(inherited);
+            }
+        }
+        AhoCorasick { patterns, goto, failure, output }
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    pub fn states_count(&self) -> usize {
+        self.goto.len()
+    }
+
+    /// None - переход не определён (для корня это значит остаться в корне)
+    pub fn goto(&self, state: usize, c: char) -> Option<usize> {
+        self.goto[state].get(&c).copied()
+    }
+
+    pub fn failure(&self, state: usize) -> usize {
+        self.failure[state]
+    }
+
+    pub fn output(&self, state: usize) -> &[usize] {
+        &self.output[state]
+    }
+
+    /// Переход автомата с учётом failure
+    pub fn next_state(&self, mut state: usize, c: char) -> usize {
+        loop {
+            if let Some(next) = self.goto(state, c) {
+                return next;
+            }
+            if state == 0 {
+                return 0;
+            }
+            state = self.failure[state];
+        }
+    }
+
+    /// Все вхождения, в том числе перекрывающиеся, в порядке концов;
+    /// при общем конце длинное слово раньше короткого
+    pub fn find_all(&self, text: &str) -> Vec<Match> {
+        let mut result = Vec::new();
+        let mut state = 0;
+        for (i, c) in text.char_indices() {
+            state = self.next_state(state, c);
+            let end = i + c.len_utf8();
+            for pattern in &self.output[state] {
+                result.push(Match { pattern: *pattern, start: end - self.patterns[*pattern].len(), end });
+            }
+        }
+        result
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        let mut state = 0;
+        text.chars().any(|c| {
+            state = self.next_state(state, c);
+            !self.output[state].is_empty()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(matcher: &AhoCorasick, text: &str) -> Vec<(usize, usize, usize)> {
+        matcher.find_all(text).iter().map(|x| (x.pattern, x.start, x.end)).collect()
+    }
+
+    #[test]
+    fn trie_test() {
+        // пример из книги: he, she, his, hers
+        let matcher = AhoCorasick::new(["he", "she", "his", "hers"]);
+        assert_eq!(matcher.states_count(), 10);
+        let h = matcher.goto(0, 'h').unwrap();
+        let s = matcher.goto(0, 's').unwrap();
+        let sh = matcher.goto(s, 'h').unwrap();
+        let she = matcher.goto(sh, 'e').unwrap();
+        let he = matcher.goto(h, 'e').unwrap();
+        assert_eq!(matcher.failure(sh), h);
+        assert_eq!(matcher.failure(she), he);
+        assert_eq!(matcher.output(she), &[1, 0]);
+        assert_eq!(matcher.goto(0, 'x'), None);
+        assert_eq!(matcher.next_state(she, 'r'), matcher.goto(he, 'r').unwrap());
+    }
+
+    #[test]
+    fn find_all_test() {
+        let matcher = AhoCorasick::new(vec!["he", "she", "his", "hers"]);
+        assert_eq!(found(&matcher, "ushers"), vec![(1, 1, 4), (0, 2, 4), (3, 2, 6)]);
+        assert_eq!(found(&matcher, "ahishers"), vec![(2, 1, 4), (1, 3, 6), (0, 4, 6), (3, 4, 8)]);
+        assert!(found(&matcher, "xyz").is_empty());
+        assert!(matcher.is_match("this"));
+        assert!(!matcher.is_match("hxs"));
+
+        let matcher = AhoCorasick::new(["a", "aa", "", "aa", "ба"]);
+        assert_eq!(found(&matcher, "aaa"), vec![(0, 0, 1), (1, 0, 2), (3, 0, 2), (0, 1, 2), (1, 1, 3), (3, 1, 3), (0, 2, 3)]);
+        // смещения в байтах
+        let text = "ааба";
+        assert_eq!(found(&matcher, text), vec![(4, 4, 8)]);
+        assert_eq!(&text[4..8], "ба");
+    }
+
+    #[test]
+    fn single_keyword_is_kmp_test() {
+        use crate::part3::aho_corasick_failure;
+        for keyword in &["ababaa", "abababaab", "aaaaaa", "abbaabb"] {
+            let matcher = AhoCorasick::new([keyword]);
+            // состояние s бора для одного слова - префикс длины s
+            let failure: Vec<usize> = (1..matcher.states_count()).map(|s| matcher.failure(s)).collect();
+            assert_eq!(failure, aho_corasick_failure(keyword));
+        }
+    }
+
+    #[test]
+    fn reserved_words_test() {
+        // ключевые слова за один проход; вхождения внутри идентификаторов отбрасываются
+        let keywords = ["if", "then", "else", "for", "do", "while"];
+        let matcher = AhoCorasick::new(keywords);
+        let text = "if format then doit; else while x do fork";
+        let is_word = |i: usize| text[i..].chars().next().is_some_and(|x| x.is_alphanumeric());
+        let words: Vec<&str> = matcher.find_all(text).into_iter()
+            .filter(|x| (x.start == 0 || !is_word(x.start - 1)) && !is_word(x.end))
+            .map(|x| keywords[x.pattern])
+            .collect();
+        assert_eq!(words, vec!["if", "then", "else", "while", "do"]);
+    }
+}
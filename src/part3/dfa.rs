@@ -0,0 +1,365 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use super::nfa::{Match, Nfa};
+use super::regex_syntax::{next_char, prev_char, CharClass};
+
+///Детерминированный конечный автомат
+///
+/// Входной алфавит разбит на непересекающиеся интервалы символов,
+/// переходы хранятся таблицей состояние x интервал. Отсутствующий переход
+/// ведёт в неявное мёртвое состояние.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dfa {
+    alphabet: Vec<(char, char)>,
+    transitions: Vec<Vec<Option<usize>>>,
+    start: usize,
+    accepting: BTreeMap<usize, usize>,
+}
+
+/// Разбиение всех диапазонов на непересекающиеся интервалы
+pub fn partition_alphabet<'a, I: IntoIterator<Item=&'a CharClass>>(classes: I) -> Vec<(char, char)> {
+    let mut bounds = BTreeSet::new();
+    let mut ranges = Vec::new();
+    for class in classes {
+        for (low, high) in class.ranges() {
+            bounds.insert(*low);
+            if let Some(next) = next_char(*high) {
+                bounds.insert(next);
+            }
+            ranges.push((*low, *high));
+        }
+    }
+    let bounds: Vec<char> = bounds.into_iter().collect();
+    let mut result = Vec::new();
+    for (i, low) in bounds.iter().enumerate() {
+        let high = match bounds.get(i + 1) {
+            Some(next) => prev_char(*next).unwrap_or(*low),
+            None => char::MAX,
+        };
+        if ranges.iter().any(|(a, b)| a <= low && low <= b) {
+            result.push((*low, high));
+        }
+    }
+    result
+}
+
+impl Dfa {
+    pub fn compile(pattern: &str) -> Result<Dfa, String> {
+        Ok(Dfa::from_nfa(&Nfa::compile(pattern)?).minimize())
+    }
+
+    /// Один минимальный автомат для нескольких шаблонов; приоритет - по порядку
+    pub fn from_patterns(patterns: &[&str]) -> Result<Dfa, String> {
+        Ok(Dfa::from_nfa(&Nfa::from_patterns(patterns)?).minimize())
+    }
+
+    ///Построение подмножеств (алгоритм 3.20)
+    pub fn from_nfa(nfa: &Nfa) -> Dfa {
+        let alphabet = partition_alphabet(nfa.states().iter()
+            .flat_map(|state| state.transitions.iter().map(|(class, _)| class)));
+        let initial = nfa.initial();
+        let mut states = vec![initial.clone()];
+        let mut index: BTreeMap<BTreeSet<usize>, usize> = BTreeMap::new();
+        index.insert(initial, 0);
+        let mut transitions = Vec::new();
+        let mut current = 0;
+        while current < states.len() {
+            let mut row = Vec::with_capacity(alphabet.len());
+            for (low, _) in &alphabet {
+                let target = nfa.epsilon_closure(&nfa.move_on(&states[current], *low));
+                if target.is_empty() {
+                    row.push(None);
+                    continue;
+                }
+                let id = match index.get(&target) {
+                    Some(id) => *id,
+                    None => {
+                        index.insert(target.clone(), states.len());
+                        states.push(target);
+                        states.len() - 1
+                    }
+                };
+                row.push(Some(id));
+            }
+            transitions.push(row);
+            current += 1;
+        }
+        let accepting = states.iter().enumerate()
+            .filter_map(|(i, state)| nfa.accepted(state).map(|pattern| (i, pattern)))
+            .collect();
+        Dfa { alphabet, transitions, start: 0, accepting }
+    }
+
+    ///Минимизация алгоритмом Хопкрофта
+    ///
+    /// Начальное разбиение: недопускающие состояния и по группе на каждый шаблон,
+    /// так что состояния разных токенов никогда не сливаются.
+    pub fn minimize(&self) -> Dfa {
+        let dead = self.transitions.len();
+        let count = dead + 1;
+        let target = |state: usize, symbol: usize| -> usize {
+            if state == dead {
+                dead
+            } else {
+                self.transitions[state][symbol].unwrap_or(dead)
+            }
+        };
+        // обратные переходы
+        let mut inverse = vec![vec![Vec::new(); count]; self.alphabet.len()];
+        for state in 0..count {
+            for (symbol, inverse_symbol) in inverse.iter_mut().enumerate() {
+                inverse_symbol[target(state, symbol)].push(state);
+            }
+        }
+
+        let mut groups: BTreeMap<Option<usize>, BTreeSet<usize>> = BTreeMap::new();
+        for state in 0..count {
+            groups.entry(self.accepting.get(&state).cloned()).or_default().insert(state);
+        }
+        let mut blocks: Vec<BTreeSet<usize>> = groups.into_values().collect();
+        let mut block_of = vec![0; count];
+        for (id, block) in blocks.iter().enumerate() {
+            for state in block {
+                block_of[*state] = id;
+            }
+        }
+        let mut work: Vec<usize> = (0..blocks.len()).collect();
+        let mut in_work = vec![true; blocks.len()];
+
+        while let Some(splitter) = work.pop() {
+            in_work[splitter] = false;
+            let splitter_states: Vec<usize> = blocks[splitter].iter().cloned().collect();
+            for inverse_symbol in &inverse {
+                let x: BTreeSet<usize> = splitter_states.iter()
+                    .flat_map(|state| inverse_symbol[*state].iter().cloned())
+                    .collect();
+                let touched: BTreeSet<usize> = x.iter().map(|state| block_of[*state]).collect();
+                for y in touched {
+                    let inside: BTreeSet<usize> = blocks[y].intersection(&x).cloned().collect();
+                    if inside.len() == blocks[y].len() {
+                        continue;
+                    }
+                    let outside: BTreeSet<usize> = blocks[y].difference(&x).cloned().collect();
+                    let new_id = blocks.len();
+                    for state in &outside {
+                        block_of[*state] = new_id;
+                    }
+                    let smaller_is_inside = inside.len() <= outside.len();
+                    blocks[y] = inside;
+                    blocks.push(outside);
+                    in_work.push(false);
+                    if in_work[y] {
+                        work.push(new_id);
+                        in_work[new_id] = true;
+                    } else {
+                        let chosen = if smaller_is_inside { y } else { new_id };
+                        work.push(chosen);
+                        in_work[chosen] = true;
+                    }
+                }
+            }
+        }
+
+        // новые номера - в порядке обхода в ширину от начального блока,
+        // поэтому эквивалентные автоматы минимизируются в одинаковые таблицы
+        let dead_block = block_of[dead];
+        let mut number = vec![None; blocks.len()];
+        let mut order = vec![block_of[self.start]];
+        number[block_of[self.start]] = Some(0);
+        let mut current = 0;
+        while current < order.len() {
+            let representative = *blocks[order[current]].iter().next().unwrap_or(&dead);
+            for symbol in 0..self.alphabet.len() {
+                let next = block_of[target(representative, symbol)];
+                if next != dead_block && number[next].is_none() {
+                    number[next] = Some(order.len());
+                    order.push(next);
+                }
+            }
+            current += 1;
+        }
+        let transitions = order.iter()
+            .map(|block| {
+                let representative = *blocks[*block].iter().next().unwrap_or(&dead);
+                (0..self.alphabet.len())
+                    .map(|symbol| number[block_of[target(representative, symbol)]])
+                    .collect()
+            })
+            .collect();
+        let accepting = self.accepting.iter()
+            .filter_map(|(state, pattern)| number[block_of[*state]].map(|id| (id, *pattern)))
+            .collect();
+        let mut result = Dfa { alphabet: self.alphabet.clone(), transitions, start: 0, accepting };
+        result.drop_unused_symbols();
+        result
+    }
+
+    // интервалы, по которым после минимизации нет ни одного перехода
+    fn drop_unused_symbols(&mut self) {
+        let used: Vec<bool> = (0..self.alphabet.len())
+            .map(|symbol| self.transitions.iter().any(|row| row[symbol].is_some()))
+            .collect();
+        self.alphabet = self.alphabet.iter().zip(&used).filter(|(_, u)| **u).map(|(a, _)| *a).collect();
+        for row in &mut self.transitions {
+            *row = row.iter().zip(&used).filter(|(_, u)| **u).map(|(t, _)| *t).collect();
+        }
+    }
+
+    pub(crate) fn from_parts(alphabet: Vec<(char, char)>,
+                             transitions: Vec<Vec<Option<usize>>>,
+                             start: usize,
+                             accepting: BTreeMap<usize, usize>) -> Dfa {
+        Dfa { alphabet, transitions, start, accepting }
+    }
+
+    pub fn states_count(&self) -> usize {
+        self.transitions.len()
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn accepting(&self) -> &BTreeMap<usize, usize> {
+        &self.accepting
+    }
+
+    pub fn alphabet(&self) -> &[(char, char)] {
+        &self.alphabet
+    }
+
+    /// Строка на состояние, столбец на интервал алфавита
+    pub fn transitions(&self) -> &[Vec<Option<usize>>] {
+        &self.transitions
+    }
+
+    fn symbol(&self, c: char) -> Option<usize> {
+        let index = match self.alphabet.binary_search_by(|(low, _)| low.cmp(&c)) {
+            Ok(index) => index,
+            Err(0) => return None,
+            Err(index) => index - 1,
+        };
+        if c <= self.alphabet[index].1 {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    pub fn next(&self, state: usize, c: char) -> Option<usize> {
+        self.symbol(c).and_then(|symbol| self.transitions[state][symbol])
+    }
+
+    pub fn is_match(&self, input: &str) -> bool {
+        let mut state = self.start;
+        for c in input.chars() {
+            state = match self.next(state, c) {
+                Some(next) => next,
+                None => return false,
+            };
+        }
+        self.accepting.contains_key(&state)
+    }
+
+    /// Максимальный жадный префикс, допускаемый автоматом
+    pub fn longest_match(&self, input: &str) -> Option<Match> {
+        self.longest_match_chars(input.chars())
+    }
+
+    /// То же для произвольного потока символов; end - длина в байтах UTF-8
+    pub fn longest_match_chars<I: IntoIterator<Item=char>>(&self, input: I) -> Option<Match> {
+        let mut state = self.start;
+        let mut last = self.accepting.get(&state).map(|pattern| Match { pattern: *pattern, end: 0 });
+        let mut position = 0;
+        for c in input {
+            state = match self.next(state, c) {
+                Some(next) => next,
+                None => break,
+            };
+            position += c.len_utf8();
+            if let Some(pattern) = self.accepting.get(&state) {
+                last = Some(Match { pattern: *pattern, end: position });
+            }
+        }
+        last
+    }
+}
+
+impl fmt::Display for Dfa {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (state, row) in self.transitions.iter().enumerate() {
+            let start = if state == self.start { "->" } else { "  " };
+            match self.accepting.get(&state) {
+                Some(pattern) => write!(f, "{}{} [{}]:", start, state, pattern)?,
+                None => write!(f, "{}{}:", start, state)?,
+            }
+            for (symbol, target) in row.iter().enumerate() {
+                if let Some(target) = target {
+                    let (low, high) = self.alphabet[symbol];
+                    write!(f, " {}->{}", CharClass::new(vec![(low, high)]), target)?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partition_alphabet_test() {
+        let classes = vec![CharClass::new(vec![('a', 'z')]), CharClass::single('e'), CharClass::new(vec![('0', '9')])];
+        assert_eq!(partition_alphabet(&classes),
+                   vec![('0', '9'), ('a', 'd'), ('e', 'e'), ('f', 'z')]);
+    }
+
+    #[test]
+    fn subset_construction_test() {
+        // пример 3.21
+        let dfa = Dfa::from_nfa(&Nfa::compile("(a|b)*abb").unwrap());
+        assert_eq!(dfa.states_count(), 5);
+        assert_eq!(dfa.accepting().len(), 1);
+        assert!(dfa.is_match("aabb"));
+        assert!(!dfa.is_match("abab"));
+    }
+
+    #[test]
+    fn minimize_test() {
+        // пример 3.40: A и C сливаются
+        let dfa = Dfa::from_nfa(&Nfa::compile("(a|b)*abb").unwrap()).minimize();
+        assert_eq!(dfa.states_count(), 4);
+        assert_eq!(dfa.to_string(), "->0: a->1 b->0\n  1: a->1 b->2\n  2: a->1 b->3\n  3 [0]: a->1 b->0\n");
+        assert!(dfa.is_match("babbabb"));
+        assert!(!dfa.is_match("abba"));
+
+        let dfa = Dfa::compile("(a|b)*|(a*b*)*").unwrap();
+        assert_eq!(dfa.states_count(), 1);
+        assert!(dfa.is_match(""));
+    }
+
+    #[test]
+    fn minimize_keeps_priorities_test() {
+        let dfa = Dfa::from_patterns(&["IF", "[A-Z]+", "[0-9]+"]).unwrap();
+        assert_eq!(dfa.longest_match("IF x"), Some(Match { pattern: 0, end: 2 }));
+        assert_eq!(dfa.longest_match("I"), Some(Match { pattern: 1, end: 1 }));
+        assert_eq!(dfa.longest_match("IFS"), Some(Match { pattern: 1, end: 3 }));
+        assert_eq!(dfa.longest_match("42IF"), Some(Match { pattern: 2, end: 2 }));
+        assert_eq!(dfa.longest_match("-"), None);
+        let accepting: BTreeSet<usize> = dfa.accepting().values().cloned().collect();
+        assert_eq!(accepting.len(), 3);
+    }
+
+    #[test]
+    fn nfa_and_dfa_agree_test() {
+        let patterns = [r"\d+(\.\d+)?([Ee][+-]?\d+)?", "[A-Za-z][A-Za-z0-9]*", "[!=]=|<=?|>=?"];
+        let nfa = Nfa::from_patterns(&patterns).unwrap();
+        let dfa = Dfa::from_patterns(&patterns).unwrap();
+        for input in &["142e-1>=", "12.x", "s241 ", ">=4", "<me", "!x", "==", "1e5e"] {
+            assert_eq!(nfa.longest_match(input), dfa.longest_match(input), "{}", input);
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use super::dfa::{partition_alphabet, Dfa};
+use super::regex_syntax::{CharClass, Regex};
+
+///Узел синтаксического дерева пополненного выражения (r)#
+///
+/// Позиции нумеруются с единицы слева направо, как в примере 3.34;
+/// концевой маркер # получает последнюю позицию.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Empty,
+    Leaf(usize),
+    Concat(Box<Node>, Box<Node>),
+    Union(Box<Node>, Box<Node>),
+    Star(Box<Node>),
+}
+
+///Таблица followpos и всё, что нужно для построения ДКА (алгоритм 3.36)
+#[derive(Debug, Clone)]
+pub struct FollowPos {
+    tree: Node,
+    /// Класс символов каждой позиции; у концевого маркера None
+    positions: Vec<Option<CharClass>>,
+    followpos: Vec<BTreeSet<usize>>,
+}
+
+impl FollowPos {
+    pub fn new(regex: &Regex) -> Self {
+        let mut positions = vec![None];
+        let body = build(regex, &mut positions);
+        positions.push(None);
+        let end = positions.len() - 1;
+        let tree = Node::Concat(Box::new(body), Box::new(Node::Leaf(end)));
+        let mut followpos = vec![BTreeSet::new(); positions.len()];
+        compute_followpos(&tree, &mut followpos);
+        FollowPos { tree, positions, followpos }
+    }
+
+    /// Позиция концевого маркера #
+    pub fn end_marker(&self) -> usize {
+        self.positions.len() - 1
+    }
+
+    pub fn positions(&self) -> usize {
+        self.positions.len() - 1
+    }
+
+    pub fn symbol(&self, position: usize) -> Option<&CharClass> {
+        self.positions[position].as_ref()
+    }
+
+    pub fn followpos(&self, position: usize) -> &BTreeSet<usize> {
+        &self.followpos[position]
+    }
+
+    pub fn nullable(&self) -> bool {
+        nullable(&self.tree)
+    }
+
+    /// firstpos корня - начальное состояние ДКА
+    pub fn firstpos(&self) -> BTreeSet<usize> {
+        firstpos(&self.tree)
+    }
+
+    pub fn lastpos(&self) -> BTreeSet<usize> {
+        lastpos(&self.tree)
+    }
+
+    /// ДКА прямо из followpos: состояния - множества позиций
+    pub fn to_dfa(&self) -> Dfa {
+        let alphabet = partition_alphabet(self.positions.iter().flatten());
+        let start = self.firstpos();
+        let mut states = vec![start.clone()];
+        let mut index: BTreeMap<BTreeSet<usize>, usize> = BTreeMap::new();
+        index.insert(start, 0);
+        let mut transitions = Vec::new();
+        let mut current = 0;
+        while current < states.len() {
+            let mut row = Vec::with_capacity(alphabet.len());
+            for (low, _) in &alphabet {
+                let target: BTreeSet<usize> = states[current].iter()
+                    .filter(|p| self.positions[**p].as_ref().map(|class| class.contains(*low)).unwrap_or(false))
+                    .flat_map(|p| self.followpos[*p].iter().cloned())
+                    .collect();
+                if target.is_empty() {
+                    row.push(None);
+                    continue;
+                }
+                let id = match index.get(&target) {
+                    Some(id) => *id,
+                    None => {
+                        index.insert(target.clone(), states.len());
+                        states.push(target);
+                        states.len() - 1
+                    }
+                };
+                row.push(Some(id));
+            }
+            transitions.push(row);
+            current += 1;
+        }
+        let end = self.end_marker();
+        let accepting = states.iter().enumerate()
+            .filter(|(_, state)| state.contains(&end))
+            .map(|(i, _)| (i, 0))
+            .collect();
+        Dfa::from_parts(alphabet, transitions, 0, accepting)
+    }
+}
+
+impl fmt::Display for FollowPos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for position in 1..self.positions.len() {
+            let symbol = match &self.positions[position] {
+                Some(class) => class.to_string(),
+                None => "#".to_owned(),
+            };
+            let follow: Vec<String> = self.followpos[position].iter().map(|x| x.to_string()).collect();
+            writeln!(f, "{} {}: {{{}}}", position, symbol, follow.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// r+ = r r*, r? = r | ε: позиции внутри r копируются
+fn build(regex: &Regex, positions: &mut Vec<Option<CharClass>>) -> Node {
+    match regex {
+        Regex::Empty => Node::Empty,
+        Regex::Class(class) => {
+            positions.push(Some(class.clone()));
+            Node::Leaf(positions.len() - 1)
+        }
+        Regex::Concat(a, b) => Node::Concat(Box::new(build(a, positions)), Box::new(build(b, positions))),
+        Regex::Union(a, b) => Node::Union(Box::new(build(a, positions)), Box::new(build(b, positions))),
+        Regex::Star(a) => Node::Star(Box::new(build(a, positions))),
+        Regex::Plus(a) => {
+            let first = build(a, positions);
+            let rest = build(a, positions);
+            Node::Concat(Box::new(first), Box::new(Node::Star(Box::new(rest))))
+        }
+        Regex::Optional(a) => Node::Union(Box::new(build(a, positions)), Box::new(Node::Empty)),
+    }
+}
+
+fn nullable(node: &Node) -> bool {
+    match node {
+        Node::Empty | Node::Star(_) => true,
+        Node::Leaf(_) => false,
+        Node::Concat(a, b) => nullable(a) && nullable(b),
+        Node::Union(a, b) => nullable(a) || nullable(b),
+    }
+}
+
+fn firstpos(node: &Node) -> BTreeSet<usize> {
+    match node {
+        Node::Empty => BTreeSet::new(),
+        Node::Leaf(position) => vec![*position].into_iter().collect(),
+        Node::Union(a, b) => firstpos(a).union(&firstpos(b)).cloned().collect(),
+        Node::Concat(a, b) => if nullable(a) {
+            firstpos(a).union(&firstpos(b)).cloned().collect()
+        } else {
+            firstpos(a)
+        },
+        Node::Star(a) => firstpos(a),
+    }
+}
+
+fn lastpos(node: &Node) -> BTreeSet<usize> {
+    match node {
+        Node::Empty => BTreeSet::new(),
+        Node::Leaf(position) => vec![*position].into_iter().collect(),
+        Node::Union(a, b) => lastpos(a).union(&lastpos(b)).cloned().collect(),
+        Node::Concat(a, b) => if nullable(b) {
+            lastpos(a).union(&lastpos(b)).cloned().collect()
+        } else {
+            lastpos(b)
+        },
+        Node::Star(a) => lastpos(a),
+    }
+}
+
+fn compute_followpos(node: &Node, followpos: &mut Vec<BTreeSet<usize>>) {
+    match node {
+        Node::Empty | Node::Leaf(_) => {}
+        Node::Union(a, b) => {
+            compute_followpos(a, followpos);
+            compute_followpos(b, followpos);
+        }
+        Node::Concat(a, b) => {
+            compute_followpos(a, followpos);
+            compute_followpos(b, followpos);
+            let first = firstpos(b);
+            for i in lastpos(a) {
+                followpos[i].extend(first.iter().cloned());
+            }
+        }
+        Node::Star(a) => {
+            compute_followpos(a, followpos);
+            let first = firstpos(a);
+            for i in lastpos(a) {
+                followpos[i].extend(first.iter().cloned());
+            }
+        }
+    }
+}
+
+impl Dfa {
+    pub fn direct(pattern: &str) -> Result<Dfa, String> {
+        Ok(FollowPos::new(&Regex::parse(pattern)?).to_dfa())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part3::nfa::Nfa;
+
+    fn set(items: &[usize]) -> BTreeSet<usize> {
+        items.iter().cloned().collect()
+    }
+
+    #[test]
+    fn followpos_test() {
+        // пример 3.35, рис. 3.60
+        let table = FollowPos::new(&Regex::parse("(a|b)*abb").unwrap());
+        assert_eq!(table.positions(), 6);
+        assert_eq!(table.end_marker(), 6);
+        assert_eq!(table.followpos(1), &set(&[1, 2, 3]));
+        assert_eq!(table.followpos(2), &set(&[1, 2, 3]));
+        assert_eq!(table.followpos(3), &set(&[4]));
+        assert_eq!(table.followpos(4), &set(&[5]));
+        assert_eq!(table.followpos(5), &set(&[6]));
+        assert_eq!(table.followpos(6), &set(&[]));
+        assert_eq!(table.firstpos(), set(&[1, 2, 3]));
+        assert_eq!(table.lastpos(), set(&[6]));
+        assert!(!table.nullable());
+        assert_eq!(table.to_string(), "1 a: {1, 2, 3}\n2 b: {1, 2, 3}\n3 a: {4}\n4 b: {5}\n5 b: {6}\n6 #: {}\n");
+    }
+
+    #[test]
+    fn plus_optional_test() {
+        let table = FollowPos::new(&Regex::parse("a+b?").unwrap());
+        // a+ = a a*: позиции 1 и 2 - обе a
+        assert_eq!(table.positions(), 4);
+        assert_eq!(table.followpos(1), &set(&[2, 3, 4]));
+        assert_eq!(table.followpos(2), &set(&[2, 3, 4]));
+        assert_eq!(table.followpos(3), &set(&[4]));
+        assert!(FollowPos::new(&Regex::parse("a?").unwrap()).firstpos().contains(&2));
+    }
+
+    #[test]
+    fn direct_dfa_test() {
+        // пример 3.37: сразу 4 состояния, как у минимального автомата
+        let dfa = Dfa::direct("(a|b)*abb").unwrap();
+        assert_eq!(dfa.states_count(), 4);
+        assert_eq!(dfa, Dfa::from_nfa(&Nfa::compile("(a|b)*abb").unwrap()).minimize());
+        assert!(dfa.is_match("aabb"));
+        assert!(!dfa.is_match("ab"));
+        assert!(Dfa::direct("(ab)*").unwrap().is_match(""));
+    }
+
+    #[test]
+    fn pipelines_agree_on_token_patterns_test() {
+        // шаблоны из extended_lexer
+        let patterns = [r"\d+(\.\d+)?([Ee][+-]?\d+)?", "[A-Za-z][A-Za-z0-9]*", "[!=]=|<=?|>=?", r#""([^"\\]|\\.)*""#];
+        let inputs = ["142e-1", "12.", "12.5", "1e", "s241", "x", "4x", "<=", "<", "!", "==", "\"vas\"", "\"v", "\"\\\"", "\"a\" x \"b\"", "\"a\\\"b\""];
+        for pattern in &patterns {
+            let direct = Dfa::direct(pattern).unwrap();
+            let thompson = Dfa::from_nfa(&Nfa::compile(pattern).unwrap());
+            for input in &inputs {
+                assert_eq!(direct.is_match(input), thompson.is_match(input), "{} on {}", pattern, input);
+                assert_eq!(direct.longest_match(input), thompson.longest_match(input), "{} on {}", pattern, input);
+            }
+            assert_eq!(direct.minimize(), thompson.minimize(), "{}", pattern);
+        }
+    }
+}
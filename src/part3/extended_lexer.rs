@@ -1,30 +1,40 @@
-use std::str::Chars;
-use std::cmp::min;
+use std::collections::VecDeque;
 use std::fmt::{Debug, Formatter, Error};
-use std::collections::HashMap;
+use std::io::{Cursor, ErrorKind, Read};
+
+use super::extended_lexer_gen::{next_token, Tag};
+use crate::lex_error::LexError;
+use crate::span::{Position, Span};
+
+//Шаблоны токенов для этого горе-лексера описаны в extended_lexer.l:
+//
+// digit ->[0-9]
+// digits -> digit+
+// number ->digits(.digits)?([eE][+-]?digits)?
+// letter ->[A-Za-z]
+// id -> letter(letter|digits)*
+// if -> IF
+// then -> THEN
+// else -> ELSE
+// relop -> >= |<= |< |== |> |!=
+// string -> "([^"\\]|\\.)*"
+// ws -> (\n\r\t\s)+
+//
+// Таблицы ДКА и next_token генерирует part3::lex_generator; при равной длине
+// побеждает правило, стоящее в спецификации раньше, поэтому IF - ключевое слово, а не id.
 
-///Шаблоны токенов для этого горе-лексера
-///
-/// digit ->[0-9]
-/// digits -> digit+
-/// number ->digits(.digits)?([eE][+-]?digits)?
-/// letter ->[A-Za-z]
-/// id -> letter(letter|digits)*
-/// if -> IF
-/// then -> THEN
-/// else -> ELSE
-/// relop -> >= |<= |< |== |> |!=
-/// ws -> (\n\r\t\s)+
-///
 #[derive(Debug, PartialEq, Clone)]
-struct Token(String);
+pub struct Token {
+    lexeme: String,
+    span: Span,
+}
 
 impl Token {
-    fn token(&self) -> String {
-        self.0.to_owned()
+    pub fn token(&self) -> String {
+        self.lexeme.to_owned()
     }
-    fn from_str(s: &str) -> Self {
-        Token(s.to_string())
+    pub fn span(&self) -> Span {
+        self.span
     }
 }
 
@@ -34,197 +44,472 @@ enum Buffer {
     B,
 }
 
+impl Buffer {
+    fn other(self) -> Buffer {
+        match self {
+            Buffer::A => Buffer::B,
+            Buffer::B => Buffer::A,
+        }
+    }
+}
+
 const BUFFERSIZE:usize=2048;
+/// Последняя ячейка половины всегда занята ограничителем
+const HALF: usize = BUFFERSIZE - 1;
+/// Ограничитель eof (раздел 3.2.2); внутри половины он означает конец входа
+const SENTINEL: char = '\0';
+
+///Символы из произвольного Read; UTF-8 может разрываться между вызовами read
+struct CharReader {
+    reader: Box<dyn Read>,
+    bytes: Vec<u8>,
+    chars: VecDeque<char>,
+    done: bool,
+}
+
+impl CharReader {
+    fn new(reader: Box<dyn Read>) -> Self {
+        CharReader { reader, bytes: Vec::new(), chars: VecDeque::new(), done: false }
+    }
 
-struct Lexer {
+    fn next(&mut self) -> Option<char> {
+        while self.chars.is_empty() && !self.done {
+            let mut chunk = [0; 1024];
+            match self.reader.read(&mut chunk) {
+                Ok(0) => {
+                    self.done = true;
+                    self.chars.extend(String::from_utf8_lossy(&self.bytes).chars());
+                    self.bytes.clear();
+                }
+                Ok(count) => {
+                    self.bytes.extend_from_slice(&chunk[..count]);
+                    self.decode();
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => self.done = true,
+            }
+        }
+        self.chars.pop_front()
+    }
+
+    // переносит в chars всё, что уже можно декодировать; неполный хвост остаётся в bytes
+    fn decode(&mut self) {
+        loop {
+            let (valid, invalid) = match std::str::from_utf8(&self.bytes) {
+                Ok(text) => (text.len(), None),
+                Err(e) => (e.valid_up_to(), e.error_len()),
+            };
+            self.chars.extend(String::from_utf8_lossy(&self.bytes[..valid]).chars());
+            match invalid {
+                Some(length) => {
+                    self.chars.push_back(char::REPLACEMENT_CHARACTER);
+                    self.bytes.drain(..valid + length);
+                }
+                None => {
+                    self.bytes.drain(..valid);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+///Лексер со схемой пары буферов
+///
+/// lexeme_begin и forward - абсолютные номера символов во входе; base_a и base_b -
+/// номера первых символов, загруженных в половины (None - половина ещё пуста).
+/// Когда forward доходит до ограничителя в конце половины, вторая половина
+/// перезагружается, если в ней ещё нет следующего куска входа. Лексема может
+/// переходить через границу половин, но не может быть длиннее половины.
+pub struct Lexer {
     buffer_a: [char; BUFFERSIZE],
     buffer_b: [char; BUFFERSIZE],
+    base_a: Option<usize>,
+    base_b: Option<usize>,
     lexeme_begin: usize,
     forward: usize,
-    buffer_idx: usize,
-    tokens: HashMap<String, regex::Regex>,
     buffer: Buffer,
+    source: CharReader,
+    /// Строка, столбец и смещение в байтах символа lexeme_begin
+    position: Position,
+}
+
+impl Default for Lexer {
+    fn default() -> Self {
+        Lexer::new()
+    }
 }
 
 impl Debug for Lexer {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        let string = self.cur_buffer().to_vec().into_iter().take_while(|x| *x != '\0').collect::<Vec<char>>();
+        let string = self.cur_buffer().iter().take_while(|x| **x != SENTINEL).collect::<Vec<&char>>();
         write!(f, "{:?}", string)
     }
 }
 
 
-impl<'a> Lexer {
-    fn new() -> Self {
-        Lexer { lexeme_begin: 0, forward: 0, buffer_a: ['\0'; BUFFERSIZE], buffer_b: ['\0'; BUFFERSIZE], buffer_idx: 0, tokens: Lexer::init_regex(), buffer: Buffer::A }
+impl Lexer {
+    pub fn new() -> Self {
+        Lexer::from_reader(std::io::empty())
     }
 
-    fn cur_buffer(&self) -> [char; BUFFERSIZE] {
-        if self.buffer == Buffer::A {
-            self.buffer_a
-        } else {
-            self.buffer_b
-        }
+    pub fn from_reader<R: Read + 'static>(reader: R) -> Self {
+        let mut lexer = Lexer {
+            buffer_a: [SENTINEL; BUFFERSIZE],
+            buffer_b: [SENTINEL; BUFFERSIZE],
+            base_a: None,
+            base_b: None,
+            lexeme_begin: 0,
+            forward: 0,
+            buffer: Buffer::A,
+            source: CharReader::new(Box::new(reader)),
+            position: Position::default(),
+        };
+        lexer.fill(Buffer::A, 0);
+        lexer
     }
 
-    fn init_regex() -> HashMap<String, regex::Regex> {
-        let mut hash: HashMap<String, regex::Regex> = HashMap::new();
-        hash.insert("number".to_owned(), regex::Regex::new(r"^(\d+(\.\d+)?(([Ee][+-])?\d+)?)").unwrap());
-        hash.insert("id".to_owned(), regex::Regex::new(r"^[a-zA-Z]([a-zA-Z0-9])*").unwrap());
-        hash.insert("relop".to_owned(), regex::Regex::new(r"^([!=]=|<[=]??|>[=]??)").unwrap());
-        hash.insert("string".to_owned(), regex::Regex::new(r#"^"([^\\])*""#).unwrap());
-      //  hash.insert("string".to_owned(), regex::Regex::new(r#"^"([^\\"]*(\\)*(\\")*)*""#).unwrap());
-        hash
+    fn cur_buffer(&self) -> &[char; BUFFERSIZE] {
+        self.half(self.buffer)
     }
 
-    fn init_buffer(&mut self, input: String) {
-        let mut input_as_chars = input.chars();
-        //Последний элемент eof
-        println!("{}", &input[self.buffer_idx..].len());
-        println!("{}", &(self.buffer_a.len() - 1));
-
-        for i in 0..self.cur_buffer().len() {
-            self.buffer_a[i] = input_as_chars.next().unwrap_or('\0');
-        }
-        if &input[self.buffer_idx..].len() > &(self.buffer_a.len() - 1) {
-            let mut idx = self.cur_buffer().len() - 2;
-            while !self.buffer_a[idx].is_whitespace() && self.buffer_a[idx] != '\0' {
-                self.buffer_a[idx] = '\0';
-                idx -= 1;
-            }
-            self.buffer_idx = idx;
+    fn half(&self, buffer: Buffer) -> &[char; BUFFERSIZE] {
+        match buffer {
+            Buffer::A => &self.buffer_a,
+            Buffer::B => &self.buffer_b,
         }
     }
 
-    fn change_buffer(&mut self, input: &str) {
-        if self.buffer == Buffer::A {
-            if &input[self.buffer_idx..].len() > &0 {
-                self.buffer == Buffer::B;
-                println!("change!");
-            }
-        } else {}
+    fn base(&self, buffer: Buffer) -> Option<usize> {
+        match buffer {
+            Buffer::A => self.base_a,
+            Buffer::B => self.base_b,
+        }
     }
 
-    fn scan_test(&mut self, input: String) -> Token {
-        Token("hello".to_owned())
+    /// Начать разбор строки с начала
+    pub fn init_buffer(&mut self, input: String) {
+        *self = Lexer::from_reader(Cursor::new(input.into_bytes()));
     }
 
-    fn is_eof(&mut self) -> bool {
-        if self.lexeme_begin >= self.cur_buffer().len() {
-            true
-        } else {
-            if self.cur_buffer()[self.lexeme_begin] == '\0'// && self.buffer == Buffer::A && self.forward == self.buffer_a.len()
-            {
-                self.clear();
-                true
-            } else { false }
-        }
-    }
-    pub fn scan(&mut self, input: &str) -> Option<Token> {
-        //Убрать пробелы
-        if self.cur_buffer()[self.lexeme_begin].is_whitespace() {
-            self.forward = self.cur_buffer()[self.lexeme_begin..self.cur_buffer().len()].iter().take_while(|&x| {
-                *x == '\n' || *x == '\r' || *x == '\t' || *x == ' '
-            }).count() + self.forward;
-            self.lexeme_begin = self.forward;
+    // загрузка очередных HALF символов входа; ограничитель ставится сразу за ними
+    fn fill(&mut self, buffer: Buffer, base: usize) {
+        let mut half = [SENTINEL; BUFFERSIZE];
+        for cell in half.iter_mut().take(HALF) {
+            match self.source.next() {
+                Some(c) => *cell = c,
+                None => break,
+            }
         }
-
-        let text = &self.cur_buffer()[self.lexeme_begin..].to_vec().iter().collect::<String>()[..];
-
-        if let Some(m) = self.tokens.get("number").unwrap().find(text) {
-            self.forward += m.end();
-            self.lexeme_begin = self.forward;
-            return Some(Token(m.as_str().to_owned()));
+        match buffer {
+            Buffer::A => {
+                self.buffer_a = half;
+                self.base_a = Some(base);
+            }
+            Buffer::B => {
+                self.buffer_b = half;
+                self.base_b = Some(base);
+            }
         }
+    }
 
-        if let Some(m) = self.tokens.get("id").unwrap().find(text) {
-            self.forward += m.end();
-            self.lexeme_begin = self.forward;
-            return match m.as_str() {
-                "IF" => Some(Token("if:IF".to_owned())),
-                "THEN" => Some(Token("then:THEN".to_owned())),
-                "ELSE" => Some(Token("else:ELSE".to_owned())),
-                _ => Some(Token(m.as_str().to_owned()))
-            };
+    // рис. 3.5: символ в позиции forward; SENTINEL без сдвига - конец входа
+    // None - лексема не помещается в половину буфера: перезагрузка затёрла бы её начало
+    fn advance(&mut self) -> Option<char> {
+        let base = self.base(self.buffer).unwrap_or(0);
+        let mut c = self.cur_buffer()[self.forward - base];
+        if c == SENTINEL && self.forward - base == HALF {
+            let other = self.buffer.other();
+            if self.base(other) != Some(base + HALF) {
+                if self.lexeme_begin < base {
+                    return None;
+                }
+                self.fill(other, base + HALF);
+            }
+            self.buffer = other;
+            c = self.cur_buffer()[0];
         }
-
-        if let Some(m) = self.tokens.get("relop").unwrap().find(text) {
-            self.forward += m.end();
-            self.lexeme_begin = self.forward;
-
-            return Some(Token(m.as_str().to_owned()));
+        if c != SENTINEL {
+            self.forward += 1;
         }
+        Some(c)
+    }
 
-        if let Some(m) = self.tokens.get("string").unwrap().find(text) {
-            self.forward += m.end();
-            self.lexeme_begin = self.forward;
-
-            return Some(Token(m.as_str().to_owned()));
+    // возврат forward назад после поиска самого длинного префикса
+    fn retract(&mut self, position: usize) {
+        self.forward = position;
+        for buffer in [Buffer::A, Buffer::B].iter() {
+            if let Some(base) = self.base(*buffer) {
+                if base <= position && position < base + HALF {
+                    self.buffer = *buffer;
+                }
+            }
         }
+    }
 
+    // самый длинный префикс с lexeme_begin; forward остаётся за последним прочитанным символом
+    fn longest_match(&mut self) -> Match {
+        let mut text = String::new();
+        let mut too_long = false;
+        let mut eof = false;
+        let matched = {
+            let lexer = &mut *self;
+            let (text, too_long, eof) = (&mut text, &mut too_long, &mut eof);
+            next_token(std::iter::from_fn(move || match lexer.advance() {
+                None => {
+                    *too_long = true;
+                    None
+                }
+                Some(SENTINEL) => {
+                    *eof = true;
+                    None
+                }
+                Some(c) => {
+                    text.push(c);
+                    Some(c)
+                }
+            }))
+        };
+        Match { matched, text, too_long, eof }
+    }
 
+    // переносит начало лексемы за text
+    fn skip(&mut self, text: &str) -> Span {
+        self.retract(self.lexeme_begin + text.chars().count());
+        self.lexeme_begin = self.forward;
+        let start = self.position;
+        text.chars().for_each(|c| self.position.advance(c));
+        Span::new(start, self.position)
+    }
 
-        if self.is_eof() {
-            self.change_buffer(&input);
-            None
-        } else {
-            panic!("Unexpected token at {} -> {}\n {:?}", self.lexeme_begin, self.cur_buffer()[self.lexeme_begin], &self.cur_buffer()[self.lexeme_begin-1..self.lexeme_begin+1])
+    /// Следующий токен; после ошибки лексер продолжает разбор с ближайшего допустимого токена
+    pub fn scan(&mut self) -> Result<Option<Token>, LexError> {
+        //пробелы пропускаются правилом ws без тэга
+        let mut invalid: Option<(char, Position)> = None;
+        loop {
+            let Match { matched, text, too_long, eof } = self.longest_match();
+            if let Some((character, start)) = invalid {
+                // режим паники: цепочка недопустимых символов продолжается, пока с них не начинается токен
+                if matched.is_none() && !too_long && !text.is_empty() && !text.starts_with('"') {
+                    let first = text.chars().next().unwrap_or(SENTINEL);
+                    self.skip(&first.to_string());
+                    continue;
+                }
+                self.retract(self.lexeme_begin);
+                return Err(LexError::InvalidCharacter { character, span: Span::new(start, self.position) });
+            }
+            if too_long {
+                // недочитанная лексема отбрасывается целиком
+                let span = self.skip(&text);
+                return Err(LexError::LexemeTooLong { span });
+            }
+            let (tag, length) = match matched {
+                Some(token) => token,
+                None if text.is_empty() => return Ok(None),
+                None if text.starts_with('"') => {
+                    // символ, на котором автомат остановился, в строку не входит
+                    let length = if eof { text.len() } else { text.len() - text.chars().last().map_or(0, char::len_utf8) };
+                    let span = self.skip(&text[..length]);
+                    return Err(LexError::UnterminatedString { span });
+                }
+                None => {
+                    let first = text.chars().next().unwrap_or(SENTINEL);
+                    invalid = Some((first, self.position));
+                    self.skip(&first.to_string());
+                    continue;
+                }
+            };
+            let lexeme = &text[..length];
+            let span = self.skip(lexeme);
+            return match tag {
+                None => continue,
+                Some(tag @ Tag::IF) | Some(tag @ Tag::THEN) | Some(tag @ Tag::ELSE) =>
+                    Ok(Some(Token { lexeme: format!("{}:{}", format!("{:?}", tag).to_lowercase(), lexeme), span })),
+                Some(_) => Ok(Some(Token { lexeme: lexeme.to_owned(), span })),
+            };
         }
     }
-    fn clear(&mut self) {
-        self.lexeme_begin = 0;
-        self.buffer_idx = 0;
-        self.forward = 0;
-        self.buffer_a = ['\0'; 2048];
-        self.buffer_b = ['\0'; 2048];
-        self.buffer = Buffer::A
-    }
 
-    fn update_buffer() {
-        unimplemented!()
+    /// Все токены до конца входа и все встреченные ошибки
+    pub fn scan_all(&mut self) -> (Vec<Token>, Vec<LexError>) {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        loop {
+            match self.scan() {
+                Ok(Some(token)) => tokens.push(token),
+                Ok(None) => return (tokens, errors),
+                Err(error) => errors.push(error),
+            }
+        }
     }
 }
 
+struct Match {
+    matched: Option<(Option<Tag>, usize)>,
+    text: String,
+    too_long: bool,
+    eof: bool,
+}
 
 #[cfg(test)]
 mod tests {
-    use super::Token;
-    use super::Lexer;
-    use std::time::Duration;
+    use super::{Lexer, BUFFERSIZE};
+    use crate::lex_error::LexError;
+    use crate::span::SourceMap;
+    use std::io::{Cursor, Read};
 
     #[test]
     fn token_test() {
-        let t = Token::from_str(">=");
+        let mut lexer = Lexer::new();
+        lexer.init_buffer(">=".to_owned());
+        let t = lexer.scan().unwrap().unwrap();
         assert_eq!(t.token(), ">=");
+        assert_eq!(t.span().text(">="), ">=");
     }
 
     #[test]
-    fn lexer_smoke_test() {
+    fn lexer_eof_test() {
         let mut lexer = Lexer::new();
-        assert_eq!(lexer.scan_test(">=".to_string()), Token::from_str("hello"));
+        let string_for_scan = "as==112.23\n  142e-1>= s241  2341 hello<me \n\"vas\"ya\"".to_owned();
+        //let string_for_scan = "42e-1>= s241".to_owned();
+        lexer.init_buffer(string_for_scan.clone());
+        // строка заканчивается на первой кавычке, последняя кавычка не закрыта до конца входа
+        let (tokens, errors) = lexer.scan_all();
+        let tokens: Vec<String> = tokens.iter().map(|x| x.token()).collect();
+        assert_eq!(tokens, vec!["as", "==", "112.23", "142e-1", ">=", "s241", "2341", "hello", "<", "me", "\"vas\"", "ya"]);
+        assert_eq!(errors, vec![LexError::UnterminatedString { span: SourceMap::new(&string_for_scan).span(50, 51) }]);
+        assert_eq!(lexer.scan(), Ok(None));
     }
 
+
     #[test]
-    fn lexer_eof_test() {
+    fn lexer_maximal_munch_test() {
         let mut lexer = Lexer::new();
-        let string_for_scan = "as==112.23\n  142e-1>= s241  2341 hello<me \n\"vas\"ya\"".to_owned();
-        //let string_for_scan = "42e-1>= s241".to_owned();
+        let string_for_scan = "IF counter <= 12.5e3 THEN x1 != \"a b\" ELSE IFS".to_owned();
         lexer.init_buffer(string_for_scan.clone());
-        let mut a = lexer.scan(&string_for_scan[..]);
-        while a != None {
-            println!("{}", &a.unwrap().token());
-            //std::thread::sleep(Duration::from_millis(1000));
-            a = lexer.scan(&string_for_scan[..]);
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.scan().unwrap() {
+            tokens.push(token.token());
         }
+        assert_eq!(tokens, vec!["if:IF", "counter", "<=", "12.5e3", "then:THEN", "x1", "!=", "\"a b\"", "else:ELSE", "IFS"]);
     }
 
+    #[test]
+    fn two_strings_test() {
+        let mut lexer = Lexer::new();
+        lexer.init_buffer("\"a\" x \"b\" \"c\\\"d\"".to_owned());
+        assert_eq!(tokens(&mut lexer), vec!["\"a\"", "x", "\"b\"", "\"c\\\"d\""]);
+    }
 
     #[test]
     fn lexer_test() {
         let mut lexer = Lexer::new();
         let string_for_scan = "  234\n   counter=1\nIF counter ==1\n THEN counter = 2\n ELSE counter =3\n".to_owned();
         lexer.init_buffer(string_for_scan.clone());
-        let a = lexer.scan(&string_for_scan[..]);
-        println!("{:?}", a);
+        let (tokens, errors) = lexer.scan_all();
+        let tokens: Vec<String> = tokens.iter().map(|x| x.token()).collect();
+        assert_eq!(tokens, vec!["234", "counter", "1", "if:IF", "counter", "==", "1",
+                                "then:THEN", "counter", "2", "else:ELSE", "counter", "3"]);
+        // одиночное = не входит в relop
+        let characters: Vec<char> = errors.iter().map(|x| match x {
+            LexError::InvalidCharacter { character, .. } => *character,
+            _ => panic!("{:?}", x),
+        }).collect();
+        assert_eq!(characters, vec!['=', '=', '=']);
+    }
+
+    fn tokens(lexer: &mut Lexer) -> Vec<String> {
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.scan().unwrap() {
+            tokens.push(token.token());
+        }
+        tokens
+    }
+
+    // отдаёт вход по одному байту, разрывая многобайтовые символы между вызовами read
+    struct ByteByByte(Cursor<Vec<u8>>);
+
+    impl Read for ByteByByte {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let length = buf.len().min(1);
+            self.0.read(&mut buf[..length])
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn spans_test() {
+        let input = "IF x1\n  <= \"привет\" THEN 42";
+        let map = SourceMap::new(input);
+        let mut lexer = Lexer::from_reader(Cursor::new(input.as_bytes().to_vec()));
+        let mut spans = Vec::new();
+        while let Some(token) = lexer.scan().unwrap() {
+            spans.push(token.span());
+        }
+        let texts: Vec<&str> = spans.iter().map(|x| x.text(input)).collect();
+        assert_eq!(texts, vec!["IF", "x1", "<=", "\"привет\"", "THEN", "42"]);
+        assert_eq!(spans[2], map.span(8, 10));
+        assert_eq!(spans[3].start.to_string(), "2:6");
+        assert_eq!(spans[3].end.to_string(), "2:14");
+        assert_eq!(spans[5].end.offset, input.len());
+    }
+
+    #[test]
+    fn long_input_test() {
+        let line = "x1 <= 42\n";
+        let input = line.repeat(1000);
+        assert!(input.len() > 4 * BUFFERSIZE);
+        let mut lexer = Lexer::new();
+        lexer.init_buffer(input);
+        let tokens = tokens(&mut lexer);
+        assert_eq!(tokens.len(), 3000);
+        assert!(tokens.chunks(3).all(|x| x == ["x1", "<=", "42"]));
+    }
+
+    #[test]
+    fn lexeme_straddles_buffers_test() {
+        // counter12 начинается в первой половине и заканчивается во второй
+        let input = " ".repeat(BUFFERSIZE - 5) + "counter12 THEN 12.5e3" + &" ".repeat(BUFFERSIZE - 18) + ">=";
+        let mut lexer = Lexer::new();
+        lexer.init_buffer(input);
+        assert_eq!(tokens(&mut lexer), vec!["counter12", "then:THEN", "12.5e3", ">="]);
+    }
+
+    #[test]
+    fn reader_test() {
+        let input = " ".repeat(BUFFERSIZE - 4) + "\"привет мир\" IF x";
+        let mut lexer = Lexer::from_reader(ByteByByte(Cursor::new(input.into_bytes())));
+        assert_eq!(tokens(&mut lexer), vec!["\"привет мир\"", "if:IF", "x"]);
+        assert_eq!(Lexer::new().scan(), Ok(None));
+    }
+
+    #[test]
+    fn lexeme_too_long_test() {
+        let mut lexer = Lexer::new();
+        lexer.init_buffer("x".repeat(3 * BUFFERSIZE) + " IF");
+        let (tokens, errors) = lexer.scan_all();
+        match &errors[..] {
+            [LexError::LexemeTooLong { span }] => assert_eq!(span.start.offset, 0),
+            _ => panic!("{:?}", errors),
+        }
+        assert_eq!(tokens.last().map(|x| x.token()), Some("if:IF".to_owned()));
+    }
+
+    #[test]
+    fn recovery_test() {
+        let input = "x1 @#$ <= 42 ?\n\"abc\\d IF \"tail \"open";
+        let map = SourceMap::new(input);
+        let mut lexer = Lexer::new();
+        lexer.init_buffer(input.to_owned());
+        let (tokens, errors) = lexer.scan_all();
+        let tokens: Vec<String> = tokens.iter().map(|x| x.token()).collect();
+        // \d внутри строки - экранированный символ
+        assert_eq!(tokens, vec!["x1", "<=", "42", "\"abc\\d IF \"", "tail"]);
+        assert_eq!(errors, vec![
+            LexError::InvalidCharacter { character: '@', span: map.span(3, 6) },
+            LexError::InvalidCharacter { character: '?', span: map.span(13, 14) },
+            LexError::UnterminatedString { span: map.span(31, 36) },
+        ]);
+        assert_eq!(errors[0].to_string(), "Invalid character '@' at 1:4");
+        assert_eq!(errors[2].to_string(), "Unterminated string at 2:17");
+    }
+}
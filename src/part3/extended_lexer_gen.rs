@@ -0,0 +1,150 @@
+// Сгенерировано part3::lex_generator из extended_lexer.l, не редактировать вручную.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Tag {
+    NUMBER,
+    IF,
+    THEN,
+    ELSE,
+    ID,
+    RELOP,
+    STRING,
+}
+
+const ALPHABET: [(char, char); 42] = [
+    ('\0', '\u{8}'),
+    ('\t', '\t'),
+    ('\n', '\n'),
+    ('\u{b}', '\u{c}'),
+    ('\r', '\r'),
+    ('\u{e}', '\u{1f}'),
+    (' ', ' '),
+    ('!', '!'),
+    ('"', '"'),
+    ('#', '*'),
+    ('+', '+'),
+    (',', ','),
+    ('-', '-'),
+    ('.', '.'),
+    ('/', '/'),
+    ('0', '9'),
+    (':', ';'),
+    ('<', '<'),
+    ('=', '='),
+    ('>', '>'),
+    ('?', '@'),
+    ('A', 'D'),
+    ('E', 'E'),
+    ('F', 'F'),
+    ('G', 'G'),
+    ('H', 'H'),
+    ('I', 'I'),
+    ('J', 'K'),
+    ('L', 'L'),
+    ('M', 'M'),
+    ('N', 'N'),
+    ('O', 'R'),
+    ('S', 'S'),
+    ('T', 'T'),
+    ('U', 'Z'),
+    ('[', '['),
+    ('\\', '\\'),
+    (']', '`'),
+    ('a', 'd'),
+    ('e', 'e'),
+    ('f', 'z'),
+    ('{', '\u{10ffff}'),
+];
+
+const NO_STATE: usize = usize::MAX;
+
+const TRANSITIONS: [[usize; 42]; 25] = [
+    [NO_STATE, 1, 1, NO_STATE, 1, NO_STATE, 1, 2, 3, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 4, NO_STATE, 5, 2, 5, NO_STATE, 6, 7, 6, 6, 6, 8, 6, 6, 6, 6, 6, 6, 9, 6, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, NO_STATE],
+    [NO_STATE, 1, 1, NO_STATE, 1, NO_STATE, 1, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 10, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE],
+    [3, 3, 3, 3, 3, 3, 3, 3, 11, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 12, 3, 3, 3, 3, 3],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 13, NO_STATE, 4, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 14, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 14, NO_STATE, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 10, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, 6, 6, 6, 6, 15, 6, 6, 6, 6, 6, 6, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, 6, 16, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, 6, 17, 6, 6, 6, 6, 6, 6, 6, 6, 6, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE],
+    [3, 3, NO_STATE, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 18, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 19, NO_STATE, 19, NO_STATE, NO_STATE, 20, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 21, 6, 6, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, 22, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 18, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 14, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 14, NO_STATE, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 20, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 20, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, 23, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, 6, 6, 6, 6, 6, 6, 24, 6, 6, 6, 6, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, NO_STATE],
+    [NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, NO_STATE, NO_STATE, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, NO_STATE, NO_STATE, NO_STATE, 6, 6, 6, NO_STATE],
+];
+
+/// None - состояние не допускающее, Some(None) - лексема пропускается
+const ACCEPT: [Option<Option<Tag>>; 25] = [
+    None,
+    Some(None),
+    None,
+    None,
+    Some(Some(Tag::NUMBER)),
+    Some(Some(Tag::RELOP)),
+    Some(Some(Tag::ID)),
+    Some(Some(Tag::ID)),
+    Some(Some(Tag::ID)),
+    Some(Some(Tag::ID)),
+    Some(Some(Tag::RELOP)),
+    Some(Some(Tag::STRING)),
+    None,
+    None,
+    None,
+    Some(Some(Tag::ID)),
+    Some(Some(Tag::IF)),
+    Some(Some(Tag::ID)),
+    Some(Some(Tag::NUMBER)),
+    None,
+    Some(Some(Tag::NUMBER)),
+    Some(Some(Tag::ID)),
+    Some(Some(Tag::ID)),
+    Some(Some(Tag::ELSE)),
+    Some(Some(Tag::THEN)),
+];
+
+const START: usize = 0;
+
+fn symbol(c: char) -> Option<usize> {
+    let index = match ALPHABET.binary_search_by(|(low, _)| low.cmp(&c)) {
+        Ok(index) => index,
+        Err(0) => return None,
+        Err(index) => index - 1,
+    };
+    if c <= ALPHABET[index].1 {
+        Some(index)
+    } else {
+        None
+    }
+}
+
+/// Самое длинное совпадение в начале input: тэг (None для пропускаемых лексем) и длина в байтах
+pub fn next_token<I: IntoIterator<Item=char>>(input: I) -> Option<(Option<Tag>, usize)> {
+    let mut state = START;
+    let mut last = None;
+    let mut length = 0;
+    for c in input {
+        state = match symbol(c).map(|s| TRANSITIONS[state][s]) {
+            Some(next) if next != NO_STATE => next,
+            _ => break,
+        };
+        length += c.len_utf8();
+        if let Some(action) = ACCEPT[state] {
+            last = Some((action, length));
+        }
+    }
+    last
+}
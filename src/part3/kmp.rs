@@ -0,0 +1,261 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+//Алгоритм Кнута-Морриса-Пратта (раздел 3.4.5 и упражнения 3.4.3-3.4.9).
+//Функция отказов f(s) - длина наибольшего собственного префикса b1..bs,
+//который одновременно его суффикс; хранится со сдвигом: failure[s - 1] = f(s).
+
+/// Функция отказов для любого ключевого слова
+pub fn failure<T: PartialEq>(keyword: &[T]) -> Vec<usize> {
+    let mut result = vec![0; keyword.len()];
+    let mut t = 0;
+    for s in 1..keyword.len() {
+        while t > 0 && keyword[s] != keyword[t] {
+            t = result[t - 1];
+        }
+        if keyword[s] == keyword[t] {
+            t += 1;
+        }
+        result[s] = t;
+    }
+    result
+}
+
+///Поиск одного слова; смещения в байтах, поэтому для &str они всегда на границах символов
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Kmp {
+    pattern: Vec<u8>,
+    failure: Vec<usize>,
+}
+
+impl Kmp {
+    pub fn new<P: AsRef<[u8]> + ?Sized>(pattern: &P) -> Self {
+        let pattern = pattern.as_ref().to_vec();
+        let failure = failure(&pattern);
+        Kmp { pattern, failure }
+    }
+
+    pub fn failure(&self) -> &[usize] {
+        &self.failure
+    }
+
+    // состояние - длина совпавшего префикса образца
+    fn next_state(&self, mut state: usize, c: u8) -> usize {
+        while state > 0 && (state == self.pattern.len() || self.pattern[state] != c) {
+            state = self.failure[state - 1];
+        }
+        if state < self.pattern.len() && self.pattern[state] == c { state + 1 } else { 0 }
+    }
+
+    /// Первое вхождение
+    pub fn find<T: AsRef<[u8]> + ?Sized>(&self, text: &T) -> Option<usize> {
+        self.find_iter(text).next()
+    }
+
+    /// Непересекающиеся вхождения слева направо
+    pub fn find_iter<'a, T: AsRef<[u8]> + ?Sized>(&'a self, text: &'a T) -> Matches<'a> {
+        Matches { kmp: self, text: text.as_ref(), position: 0, state: 0, overlapping: false }
+    }
+
+    /// Все вхождения, в том числе пересекающиеся: "aa" в "aaa" находится дважды
+    pub fn find_overlapping<'a, T: AsRef<[u8]> + ?Sized>(&'a self, text: &'a T) -> Matches<'a> {
+        Matches { kmp: self, text: text.as_ref(), position: 0, state: 0, overlapping: true }
+    }
+}
+
+///Начала вхождений; пустой образец находится в каждой позиции от 0 до длины текста
+pub struct Matches<'a> {
+    kmp: &'a Kmp,
+    text: &'a [u8],
+    position: usize,
+    state: usize,
+    overlapping: bool,
+}
+
+impl<'a> Iterator for Matches<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let length = self.kmp.pattern.len();
+        if length == 0 {
+            self.position += 1;
+            return Some(self.position - 1).filter(|x| *x <= self.text.len());
+        }
+        while self.position < self.text.len() {
+            self.state = self.kmp.next_state(self.state, self.text[self.position]);
+            self.position += 1;
+            if self.state == length {
+                self.state = if self.overlapping { self.kmp.failure[length - 1] } else { 0 };
+                return Some(self.position - length);
+            }
+        }
+        None
+    }
+}
+
+/// Строки Фибоначчи (упражнение 3.4.8): s1 = b, s2 = a, sk = sk-1 sk-2;
+/// s0 - пустая строка, как и |s0| = 0 в fibonacci_failure
+pub fn fibonacci_string(n: usize) -> String {
+    match n {
+        0 => return String::new(),
+        1 => return "b".to_owned(),
+        _ => {}
+    }
+    let (mut previous, mut current) = ("b".to_owned(), "a".to_owned());
+    for _ in 2..n {
+        let next = current.to_owned() + &previous;
+        previous = current;
+        current = next;
+    }
+    current
+}
+
+/// Функция отказов sn по формуле из упражнения 3.4.9: f(j) = j - |sk-1|,
+/// где k - наибольшее число, для которого |sk| <= j + 1
+pub fn fibonacci_failure(n: usize) -> Vec<usize> {
+    // lengths[k] = |sk|
+    let mut lengths = vec![0, 1, 1];
+    while lengths.len() <= n + 1 {
+        let k = lengths.len();
+        lengths.push(lengths[k - 1] + lengths[k - 2]);
+    }
+    (1..=lengths[n]).map(|j| {
+        let k = (1..lengths.len()).rev().find(|k| lengths[*k] <= j + 1).unwrap();
+        j - lengths[k - 1]
+    }).collect()
+}
+
+///ДКА, построенный по функции отказов: состояние s - совпали s первых символов.
+///Таблица переходов полная для символов слова, прочие символы ведут в 0
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeywordDfa {
+    keyword: Vec<char>,
+    table: Vec<BTreeMap<char, usize>>,
+}
+
+impl KeywordDfa {
+    pub fn new(keyword: &str) -> Self {
+        let keyword: Vec<char> = keyword.chars().collect();
+        let alphabet: BTreeSet<char> = keyword.iter().copied().collect();
+        let failure = failure(&keyword);
+        let mut table: Vec<BTreeMap<char, usize>> = Vec::new();
+        for s in 0..=keyword.len() {
+            // переходы из f(s) уже в таблице: f(s) < s
+            let row = alphabet.iter().map(|c| {
+                let next = match keyword.get(s) {
+                    Some(x) if x == c => s + 1,
+                    _ if s == 0 => 0,
+                    _ => table[failure[s - 1]][c],
+                };
+                (*c, next)
+            }).collect();
+            table.push(row);
+        }
+        KeywordDfa { keyword, table }
+    }
+
+    pub fn states_count(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn next_state(&self, state: usize, c: char) -> usize {
+        self.table[state].get(&c).copied().unwrap_or(0)
+    }
+
+    pub fn is_accepting(&self, state: usize) -> bool {
+        state == self.keyword.len()
+    }
+
+    /// Байтовые смещения всех вхождений, в том числе пересекающихся;
+    /// пустое слово, как и в Kmp, находится перед каждым символом и в конце
+    pub fn find_overlapping(&self, text: &str) -> Vec<usize> {
+        let length: usize = self.keyword.iter().map(|x| x.len_utf8()).sum();
+        let mut state = 0;
+        // начальное состояние допускающее только для пустого слова
+        let mut result: Vec<usize> = if self.is_accepting(0) { vec![0] } else { Vec::new() };
+        result.extend(text.char_indices().filter_map(|(i, c)| {
+            state = self.next_state(state, c);
+            self.is_accepting(state).then(|| i + c.len_utf8() - length)
+        }));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failure_test() {
+        assert_eq!(failure(b"ababaa"), vec![0, 0, 1, 2, 3, 1]);
+        assert_eq!(failure(&[1, 2, 1, 2, 3]), vec![0, 0, 1, 2, 0]);
+        assert_eq!(failure("abcabcabcabcabc".as_bytes()), vec![0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+        assert!(failure::<u8>(&[]).is_empty());
+        assert_eq!(Kmp::new("abab").failure(), &[0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn find_test() {
+        let kmp = Kmp::new("aba");
+        assert_eq!(kmp.find("cabababa"), Some(1));
+        assert_eq!(kmp.find(&b"xxaba"[..]), Some(2));
+        assert_eq!(kmp.find("ab"), None);
+        assert_eq!(kmp.find_iter("abababa").collect::<Vec<_>>(), vec![0, 4]);
+        assert_eq!(kmp.find_overlapping("abababa").collect::<Vec<_>>(), vec![0, 2, 4]);
+        assert_eq!(Kmp::new("aa").find_overlapping("aaaa").collect::<Vec<_>>(), vec![0, 1, 2]);
+
+        // смещения в байтах
+        let text = "ёжик и ёж";
+        let positions: Vec<usize> = Kmp::new("ёж").find_iter(text).collect();
+        assert_eq!(positions, vec![0, 12]);
+        assert_eq!(&text[12..], "ёж");
+
+        assert_eq!(Kmp::new("").find_iter("ab").collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(Kmp::new("").find(""), Some(0));
+    }
+
+    #[test]
+    fn find_agrees_with_std_test() {
+        let text = fibonacci_string(12);
+        for pattern in &["a", "ab", "aab", "abaab", "babb", "abaababaabaab"] {
+            let expected: Vec<usize> = text.match_indices(pattern).map(|(i, _)| i).collect();
+            assert_eq!(Kmp::new(*pattern).find_iter(&text).collect::<Vec<_>>(), expected);
+            let overlapping: Vec<usize> = (0..text.len()).filter(|i| text[*i..].starts_with(pattern)).collect();
+            assert_eq!(Kmp::new(*pattern).find_overlapping(&text).collect::<Vec<_>>(), overlapping);
+        }
+    }
+
+    #[test]
+    fn fibonacci_test() {
+        let strings: Vec<String> = (0..=6).map(fibonacci_string).collect();
+        assert_eq!(strings, vec!["", "b", "a", "ab", "aba", "abaab", "abaababa"]);
+        assert_eq!(failure(b"abaababa"), vec![0, 0, 1, 1, 2, 3, 2, 3]);
+        for n in 0..=20 {
+            assert_eq!(failure(fibonacci_string(n).as_bytes()), fibonacci_failure(n), "s{}", n);
+        }
+    }
+
+    #[test]
+    fn keyword_dfa_test() {
+        let dfa = KeywordDfa::new("abab");
+        assert_eq!(dfa.states_count(), 5);
+        let row = |s: usize| (dfa.next_state(s, 'a'), dfa.next_state(s, 'b'));
+        assert_eq!((0..5).map(row).collect::<Vec<_>>(), vec![(1, 0), (1, 2), (3, 0), (1, 4), (3, 0)]);
+        assert_eq!(dfa.next_state(3, 'z'), 0);
+        assert!(dfa.is_accepting(4));
+        assert_eq!(KeywordDfa::new("").find_overlapping("ёж"), vec![0, 2, 4]);
+        assert_eq!(KeywordDfa::new("").find_overlapping(""), vec![0]);
+
+        // ДКА делает то же, что КМП, но без цепочек отказов
+        let text = fibonacci_string(11);
+        for keyword in &["abaab", "aa", "b", "abaababaab", ""] {
+            let dfa = KeywordDfa::new(keyword);
+            let kmp = Kmp::new(*keyword);
+            assert_eq!(dfa.find_overlapping(&text), kmp.find_overlapping(&text).collect::<Vec<_>>());
+            for s in 0..dfa.states_count() {
+                for c in "ab".chars() {
+                    assert_eq!(dfa.next_state(s, c), kmp.next_state(s, c as u8));
+                }
+            }
+        }
+    }
+}
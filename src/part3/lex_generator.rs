@@ -0,0 +1,452 @@
+use std::fmt::Write;
+
+use super::dfa::Dfa;
+
+///Правило спецификации: шаблон и тэг токена (None - лексема пропускается)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexRule {
+    /// Шаблон как он записан в спецификации
+    pub pattern: String,
+    /// Шаблон после подстановки определений и раскрытия строк в кавычках
+    pub regex: String,
+    pub action: Option<String>,
+}
+
+///Спецификация в духе Lex
+///
+/// // комментарий
+/// digit   [0-9]
+/// letter  [A-Za-z]
+/// %%
+/// [ \t\n]+                    ;
+/// {digit}+                    NUM
+/// "IF"                        IF
+/// {letter}({letter}|{digit})* ID
+/// <<EOF>>                     EOF
+/// %%
+///
+/// До первого %% - именованные определения, затем правила: шаблон и действие.
+/// Действие - имя тэга (можно в виде { return NUM; }) или ; для пропуска.
+/// Из нескольких совпадений выбирается самое длинное, при равной длине - правило выше.
+/// Всё после второго %% игнорируется.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexSpec {
+    definitions: Vec<(String, String)>,
+    rules: Vec<LexRule>,
+    eof: Option<String>,
+}
+
+impl LexSpec {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut definitions: Vec<(String, String)> = Vec::new();
+        let mut rules = Vec::new();
+        let mut eof = None;
+        let mut section = 0;
+        for (number, line) in spec.lines().enumerate() {
+            let number = number + 1;
+            if line.trim() == "%%" {
+                section += 1;
+                continue;
+            }
+            if line.trim().is_empty() || line.trim_start().starts_with("//") || section > 1 {
+                continue;
+            }
+            if section == 0 {
+                let line = line.trim();
+                let name: String = line.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+                let body = line[name.len()..].trim();
+                if name.is_empty() || body.is_empty() {
+                    return Err(format!("Line {}: expected 'name pattern'", number));
+                }
+                let body = expand(body, &definitions).map_err(|e| format!("Line {}: {}", number, e))?;
+                definitions.push((name, body));
+            } else {
+                let line = line.trim();
+                let end = pattern_end(line).map_err(|e| format!("Line {}: {}", number, e))?;
+                let (pattern, action) = line.split_at(end);
+                let action = parse_action(action).map_err(|e| format!("Line {}: {}", number, e))?;
+                if pattern == "<<EOF>>" {
+                    if action.is_none() {
+                        return Err(format!("Line {}: <<EOF>> needs a tag", number));
+                    }
+                    eof = action;
+                    continue;
+                }
+                let regex = expand(pattern, &definitions).map_err(|e| format!("Line {}: {}", number, e))?;
+                rules.push(LexRule { pattern: pattern.to_owned(), regex, action });
+            }
+        }
+        if rules.is_empty() {
+            return Err("No rules".to_owned());
+        }
+        Ok(LexSpec { definitions, rules, eof })
+    }
+
+    pub fn definitions(&self) -> &[(String, String)] {
+        &self.definitions
+    }
+
+    pub fn rules(&self) -> &[LexRule] {
+        &self.rules
+    }
+
+    /// Тэг конца входа из правила <<EOF>>
+    pub fn eof(&self) -> Option<&str> {
+        self.eof.as_deref()
+    }
+
+    /// Имена тэгов в порядке первого появления; тэг <<EOF>> - последний
+    pub fn tags(&self) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+        for tag in self.rules.iter().filter_map(|rule| rule.action.as_ref()).chain(self.eof.iter()) {
+            if !result.contains(tag) {
+                result.push(tag.clone());
+            }
+        }
+        result
+    }
+
+    /// Один минимальный ДКА по всем правилам; номер шаблона - номер правила
+    pub fn dfa(&self) -> Result<Dfa, String> {
+        let patterns: Vec<&str> = self.rules.iter().map(|rule| &rule.regex[..]).collect();
+        Dfa::from_patterns(&patterns)
+    }
+
+    pub fn lexer(&self) -> Result<SpecLexer, String> {
+        Ok(SpecLexer {
+            dfa: self.dfa()?,
+            actions: self.rules.iter().map(|rule| rule.action.clone()).collect(),
+            eof: self.eof.clone(),
+        })
+    }
+
+    pub fn generate_tags(&self) -> String {
+        let mut result = String::new();
+        result += "#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]\n";
+        result += "#[allow(clippy::upper_case_acronyms)]\n";
+        result += "pub enum Tag {\n";
+        for tag in self.tags() {
+            let _ = writeln!(result, "    {},", tag);
+        }
+        result += "}\n";
+        result
+    }
+
+    ///Исходный текст модуля Rust с тэгами, таблицами ДКА и функцией next_token
+    pub fn generate_module(&self, source: &str) -> Result<String, String> {
+        let dfa = self.dfa()?;
+        let alphabet = dfa.alphabet();
+        let mut result = format!("// Сгенерировано part3::lex_generator из {}, не редактировать вручную.\n\n", source);
+        result += &self.generate_tags();
+
+        let _ = write!(result, "\nconst ALPHABET: [(char, char); {}] = [\n", alphabet.len());
+        for (low, high) in alphabet {
+            let _ = writeln!(result, "    ({:?}, {:?}),", low, high);
+        }
+        result += "];\n\nconst NO_STATE: usize = usize::MAX;\n";
+
+        let _ = write!(result, "\nconst TRANSITIONS: [[usize; {}]; {}] = [\n", alphabet.len(), dfa.states_count());
+        for row in dfa.transitions() {
+            let cells: Vec<String> = row.iter()
+                .map(|target| target.map(|x| x.to_string()).unwrap_or_else(|| "NO_STATE".to_owned()))
+                .collect();
+            let _ = writeln!(result, "    [{}],", cells.join(", "));
+        }
+        result += "];\n";
+
+        result += "\n/// None - состояние не допускающее, Some(None) - лексема пропускается\n";
+        let _ = writeln!(result, "const ACCEPT: [Option<Option<Tag>>; {}] = [", dfa.states_count());
+        for state in 0..dfa.states_count() {
+            let action = match dfa.accepting().get(&state) {
+                None => "None".to_owned(),
+                Some(rule) => match &self.rules[*rule].action {
+                    None => "Some(None)".to_owned(),
+                    Some(tag) => format!("Some(Some(Tag::{}))", tag),
+                },
+            };
+            let _ = writeln!(result, "    {},", action);
+        }
+        result += "];\n";
+
+        let _ = write!(result, "\nconst START: usize = {};\n", dfa.start());
+        if let Some(eof) = &self.eof {
+            let _ = write!(result, "\npub const EOF: Tag = Tag::{};\n", eof);
+        }
+        result += NEXT_TOKEN;
+        Ok(result)
+    }
+}
+
+const NEXT_TOKEN: &str = r#"
+fn symbol(c: char) -> Option<usize> {
+    let index = match ALPHABET.binary_search_by(|(low, _)| low.cmp(&c)) {
+        Ok(index) => index,
+        Err(0) => return None,
+        Err(index) => index - 1,
+    };
+    if c <= ALPHABET[index].1 {
+        Some(index)
+    } else {
+        None
+    }
+}
+
+/// Самое длинное совпадение в начале input: тэг (None для пропускаемых лексем) и длина в байтах
+pub fn next_token<I: IntoIterator<Item=char>>(input: I) -> Option<(Option<Tag>, usize)> {
+    let mut state = START;
+    let mut last = None;
+    let mut length = 0;
+    for c in input {
+        state = match symbol(c).map(|s| TRANSITIONS[state][s]) {
+            Some(next) if next != NO_STATE => next,
+            _ => break,
+        };
+        length += c.len_utf8();
+        if let Some(action) = ACCEPT[state] {
+            last = Some((action, length));
+        }
+    }
+    last
+}
+"#;
+
+///Лексер, работающий прямо по спецификации
+#[derive(Debug, Clone)]
+pub struct SpecLexer {
+    dfa: Dfa,
+    actions: Vec<Option<String>>,
+    eof: Option<String>,
+}
+
+impl SpecLexer {
+    /// Самое длинное совпадение в начале input: тэг (None для пропускаемых лексем) и длина в байтах
+    pub fn next_token(&self, input: &str) -> Option<(Option<&str>, usize)> {
+        self.dfa.longest_match(input)
+            .filter(|m| m.end > 0)
+            .map(|m| (self.actions[m.pattern].as_deref(), m.end))
+    }
+
+    /// Пары (тэг, лексема); в конце - тэг <<EOF>> с пустой лексемой, если он задан
+    pub fn tokenize(&self, input: &str) -> Result<Vec<(String, String)>, String> {
+        let mut result = Vec::new();
+        let mut position = 0;
+        while position < input.len() {
+            match self.next_token(&input[position..]) {
+                Some((tag, length)) => {
+                    if let Some(tag) = tag {
+                        result.push((tag.to_owned(), input[position..position + length].to_owned()));
+                    }
+                    position += length;
+                }
+                None => {
+                    let c = input[position..].chars().next().unwrap_or('\0');
+                    return Err(format!("Unexpected character {:?} at {}", c, position));
+                }
+            }
+        }
+        if let Some(eof) = &self.eof {
+            result.push((eof.clone(), String::new()));
+        }
+        Ok(result)
+    }
+}
+
+// конец шаблона - первый пробельный символ вне [...] и "..."
+fn pattern_end(line: &str) -> Result<usize, String> {
+    let mut in_class = false;
+    let mut in_quotes = false;
+    let mut chars = line.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' if !in_class => in_quotes = !in_quotes,
+            '[' if !in_quotes => in_class = true,
+            ']' if !in_quotes => in_class = false,
+            _ if c.is_whitespace() && !in_class && !in_quotes => return Ok(i),
+            _ => {}
+        }
+    }
+    if in_quotes {
+        Err("Unclosed quote in pattern".to_owned())
+    } else {
+        Ok(line.len())
+    }
+}
+
+fn parse_action(action: &str) -> Result<Option<String>, String> {
+    let mut action = action.trim();
+    if action.is_empty() || action == ";" {
+        return Ok(None);
+    }
+    if action.starts_with('{') && action.ends_with('}') {
+        action = action[1..action.len() - 1].trim();
+        action = action.strip_prefix("return").unwrap_or(action).trim();
+        action = action.strip_suffix(';').unwrap_or(action).trim();
+    }
+    let valid = action.chars().next().map(|c| c.is_alphabetic() || c == '_').unwrap_or(false)
+        && action.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if valid {
+        Ok(Some(action.to_owned()))
+    } else {
+        Err(format!("Invalid action '{}'", action))
+    }
+}
+
+// {name} -> (определение), "..." -> экранированная строка, остальное без изменений
+fn expand(pattern: &str, definitions: &[(String, String)]) -> Result<String, String> {
+    let mut result = String::new();
+    let mut chars = pattern.chars().peekable();
+    let mut in_class = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                result.push(c);
+                if let Some(next) = chars.next() {
+                    result.push(next);
+                }
+            }
+            '[' => {
+                in_class = true;
+                result.push(c);
+            }
+            ']' => {
+                in_class = false;
+                result.push(c);
+            }
+            '"' if !in_class => {
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => {
+                            let escaped = chars.next().ok_or("Unclosed quote in pattern")?;
+                            result.push('\\');
+                            result.push(escaped);
+                        }
+                        Some(c) if c.is_alphanumeric() || c == ' ' => result.push(c),
+                        Some(c) => {
+                            result.push('\\');
+                            result.push(c);
+                        }
+                        None => return Err("Unclosed quote in pattern".to_owned()),
+                    }
+                }
+            }
+            '{' if !in_class => {
+                let name: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                match definitions.iter().find(|(n, _)| *n == name) {
+                    Some((_, body)) => {
+                        result.push('(');
+                        result += body;
+                        result.push(')');
+                    }
+                    None => return Err(format!("Unknown definition '{}'", name)),
+                }
+            }
+            _ => result.push(c),
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Сверяет сгенерированный файл с ожидаемым текстом.
+    /// UPDATE_GENERATED=1 cargo test перезаписывает файл.
+    fn check_generated(path: &str, expected: &str) {
+        let path = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), path);
+        if std::env::var("UPDATE_GENERATED").is_ok() {
+            std::fs::write(&path, expected).unwrap();
+        }
+        let actual = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(actual == expected, "{} is out of date, rerun tests with UPDATE_GENERATED=1", path);
+    }
+
+    #[test]
+    fn parse_spec_test() {
+        let spec = LexSpec::parse("
+// числа и идентификаторы
+digit   [0-9]
+digits  {digit}+
+%%
+[ \\t]+            ;
+{digits}          { return NUM; }
+\"<=\"|\"*\"          OP
+[a-z\"]+          ID
+<<EOF>>           END
+%%
+fn unused() {}
+").unwrap();
+        assert_eq!(spec.definitions()[1], ("digits".to_owned(), "([0-9])+".to_owned()));
+        let regexes: Vec<&str> = spec.rules().iter().map(|x| &x.regex[..]).collect();
+        assert_eq!(regexes, vec!["[ \\t]+", "(([0-9])+)", "\\<\\=|\\*", "[a-z\"]+"]);
+        let actions: Vec<Option<&str>> = spec.rules().iter().map(|x| x.action.as_deref()).collect();
+        assert_eq!(actions, vec![None, Some("NUM"), Some("OP"), Some("ID")]);
+        assert_eq!(spec.eof(), Some("END"));
+        assert_eq!(spec.tags(), vec!["NUM", "OP", "ID", "END"]);
+    }
+
+    #[test]
+    fn parse_spec_error_test() {
+        assert_eq!(LexSpec::parse("%%\n"), Err("No rules".to_owned()));
+        assert_eq!(LexSpec::parse("digit\n%%\n{digit} NUM"), Err("Line 1: expected 'name pattern'".to_owned()));
+        assert_eq!(LexSpec::parse("%%\n{digit} NUM"), Err("Line 2: Unknown definition 'digit'".to_owned()));
+        assert_eq!(LexSpec::parse("%%\n\"abc NUM"), Err("Line 2: Unclosed quote in pattern".to_owned()));
+        assert_eq!(LexSpec::parse("%%\nabc 1NUM"), Err("Line 2: Invalid action '1NUM'".to_owned()));
+    }
+
+    #[test]
+    fn spec_lexer_priority_test() {
+        let lexer = LexSpec::parse("%%\n[ ]+ ;\nIF KEYWORD\n[A-Z]+ ID\n[0-9]+ NUM\n").unwrap().lexer().unwrap();
+        let tokens = lexer.tokenize("IF IFS 42 I").unwrap();
+        let expected: Vec<(String, String)> = vec![("KEYWORD", "IF"), ("ID", "IFS"), ("NUM", "42"), ("ID", "I")]
+            .into_iter().map(|(a, b)| (a.to_owned(), b.to_owned())).collect();
+        assert_eq!(tokens, expected);
+        assert_eq!(lexer.tokenize("IF ?"), Err("Unexpected character '?' at 3".to_owned()));
+    }
+
+    #[test]
+    fn part2_spec_matches_hand_written_lexer_test() {
+        use crate::part2::lexer::{Lexer, Tag};
+        let spec = LexSpec::parse(include_str!("../part2/lexer.l")).unwrap();
+        let input = "a = 3\nb >= 23\nTRUE != x\n(count+2)*3;";
+        let expected = spec.lexer().unwrap().tokenize(input).unwrap();
+        let mut lexer = Lexer::new();
+        let mut actual = Vec::new();
+        loop {
+            let token = lexer.scan(input).unwrap();
+            let text = if token.tag() == &Tag::EOF { String::new() } else { token.value() };
+            actual.push((format!("{:?}", token.tag()), text));
+            if token.tag() == &Tag::EOF {
+                break;
+            }
+        }
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn generated_files_test() {
+        let part2 = LexSpec::parse(include_str!("../part2/lexer.l")).unwrap();
+        let tags = format!("// Сгенерировано part3::lex_generator из lexer.l, не редактировать вручную.\n\n{}",
+                           part2.generate_tags());
+        check_generated("src/part2/lexer_tags.rs", &tags);
+
+        let extended = LexSpec::parse(include_str!("extended_lexer.l")).unwrap();
+        check_generated("src/part3/extended_lexer_gen.rs", &extended.generate_module("extended_lexer.l").unwrap());
+    }
+
+    #[test]
+    fn generated_module_agrees_with_spec_lexer_test() {
+        use crate::part3::extended_lexer_gen::{next_token, Tag};
+        let lexer = LexSpec::parse(include_str!("extended_lexer.l")).unwrap().lexer().unwrap();
+        for input in &["IF x", "IFS", "12.5e3 ", "<=", "\"a b\" c", "\"a\" x \"b\"", "  \t", "?"] {
+            let generated = next_token(input.chars()).map(|(tag, length)| (tag.map(|x| format!("{:?}", x)), length));
+            let expected = lexer.next_token(input).map(|(tag, length)| (tag.map(str::to_owned), length));
+            assert_eq!(generated, expected, "{}", input);
+        }
+        assert_eq!(next_token("THEN".chars()), Some((Some(Tag::THEN), 4)));
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use super::regex_syntax::{CharClass, Regex};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NfaState {
+    pub epsilon: Vec<usize>,
+    pub transitions: Vec<(CharClass, usize)>,
+}
+
+///Недетерминированный конечный автомат, построенный алгоритмом Мак-Нотона-Ямады-Томпсона
+///
+/// Допускающие состояния помечены номером шаблона: при объединении нескольких
+/// шаблонов меньший номер означает больший приоритет.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nfa {
+    states: Vec<NfaState>,
+    start: usize,
+    accepting: BTreeMap<usize, usize>,
+}
+
+///Результат поиска самого длинного префикса
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    pub pattern: usize,
+    /// Длина префикса в байтах
+    pub end: usize,
+}
+
+impl Nfa {
+    pub fn compile(pattern: &str) -> Result<Nfa, String> {
+        Ok(Nfa::thompson(&Regex::parse(pattern)?))
+    }
+
+    pub fn thompson(regex: &Regex) -> Nfa {
+        Nfa::from_regexes(&[regex])
+    }
+
+    /// Общий автомат для нескольких шаблонов: новое начальное состояние
+    /// с ε-переходами в начальные состояния каждого из них
+    pub fn from_patterns(patterns: &[&str]) -> Result<Nfa, String> {
+        let regexes = patterns.iter()
+            .map(|pattern| Regex::parse(pattern))
+            .collect::<Result<Vec<Regex>, String>>()?;
+        Ok(Nfa::from_regexes(&regexes.iter().collect::<Vec<&Regex>>()))
+    }
+
+    pub fn from_regexes(regexes: &[&Regex]) -> Nfa {
+        let mut nfa = Nfa { states: Vec::new(), start: 0, accepting: BTreeMap::new() };
+        if regexes.len() == 1 {
+            let (start, end) = nfa.build(regexes[0]);
+            nfa.start = start;
+            nfa.accepting.insert(end, 0);
+        } else {
+            nfa.start = nfa.add_state();
+            for (pattern, regex) in regexes.iter().enumerate() {
+                let (start, end) = nfa.build(regex);
+                nfa.states[nfa.start].epsilon.push(start);
+                nfa.accepting.insert(end, pattern);
+            }
+        }
+        nfa
+    }
+
+    fn add_state(&mut self) -> usize {
+        self.states.push(NfaState::default());
+        self.states.len() - 1
+    }
+
+    // правила 3.3 - 3.4; возвращает начальное и заключительное состояния N(r)
+    fn build(&mut self, regex: &Regex) -> (usize, usize) {
+        match regex {
+            Regex::Empty => {
+                let start = self.add_state();
+                let end = self.add_state();
+                self.states[start].epsilon.push(end);
+                (start, end)
+            }
+            Regex::Class(class) => {
+                let start = self.add_state();
+                let end = self.add_state();
+                self.states[start].transitions.push((class.clone(), end));
+                (start, end)
+            }
+            Regex::Concat(left, right) => {
+                let (start, middle) = self.build(left);
+                let (next, end) = self.build(right);
+                self.states[middle].epsilon.push(next);
+                (start, end)
+            }
+            Regex::Union(left, right) => {
+                let start = self.add_state();
+                let (a_start, a_end) = self.build(left);
+                let (b_start, b_end) = self.build(right);
+                let end = self.add_state();
+                self.states[start].epsilon.extend(vec![a_start, b_start]);
+                self.states[a_end].epsilon.push(end);
+                self.states[b_end].epsilon.push(end);
+                (start, end)
+            }
+            Regex::Star(inner) | Regex::Plus(inner) | Regex::Optional(inner) => {
+                let start = self.add_state();
+                let (inner_start, inner_end) = self.build(inner);
+                let end = self.add_state();
+                self.states[start].epsilon.push(inner_start);
+                self.states[inner_end].epsilon.push(end);
+                if let Regex::Star(_) | Regex::Optional(_) = regex {
+                    self.states[start].epsilon.push(end);
+                }
+                if let Regex::Star(_) | Regex::Plus(_) = regex {
+                    self.states[inner_end].epsilon.push(inner_start);
+                }
+                (start, end)
+            }
+        }
+    }
+
+    pub fn states(&self) -> &[NfaState] {
+        &self.states
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Допускающие состояния и номера их шаблонов
+    pub fn accepting(&self) -> &BTreeMap<usize, usize> {
+        &self.accepting
+    }
+
+    pub fn epsilon_closure(&self, states: &BTreeSet<usize>) -> BTreeSet<usize> {
+        let mut result = states.clone();
+        let mut stack: Vec<usize> = states.iter().cloned().collect();
+        while let Some(state) = stack.pop() {
+            for next in &self.states[state].epsilon {
+                if result.insert(*next) {
+                    stack.push(*next);
+                }
+            }
+        }
+        result
+    }
+
+    /// move(T, c) без ε-замыкания
+    pub fn move_on(&self, states: &BTreeSet<usize>, c: char) -> BTreeSet<usize> {
+        states.iter()
+            .flat_map(|state| self.states[*state].transitions.iter())
+            .filter(|(class, _)| class.contains(c))
+            .map(|(_, next)| *next)
+            .collect()
+    }
+
+    /// Шаблон с наивысшим приоритетом среди допускающих состояний множества
+    pub fn accepted(&self, states: &BTreeSet<usize>) -> Option<usize> {
+        states.iter().filter_map(|state| self.accepting.get(state)).min().cloned()
+    }
+
+    pub fn initial(&self) -> BTreeSet<usize> {
+        self.epsilon_closure(&vec![self.start].into_iter().collect())
+    }
+
+    /// Моделирование НКА (алгоритм 3.22) на всей строке
+    pub fn is_match(&self, input: &str) -> bool {
+        let mut current = self.initial();
+        for c in input.chars() {
+            current = self.epsilon_closure(&self.move_on(&current, c));
+            if current.is_empty() {
+                return false;
+            }
+        }
+        self.accepted(&current).is_some()
+    }
+
+    /// Самый длинный префикс входа, допускаемый автоматом
+    pub fn longest_match(&self, input: &str) -> Option<Match> {
+        let mut current = self.initial();
+        let mut last = self.accepted(&current).map(|pattern| Match { pattern, end: 0 });
+        for (position, c) in input.char_indices() {
+            current = self.epsilon_closure(&self.move_on(&current, c));
+            if current.is_empty() {
+                break;
+            }
+            if let Some(pattern) = self.accepted(&current) {
+                last = Some(Match { pattern, end: position + c.len_utf8() });
+            }
+        }
+        last
+    }
+}
+
+impl fmt::Display for Nfa {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, state) in self.states.iter().enumerate() {
+            let start = if i == self.start { "->" } else { "  " };
+            match self.accepting.get(&i) {
+                Some(pattern) => write!(f, "{}{} [{}]:", start, i, pattern)?,
+                None => write!(f, "{}{}:", start, i)?,
+            }
+            for next in &state.epsilon {
+                write!(f, " ε->{}", next)?;
+            }
+            for (class, next) in &state.transitions {
+                write!(f, " {}->{}", class, next)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thompson_test() {
+        let nfa = Nfa::compile("a|b").unwrap();
+        assert_eq!(nfa.states().len(), 6);
+        assert_eq!(nfa.to_string(), "->0: ε->1 ε->3\n  1: a->2\n  2: ε->5\n  3: b->4\n  4: ε->5\n  5 [0]:\n");
+        let nfa = Nfa::compile("(a|b)*abb").unwrap();
+        assert!(nfa.is_match("abb"));
+        assert!(nfa.is_match("babaabb"));
+        assert!(!nfa.is_match("abba"));
+        assert!(!nfa.is_match(""));
+    }
+
+    #[test]
+    fn operators_test() {
+        let nfa = Nfa::compile("ab+c?").unwrap();
+        assert!(nfa.is_match("ab"));
+        assert!(nfa.is_match("abbbc"));
+        assert!(!nfa.is_match("ac"));
+        assert!(!nfa.is_match("abcc"));
+        let nfa = Nfa::compile("(ab)*").unwrap();
+        assert!(nfa.is_match(""));
+        assert!(nfa.is_match("abab"));
+        assert!(!nfa.is_match("aba"));
+        let nfa = Nfa::compile("[a-c]x.").unwrap();
+        assert!(nfa.is_match("bxя"));
+        assert!(!nfa.is_match("dxy"));
+        assert!(!nfa.is_match("ax\n"));
+    }
+
+    #[test]
+    fn longest_match_test() {
+        let nfa = Nfa::compile(r"\d+(\.\d+)?([Ee][+-]?\d+)?").unwrap();
+        assert_eq!(nfa.longest_match("142e-1>= s"), Some(Match { pattern: 0, end: 6 }));
+        assert_eq!(nfa.longest_match("112.23\n"), Some(Match { pattern: 0, end: 6 }));
+        // "12." - точка без цифр не входит в число
+        assert_eq!(nfa.longest_match("12.x"), Some(Match { pattern: 0, end: 2 }));
+        assert_eq!(nfa.longest_match("x12"), None);
+        assert_eq!(Nfa::compile("a*").unwrap().longest_match("b"), Some(Match { pattern: 0, end: 0 }));
+    }
+
+    #[test]
+    fn token_patterns_test() {
+        // number, id, relop, string из extended_lexer
+        let nfa = Nfa::from_patterns(&[
+            r"\d+(\.\d+)?([Ee][+-]?\d+)?",
+            "[A-Za-z][A-Za-z0-9]*",
+            "[!=]=|<=?|>=?",
+            r#""([^"\\]|\\.)*""#,
+        ]).unwrap();
+        assert_eq!(nfa.accepting().len(), 4);
+        assert_eq!(nfa.longest_match("2341 hello"), Some(Match { pattern: 0, end: 4 }));
+        assert_eq!(nfa.longest_match("s241  2341"), Some(Match { pattern: 1, end: 4 }));
+        assert_eq!(nfa.longest_match(">= s241"), Some(Match { pattern: 2, end: 2 }));
+        assert_eq!(nfa.longest_match("<me"), Some(Match { pattern: 2, end: 1 }));
+        assert_eq!(nfa.longest_match(r#""va\"s"ya"#), Some(Match { pattern: 3, end: 7 }));
+        assert_eq!(nfa.longest_match("\"unclosed"), None);
+        assert_eq!(nfa.longest_match("!x"), None);
+    }
+
+    #[test]
+    fn priority_test() {
+        let nfa = Nfa::from_patterns(&["IF", "[A-Z]+"]).unwrap();
+        assert_eq!(nfa.longest_match("IF x"), Some(Match { pattern: 0, end: 2 }));
+        assert_eq!(nfa.longest_match("IFS"), Some(Match { pattern: 1, end: 3 }));
+    }
+}
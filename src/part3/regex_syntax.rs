@@ -0,0 +1,405 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+///Класс символов: отсортированные непересекающиеся диапазоны
+///
+/// Отрицание ([^...]) раскрывается при разборе, поэтому класс всегда позитивный.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CharClass {
+    ranges: Vec<(char, char)>,
+}
+
+impl CharClass {
+    pub fn new(ranges: Vec<(char, char)>) -> Self {
+        let mut ranges: Vec<(char, char)> = ranges.into_iter().filter(|(a, b)| a <= b).collect();
+        ranges.sort();
+        let mut merged: Vec<(char, char)> = Vec::new();
+        for (low, high) in ranges {
+            match merged.last_mut() {
+                Some(last) if low <= next_char(last.1).unwrap_or(char::MAX) => {
+                    if high > last.1 {
+                        last.1 = high;
+                    }
+                }
+                _ => merged.push((low, high)),
+            }
+        }
+        CharClass { ranges: merged }
+    }
+
+    pub fn single(c: char) -> Self {
+        CharClass { ranges: vec![(c, c)] }
+    }
+
+    /// Точка: любой символ, кроме перевода строки
+    pub fn any() -> Self {
+        CharClass::single('\n').negate()
+    }
+
+    pub fn ranges(&self) -> &[(char, char)] {
+        &self.ranges
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.ranges.iter().any(|(low, high)| *low <= c && c <= *high)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn negate(&self) -> Self {
+        let mut result = Vec::new();
+        let mut low = Some('\0');
+        for (a, b) in &self.ranges {
+            if let Some(l) = low {
+                if l < *a {
+                    result.push((l, prev_char(*a).unwrap_or('\0')));
+                }
+            }
+            low = next_char(*b);
+        }
+        if let Some(l) = low {
+            result.push((l, char::MAX));
+        }
+        CharClass { ranges: result }
+    }
+
+    fn union(&self, other: &CharClass) -> Self {
+        CharClass::new(self.ranges.iter().chain(other.ranges.iter()).cloned().collect())
+    }
+}
+
+impl fmt::Display for CharClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ranges.len() == 1 && self.ranges[0].0 == self.ranges[0].1 {
+            return write!(f, "{}", escape(self.ranges[0].0));
+        }
+        write!(f, "[")?;
+        for (low, high) in &self.ranges {
+            if low == high {
+                write!(f, "{}", escape(*low))?;
+            } else {
+                write!(f, "{}-{}", escape(*low), escape(*high))?;
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+fn escape(c: char) -> String {
+    match c {
+        '\n' => "\\n".to_owned(),
+        '\t' => "\\t".to_owned(),
+        '\r' => "\\r".to_owned(),
+        '\0' => "\\0".to_owned(),
+        char::MAX => "\\u{10ffff}".to_owned(),
+        '|' | '*' | '+' | '?' | '(' | ')' | '[' | ']' | '.' | '\\' | '-' | '^' | '#' => format!("\\{}", c),
+        _ => c.to_string(),
+    }
+}
+
+/// Следующий символ с учётом дыры суррогатов
+pub fn next_char(c: char) -> Option<char> {
+    match c {
+        '\u{D7FF}' => Some('\u{E000}'),
+        char::MAX => None,
+        _ => std::char::from_u32(c as u32 + 1),
+    }
+}
+
+pub fn prev_char(c: char) -> Option<char> {
+    match c {
+        '\u{E000}' => Some('\u{D7FF}'),
+        '\0' => None,
+        _ => std::char::from_u32(c as u32 - 1),
+    }
+}
+
+///Синтаксическое дерево регулярного выражения
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Regex {
+    /// ε
+    Empty,
+    Class(CharClass),
+    Concat(Box<Regex>, Box<Regex>),
+    Union(Box<Regex>, Box<Regex>),
+    Star(Box<Regex>),
+    Plus(Box<Regex>),
+    Optional(Box<Regex>),
+}
+
+impl Regex {
+    ///Разбор выражения
+    ///
+    /// r | s, rs, r*, r+, r?, (r), ., [abc], [a-z], [^"\\],
+    /// \d \w \s \n \t \r и экранирование метасимволов: \. \* \\ и т.д.
+    pub fn parse(pattern: &str) -> Result<Regex, String> {
+        let mut parser = Parser { chars: pattern.chars().peekable(), position: 0 };
+        let regex = parser.union()?;
+        match parser.peek() {
+            None => Ok(regex),
+            Some(c) => Err(format!("Unexpected '{}' at {}", c, parser.position)),
+        }
+    }
+
+    pub fn concat(left: Regex, right: Regex) -> Regex {
+        Regex::Concat(Box::new(left), Box::new(right))
+    }
+
+    pub fn union(left: Regex, right: Regex) -> Regex {
+        Regex::Union(Box::new(left), Box::new(right))
+    }
+
+    pub fn literal(string: &str) -> Regex {
+        string.chars()
+            .map(|c| Regex::Class(CharClass::single(c)))
+            .fold(Regex::Empty, |acc, next| match acc {
+                Regex::Empty => next,
+                _ => Regex::concat(acc, next),
+            })
+    }
+
+    /// Все классы символов выражения слева направо
+    pub fn classes(&self) -> Vec<&CharClass> {
+        match self {
+            Regex::Empty => Vec::new(),
+            Regex::Class(class) => vec![class],
+            Regex::Concat(a, b) | Regex::Union(a, b) => {
+                let mut result = a.classes();
+                result.extend(b.classes());
+                result
+            }
+            Regex::Star(a) | Regex::Plus(a) | Regex::Optional(a) => a.classes(),
+        }
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Regex::Empty => write!(f, "()"),
+            Regex::Class(class) => write!(f, "{}", class),
+            Regex::Concat(a, b) => {
+                for part in &[a, b] {
+                    match part.as_ref() {
+                        Regex::Union(_, _) => write!(f, "({})", part)?,
+                        _ => write!(f, "{}", part)?,
+                    }
+                }
+                Ok(())
+            }
+            Regex::Union(a, b) => write!(f, "{}|{}", a, b),
+            Regex::Star(a) => write_postfix(f, a, '*'),
+            Regex::Plus(a) => write_postfix(f, a, '+'),
+            Regex::Optional(a) => write_postfix(f, a, '?'),
+        }
+    }
+}
+
+fn write_postfix(f: &mut fmt::Formatter<'_>, regex: &Regex, op: char) -> fmt::Result {
+    match regex {
+        Regex::Class(_) | Regex::Empty => write!(f, "{}{}", regex, op),
+        _ => write!(f, "({}){}", regex, op),
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c.is_some() {
+            self.position += 1;
+        }
+        c
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("Expected '{}' at {}, found '{}'", expected, self.position - 1, c)),
+            None => Err(format!("Expected '{}' at {}, found end of pattern", expected, self.position)),
+        }
+    }
+
+    // union -> concat ('|' concat)*
+    fn union(&mut self) -> Result<Regex, String> {
+        let mut result = self.concat()?;
+        while self.peek() == Some('|') {
+            self.next();
+            result = Regex::union(result, self.concat()?);
+        }
+        Ok(result)
+    }
+
+    // concat -> repeat*
+    fn concat(&mut self) -> Result<Regex, String> {
+        let mut result = Regex::Empty;
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let next = self.repeat()?;
+            result = match result {
+                Regex::Empty => next,
+                _ => Regex::concat(result, next),
+            };
+        }
+        Ok(result)
+    }
+
+    // repeat -> atom ('*' | '+' | '?')*
+    fn repeat(&mut self) -> Result<Regex, String> {
+        let mut result = self.atom()?;
+        while let Some(c) = self.peek() {
+            result = match c {
+                '*' => Regex::Star(Box::new(result)),
+                '+' => Regex::Plus(Box::new(result)),
+                '?' => Regex::Optional(Box::new(result)),
+                _ => break,
+            };
+            self.next();
+        }
+        Ok(result)
+    }
+
+    fn atom(&mut self) -> Result<Regex, String> {
+        let position = self.position;
+        match self.next() {
+            Some('(') => {
+                let result = self.union()?;
+                self.expect(')')?;
+                Ok(result)
+            }
+            Some('[') => Ok(Regex::Class(self.class()?)),
+            Some('.') => Ok(Regex::Class(CharClass::any())),
+            Some('\\') => Ok(Regex::Class(self.escape()?)),
+            Some(c) if c == '*' || c == '+' || c == '?' => Err(format!("Nothing to repeat at {}", position)),
+            Some(c) => Ok(Regex::Class(CharClass::single(c))),
+            None => Err(format!("Unexpected end of pattern at {}", position)),
+        }
+    }
+
+    fn escape(&mut self) -> Result<CharClass, String> {
+        match self.next() {
+            Some('d') => Ok(CharClass::new(vec![('0', '9')])),
+            Some('w') => Ok(CharClass::new(vec![('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')])),
+            Some('s') => Ok(CharClass::new(vec![(' ', ' '), ('\t', '\n'), ('\r', '\r')])),
+            Some('n') => Ok(CharClass::single('\n')),
+            Some('t') => Ok(CharClass::single('\t')),
+            Some('r') => Ok(CharClass::single('\r')),
+            Some('0') => Ok(CharClass::single('\0')),
+            Some(c) => Ok(CharClass::single(c)),
+            None => Err(format!("Unexpected end of pattern at {}", self.position)),
+        }
+    }
+
+    // [abc], [a-z0-9], [^"\\]
+    fn class(&mut self) -> Result<CharClass, String> {
+        let start = self.position - 1;
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.next();
+        }
+        let mut result = CharClass::new(Vec::new());
+        let mut first = true;
+        loop {
+            let low = match self.next() {
+                None => return Err(format!("Unclosed class at {}", start)),
+                Some(']') if !first => break,
+                Some('\\') => {
+                    let escaped = self.escape()?;
+                    if escaped.ranges().len() != 1 || escaped.ranges()[0].0 != escaped.ranges()[0].1 {
+                        result = result.union(&escaped);
+                        first = false;
+                        continue;
+                    }
+                    escaped.ranges()[0].0
+                }
+                Some(c) => c,
+            };
+            first = false;
+            let mut lookahead = self.chars.clone();
+            if lookahead.next() == Some('-') && lookahead.peek().map(|c| *c != ']').unwrap_or(false) {
+                self.next();
+                let high = match self.next() {
+                    Some('\\') => {
+                        let escaped = self.escape()?;
+                        escaped.ranges()[0].0
+                    }
+                    Some(c) => c,
+                    None => return Err(format!("Unclosed class at {}", start)),
+                };
+                if high < low {
+                    return Err(format!("Invalid range {}-{} at {}", low, high, self.position - 1));
+                }
+                result = result.union(&CharClass::new(vec![(low, high)]));
+            } else {
+                result = result.union(&CharClass::single(low));
+            }
+        }
+        Ok(if negated { result.negate() } else { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn char_class_test() {
+        let class = CharClass::new(vec![('a', 'f'), ('0', '9'), ('c', 'z'), ('A', 'A')]);
+        assert_eq!(class.ranges(), &[('0', '9'), ('A', 'A'), ('a', 'z')]);
+        assert!(class.contains('q'));
+        assert!(!class.contains('B'));
+        let negated = class.negate();
+        assert!(negated.contains('B'));
+        assert!(!negated.contains('5'));
+        assert_eq!(negated.negate(), class);
+        assert!(CharClass::any().contains('\u{E000}'));
+        assert!(!CharClass::any().contains('\n'));
+        assert_eq!(CharClass::new(vec![('a', 'b'), ('c', 'd')]).ranges(), &[('a', 'd')]);
+    }
+
+    #[test]
+    fn parse_test() {
+        assert_eq!(Regex::parse("ab").unwrap(), Regex::literal("ab"));
+        assert_eq!(Regex::parse("(a|b)*abb").unwrap().to_string(), "(a|b)*abb");
+        assert_eq!(Regex::parse("a|bc?").unwrap(),
+                   Regex::union(Regex::literal("a"),
+                                Regex::concat(Regex::literal("b"), Regex::Optional(Box::new(Regex::literal("c"))))));
+        assert_eq!(Regex::parse(r"\d+(\.\d+)?").unwrap().to_string(), r"[0-9]+(\.[0-9]+)?");
+        assert_eq!(Regex::parse("[A-Za-z_]").unwrap().to_string(), "[A-Z_a-z]");
+        assert_eq!(Regex::parse("[+-]").unwrap().to_string(), r"[\+\-]");
+        assert_eq!(Regex::parse("[]a]").unwrap().to_string(), r"[\]a]");
+        assert_eq!(Regex::parse("").unwrap(), Regex::Empty);
+
+        let string = Regex::parse(r#"[^"\\]"#).unwrap();
+        match string {
+            Regex::Class(class) => {
+                assert!(class.contains('a'));
+                assert!(!class.contains('"'));
+                assert!(!class.contains('\\'));
+            }
+            _ => panic!("Expected class"),
+        }
+    }
+
+    #[test]
+    fn parse_error_test() {
+        assert_eq!(Regex::parse("(ab"), Err("Expected ')' at 3, found end of pattern".to_owned()));
+        assert_eq!(Regex::parse("ab)"), Err("Unexpected ')' at 2".to_owned()));
+        assert_eq!(Regex::parse("*a"), Err("Nothing to repeat at 0".to_owned()));
+        assert_eq!(Regex::parse("[a-"), Err("Unclosed class at 0".to_owned()));
+        assert_eq!(Regex::parse("[z-a]"), Err("Invalid range z-a at 3".to_owned()));
+    }
+}
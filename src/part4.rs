@@ -0,0 +1,4 @@
+pub mod synt_analizer;
+pub mod lr_analizer;
+pub mod lalr_analizer;
+pub mod yacc_generator;
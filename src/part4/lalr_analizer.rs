@@ -0,0 +1,313 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use super::lr_analizer::{ConflictKind, Item, ItemSet, LrConflict, LrState, LrTable};
+use super::synt_analizer::{first_of_sequence, Grammar, Symbol, SymbolSets, END_MARKER, EPSILON};
+
+///LR(1)-пункт [A -> α . β, a]
+pub type Lr1Item = (Item, String);
+
+pub type Lr1ItemSet = BTreeSet<Lr1Item>;
+
+pub fn closure(grammar: &Grammar, first: &SymbolSets, items: &Lr1ItemSet) -> Lr1ItemSet {
+    let mut result = items.clone();
+    let mut work: Vec<Lr1Item> = items.iter().cloned().collect();
+    while let Some((item, lookahead)) = work.pop() {
+        if let Some(Symbol::NonTerminal(name)) = item.next_symbol(grammar) {
+            // FIRST(βa)
+            let mut rest = grammar.production(item.production).body[item.dot + 1..].to_vec();
+            rest.push(Symbol::Terminal(lookahead.clone()));
+            let lookaheads = first_of_sequence(first, &rest);
+            for (index, production) in grammar.productions().iter().enumerate() {
+                if &production.head != name {
+                    continue;
+                }
+                for terminal in lookaheads.iter().filter(|x| *x != EPSILON) {
+                    let new_item = (Item::new(index, 0), terminal.clone());
+                    if result.insert(new_item.clone()) {
+                        work.push(new_item);
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+pub fn goto(grammar: &Grammar, first: &SymbolSets, items: &Lr1ItemSet, symbol: &Symbol) -> Lr1ItemSet {
+    let moved = items.iter()
+        .filter(|(item, _)| item.next_symbol(grammar) == Some(symbol))
+        .map(|(item, lookahead)| (item.advance(), lookahead.clone()))
+        .collect();
+    closure(grammar, first, &moved)
+}
+
+/// Пункты с одинаковым ядром объединяются, предпросмотры собираются в множество
+fn to_state(items: &Lr1ItemSet) -> LrState {
+    let mut state = LrState::new();
+    for (item, lookahead) in items {
+        state.entry(*item).or_default().insert(lookahead.clone());
+    }
+    state
+}
+
+///Каноническая система множеств LR(1)-пунктов
+#[derive(Debug, Clone)]
+pub struct Lr1Automaton {
+    grammar: Grammar,
+    states: Vec<Lr1ItemSet>,
+    transitions: BTreeMap<(usize, Symbol), usize>,
+}
+
+impl Lr1Automaton {
+    /// Грамматика пополняется автоматически
+    pub fn new(grammar: &Grammar) -> Self {
+        let grammar = grammar.augmented();
+        let first = grammar.first_sets();
+        let start: Lr1ItemSet = vec![(Item::new(0, 0), END_MARKER.to_owned())].into_iter().collect();
+        let mut states = vec![closure(&grammar, &first, &start)];
+        let mut index: BTreeMap<Lr1ItemSet, usize> = BTreeMap::new();
+        index.insert(states[0].clone(), 0);
+        let mut transitions = BTreeMap::new();
+        let mut current = 0;
+        while current < states.len() {
+            let symbols: BTreeSet<&Symbol> = states[current].iter()
+                .filter_map(|(item, _)| item.next_symbol(&grammar))
+                .collect();
+            for symbol in symbols {
+                let target = goto(&grammar, &first, &states[current], symbol);
+                let id = match index.get(&target) {
+                    Some(id) => *id,
+                    None => {
+                        index.insert(target.clone(), states.len());
+                        states.push(target);
+                        states.len() - 1
+                    }
+                };
+                transitions.insert((current, symbol.clone()), id);
+            }
+            current += 1;
+        }
+        Lr1Automaton { grammar, states, transitions }
+    }
+
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    pub fn states(&self) -> &[Lr1ItemSet] {
+        &self.states
+    }
+
+    pub fn transitions(&self) -> &BTreeMap<(usize, Symbol), usize> {
+        &self.transitions
+    }
+
+    /// Ядро состояния - его LR(0)-пункты без предпросмотров
+    pub fn core(&self, state: usize) -> ItemSet {
+        self.states[state].iter().map(|(item, _)| *item).collect()
+    }
+}
+
+impl fmt::Display for Lr1Automaton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, state) in self.states.iter().enumerate() {
+            writeln!(f, "I{}:", i)?;
+            for (item, lookaheads) in to_state(state) {
+                let lookaheads: Vec<String> = lookaheads.into_iter().collect();
+                writeln!(f, "    [{}, {}]", item.to_string(&self.grammar), lookaheads.join("/"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+///LALR-состояние, собранное из нескольких LR(1)-состояний с общим ядром
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateMerge {
+    pub state: usize,
+    pub lr1_states: Vec<usize>,
+    /// Конфликты, которых не было ни в одном из исходных LR(1)-состояний
+    pub new_conflicts: Vec<LrConflict>,
+}
+
+impl fmt::Display for StateMerge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let merged: Vec<String> = self.lr1_states.iter().map(|x| x.to_string()).collect();
+        write!(f, "LALR state {} merges LR(1) states {}", self.state, merged.join(", "))?;
+        for conflict in &self.new_conflicts {
+            write!(f, "\n{}", conflict)?;
+        }
+        Ok(())
+    }
+}
+
+///Результат построения LALR(1): таблица и сведения о слиянии состояний
+#[derive(Debug, Clone)]
+pub struct LalrAnalysis {
+    pub table: LrTable,
+    pub merges: Vec<StateMerge>,
+}
+
+impl LalrAnalysis {
+    pub fn new(grammar: &Grammar) -> Self {
+        let automaton = Lr1Automaton::new(grammar);
+        let lr1_table = LrTable::from_lr1(&automaton);
+
+        // номера LALR-состояний в порядке первого появления ядра
+        let mut cores: BTreeMap<ItemSet, usize> = BTreeMap::new();
+        let mut lalr_of = Vec::new();
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for state in 0..automaton.states().len() {
+            let core = automaton.core(state);
+            let next = cores.len();
+            let id = *cores.entry(core).or_insert(next);
+            if id == groups.len() {
+                groups.push(Vec::new());
+            }
+            groups[id].push(state);
+            lalr_of.push(id);
+        }
+
+        let mut states = vec![LrState::new(); groups.len()];
+        for (state, items) in automaton.states().iter().enumerate() {
+            for (item, lookahead) in items {
+                states[lalr_of[state]].entry(*item).or_default().insert(lookahead.clone());
+            }
+        }
+        let transitions = automaton.transitions().iter()
+            .map(|((state, symbol), target)| ((lalr_of[*state], symbol.clone()), lalr_of[*target]))
+            .collect();
+        let table = LrTable::build(automaton.grammar().clone(), states, &transitions, |_, lookaheads| lookaheads.clone());
+
+        let old_conflicts: BTreeSet<(usize, String)> = lr1_table.conflicts().into_iter()
+            .map(|conflict| (conflict.state, conflict.terminal))
+            .collect();
+        let conflicts = table.conflicts();
+        let merges = groups.into_iter().enumerate()
+            .filter(|(_, group)| group.len() > 1)
+            .map(|(state, lr1_states)| {
+                let new_conflicts = conflicts.iter()
+                    .filter(|conflict| conflict.state == state && conflict.kind == ConflictKind::ReduceReduce)
+                    .filter(|conflict| lr1_states.iter()
+                        .all(|old| !old_conflicts.contains(&(*old, conflict.terminal.clone()))))
+                    .cloned()
+                    .collect();
+                StateMerge { state, lr1_states, new_conflicts }
+            })
+            .collect();
+        LalrAnalysis { table, merges }
+    }
+
+    /// Слияния, которые внесли новые конфликты свёртка/свёртка
+    pub fn conflicting_merges(&self) -> Vec<&StateMerge> {
+        self.merges.iter().filter(|merge| !merge.new_conflicts.is_empty()).collect()
+    }
+}
+
+impl LrTable {
+    /// Каноническая LR(1)-таблица: свёртка только по предпросмотрам пункта
+    pub fn lr1(grammar: &Grammar) -> Self {
+        LrTable::from_lr1(&Lr1Automaton::new(grammar))
+    }
+
+    /// LALR(1)-таблица, построенная слиянием LR(1)-состояний с общими ядрами
+    pub fn lalr(grammar: &Grammar) -> Self {
+        LalrAnalysis::new(grammar).table
+    }
+
+    fn from_lr1(automaton: &Lr1Automaton) -> Self {
+        let states = automaton.states().iter().map(to_state).collect();
+        LrTable::build(automaton.grammar().clone(), states, automaton.transitions(), |_, lookaheads| lookaheads.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part4::lr_analizer::{Action, LrParser};
+    use crate::part4::synt_analizer::Lexeme;
+
+    // пример 4.48: не SLR, но LALR(1)
+    const ASSIGNMENTS: &str = "
+        S -> L = R | R
+        L -> * R | id
+        R -> L
+    ";
+
+    // пример 4.58: LR(1), но не LALR(1)
+    const NOT_LALR: &str = "
+        S -> a A d | b B d | a B e | b A e
+        A -> c
+        B -> c
+    ";
+
+    #[test]
+    fn lr1_closure_test() {
+        let g = Grammar::parse(ASSIGNMENTS).unwrap().augmented();
+        let first = g.first_sets();
+        let start: Lr1ItemSet = vec![(Item::new(0, 0), END_MARKER.to_owned())].into_iter().collect();
+        let items: Vec<String> = to_state(&closure(&g, &first, &start)).into_iter()
+            .map(|(item, lookaheads)| {
+                let lookaheads: Vec<String> = lookaheads.into_iter().collect();
+                format!("[{}, {}]", item.to_string(&g), lookaheads.join("/"))
+            })
+            .collect();
+        assert_eq!(items, vec![
+            "[S' -> . S, $]",
+            "[S -> . L = R, $]",
+            "[S -> . R, $]",
+            "[L -> . * R, $/=]",
+            "[L -> . id, $/=]",
+            "[R -> . L, $]",
+        ]);
+    }
+
+    #[test]
+    fn lr1_automaton_test() {
+        let automaton = Lr1Automaton::new(&Grammar::parse(ASSIGNMENTS).unwrap());
+        assert_eq!(automaton.states().len(), 14);
+        assert!(automaton.to_string().contains("    [L -> . * R, $/=]\n"));
+    }
+
+    #[test]
+    fn lr1_and_lalr_resolve_slr_conflict_test() {
+        let g = Grammar::parse(ASSIGNMENTS).unwrap();
+        assert!(!LrTable::slr(&g).is_deterministic());
+        let lr1 = LrTable::lr1(&g);
+        assert!(lr1.is_deterministic());
+        assert_eq!(lr1.states().len(), 14);
+        let analysis = LalrAnalysis::new(&g);
+        assert!(analysis.table.is_deterministic());
+        assert_eq!(analysis.table.states().len(), 10);
+        assert_eq!(analysis.merges.len(), 4);
+        assert!(analysis.conflicting_merges().is_empty());
+    }
+
+    #[test]
+    fn lalr_merge_conflict_test() {
+        let g = Grammar::parse(NOT_LALR).unwrap();
+        assert!(LrTable::lr1(&g).is_deterministic());
+        let analysis = LalrAnalysis::new(&g);
+        assert!(!analysis.table.is_deterministic());
+        let merges = analysis.conflicting_merges();
+        assert_eq!(merges.len(), 1);
+        let merge = merges[0];
+        assert_eq!(merge.lr1_states.len(), 2);
+        let terminals: Vec<&str> = merge.new_conflicts.iter().map(|x| &x.terminal[..]).collect();
+        assert_eq!(terminals, vec!["d", "e"]);
+        assert_eq!(merge.new_conflicts[0].actions, vec![Action::Reduce(5), Action::Reduce(6)]);
+        assert_eq!(merge.new_conflicts[0].items, vec!["[A -> c ., d/e]".to_owned(), "[B -> c ., d/e]".to_owned()]);
+        assert!(merge.to_string().starts_with(&format!("LALR state {} merges LR(1) states {}, {}\nState {}, 'd': reduce/reduce",
+                                                       merge.state, merge.lr1_states[0], merge.lr1_states[1], merge.state)));
+    }
+
+    #[test]
+    fn lalr_parse_test() {
+        let parser = LrParser::new(LrTable::lalr(&Grammar::parse(ASSIGNMENTS).unwrap())).unwrap();
+        assert_eq!(parser.parse("* p = q").unwrap().to_string(), "S(L(* R(L(p))) = R(L(q)))");
+        let parser = LrParser::new(LrTable::lr1(&Grammar::parse(NOT_LALR).unwrap())).unwrap();
+        let tokens = vec![Lexeme::new("a", "a"), Lexeme::new("c", "c"), Lexeme::new("e", "e")];
+        assert_eq!(parser.parse_tokens(tokens).unwrap().to_string(), "S(a B(c) e)");
+    }
+}
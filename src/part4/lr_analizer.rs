@@ -0,0 +1,554 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::part2::lexer::Token;
+use super::synt_analizer::{Grammar, Lexeme, ParseError, ParseTree, Symbol, END_MARKER, tokenize};
+
+///LR(0)-пункт: продукция с точкой в теле, A -> α . β
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Item {
+    pub production: usize,
+    pub dot: usize,
+}
+
+impl Item {
+    pub fn new(production: usize, dot: usize) -> Self {
+        Item { production, dot }
+    }
+
+    pub fn next_symbol<'g>(&self, grammar: &'g Grammar) -> Option<&'g Symbol> {
+        grammar.production(self.production).body.get(self.dot)
+    }
+
+    pub fn is_complete(&self, grammar: &Grammar) -> bool {
+        self.dot >= grammar.production(self.production).body.len()
+    }
+
+    pub fn advance(&self) -> Self {
+        Item { production: self.production, dot: self.dot + 1 }
+    }
+
+    pub fn to_string(&self, grammar: &Grammar) -> String {
+        let production = grammar.production(self.production);
+        let mut result = format!("{} ->", production.head);
+        for (i, symbol) in production.body.iter().enumerate() {
+            if i == self.dot {
+                result += " .";
+            }
+            result += " ";
+            result += symbol.name();
+        }
+        if self.is_complete(grammar) {
+            result += " .";
+        }
+        result
+    }
+}
+
+pub type ItemSet = BTreeSet<Item>;
+
+pub fn closure(grammar: &Grammar, items: &ItemSet) -> ItemSet {
+    let mut result = items.clone();
+    let mut work: Vec<Item> = items.iter().cloned().collect();
+    while let Some(item) = work.pop() {
+        if let Some(Symbol::NonTerminal(name)) = item.next_symbol(grammar) {
+            for (index, production) in grammar.productions().iter().enumerate() {
+                let new_item = Item::new(index, 0);
+                if &production.head == name && result.insert(new_item) {
+                    work.push(new_item);
+                }
+            }
+        }
+    }
+    result
+}
+
+pub fn goto(grammar: &Grammar, items: &ItemSet, symbol: &Symbol) -> ItemSet {
+    let moved = items.iter()
+        .filter(|item| item.next_symbol(grammar) == Some(symbol))
+        .map(Item::advance)
+        .collect();
+    closure(grammar, &moved)
+}
+
+///Каноническая система множеств LR(0)-пунктов
+#[derive(Debug, Clone)]
+pub struct Lr0Automaton {
+    grammar: Grammar,
+    states: Vec<ItemSet>,
+    transitions: BTreeMap<(usize, Symbol), usize>,
+}
+
+impl Lr0Automaton {
+    /// Грамматика пополняется автоматически
+    pub fn new(grammar: &Grammar) -> Self {
+        let grammar = grammar.augmented();
+        let start: ItemSet = vec![Item::new(0, 0)].into_iter().collect();
+        let mut states = vec![closure(&grammar, &start)];
+        let mut index: BTreeMap<ItemSet, usize> = BTreeMap::new();
+        index.insert(states[0].clone(), 0);
+        let mut transitions = BTreeMap::new();
+        let mut current = 0;
+        while current < states.len() {
+            let symbols: BTreeSet<&Symbol> = states[current].iter()
+                .filter_map(|item| item.next_symbol(&grammar))
+                .collect();
+            for symbol in symbols {
+                let target = goto(&grammar, &states[current], symbol);
+                let id = match index.get(&target) {
+                    Some(id) => *id,
+                    None => {
+                        index.insert(target.clone(), states.len());
+                        states.push(target);
+                        states.len() - 1
+                    }
+                };
+                transitions.insert((current, symbol.clone()), id);
+            }
+            current += 1;
+        }
+        Lr0Automaton { grammar, states, transitions }
+    }
+
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    pub fn states(&self) -> &[ItemSet] {
+        &self.states
+    }
+
+    pub fn transitions(&self) -> &BTreeMap<(usize, Symbol), usize> {
+        &self.transitions
+    }
+
+    pub fn goto_state(&self, state: usize, symbol: &Symbol) -> Option<usize> {
+        self.transitions.get(&(state, symbol.clone())).cloned()
+    }
+}
+
+impl fmt::Display for Lr0Automaton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, state) in self.states.iter().enumerate() {
+            writeln!(f, "I{}:", i)?;
+            for item in state {
+                writeln!(f, "    {}", item.to_string(&self.grammar))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    Shift(usize),
+    Reduce(usize),
+    Accept,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Shift(state) => write!(f, "s{}", state),
+            Action::Reduce(production) => write!(f, "r{}", production),
+            Action::Accept => write!(f, "acc"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictKind {
+    ShiftReduce,
+    ReduceReduce,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LrConflict {
+    pub state: usize,
+    pub terminal: String,
+    pub kind: ConflictKind,
+    pub actions: Vec<Action>,
+    /// Пункты состояния, породившие конфликтующие действия
+    pub items: Vec<String>,
+}
+
+impl fmt::Display for LrConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            ConflictKind::ShiftReduce => "shift/reduce",
+            ConflictKind::ReduceReduce => "reduce/reduce",
+        };
+        let actions: Vec<String> = self.actions.iter().map(|x| x.to_string()).collect();
+        write!(f, "State {}, '{}': {} conflict ({})", self.state, self.terminal, kind, actions.join(", "))?;
+        for item in &self.items {
+            write!(f, "\n    {}", item)?;
+        }
+        Ok(())
+    }
+}
+
+/// Множество пунктов состояния вместе с их предпросмотрами (для SLR они пусты)
+pub type LrState = BTreeMap<Item, BTreeSet<String>>;
+
+///Таблицы ACTION и GOTO
+#[derive(Debug, Clone)]
+pub struct LrTable {
+    grammar: Grammar,
+    states: Vec<LrState>,
+    action: BTreeMap<(usize, String), Vec<Action>>,
+    goto: BTreeMap<(usize, String), usize>,
+}
+
+impl LrTable {
+    /// SLR(1): свёртка по A -> α . для всех терминалов из FOLLOW(A)
+    pub fn slr(grammar: &Grammar) -> Self {
+        let automaton = Lr0Automaton::new(grammar);
+        let first = automaton.grammar().first_sets();
+        let follow = automaton.grammar().follow_sets(&first);
+        let states = automaton.states().iter()
+            .map(|state| state.iter().map(|item| (*item, BTreeSet::new())).collect())
+            .collect();
+        let grammar = automaton.grammar().clone();
+        LrTable::build(grammar.clone(), states, automaton.transitions(), |item, _| {
+            follow[&grammar.production(item.production).head].clone()
+        })
+    }
+
+    /// Заполняет таблицы по готовому автомату; reduce_on возвращает терминалы,
+    /// по которым выполняется свёртка для завершённого пункта
+    pub(crate) fn build<F>(grammar: Grammar,
+                           states: Vec<LrState>,
+                           transitions: &BTreeMap<(usize, Symbol), usize>,
+                           reduce_on: F) -> Self
+        where F: Fn(&Item, &BTreeSet<String>) -> BTreeSet<String> {
+        let mut action: BTreeMap<(usize, String), Vec<Action>> = BTreeMap::new();
+        let mut goto = BTreeMap::new();
+        for ((state, symbol), target) in transitions {
+            match symbol {
+                Symbol::Terminal(name) => add_action(&mut action, *state, name, Action::Shift(*target)),
+                Symbol::NonTerminal(name) => {
+                    goto.insert((*state, name.clone()), *target);
+                }
+            }
+        }
+        for (state, items) in states.iter().enumerate() {
+            for (item, lookaheads) in items {
+                if !item.is_complete(&grammar) {
+                    continue;
+                }
+                if item.production == 0 {
+                    add_action(&mut action, state, END_MARKER, Action::Accept);
+                } else {
+                    for terminal in reduce_on(item, lookaheads) {
+                        add_action(&mut action, state, &terminal, Action::Reduce(item.production));
+                    }
+                }
+            }
+        }
+        LrTable { grammar, states, action, goto }
+    }
+
+    /// Пополненная грамматика; продукция 0 - S' -> S
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    pub fn states(&self) -> &[LrState] {
+        &self.states
+    }
+
+    pub fn action(&self, state: usize, terminal: &str) -> &[Action] {
+        self.action.get(&(state, terminal.to_owned()))
+            .map(|x| &x[..])
+            .unwrap_or(&[])
+    }
+
+    pub fn goto(&self, state: usize, nonterminal: &str) -> Option<usize> {
+        self.goto.get(&(state, nonterminal.to_owned())).cloned()
+    }
+
+    /// Терминалы, для которых в состоянии определено действие
+    pub fn expected(&self, state: usize) -> Vec<String> {
+        self.action.keys()
+            .filter(|(s, _)| *s == state)
+            .map(|(_, terminal)| terminal.clone())
+            .collect()
+    }
+
+    pub fn conflicts(&self) -> Vec<LrConflict> {
+        self.action.iter()
+            .filter(|(_, actions)| actions.len() > 1)
+            .map(|((state, terminal), actions)| {
+                let kind = if actions.iter().any(|x| matches!(x, Action::Shift(_))) {
+                    ConflictKind::ShiftReduce
+                } else {
+                    ConflictKind::ReduceReduce
+                };
+                LrConflict {
+                    state: *state,
+                    terminal: terminal.clone(),
+                    kind,
+                    actions: actions.clone(),
+                    items: self.items_for(*state, terminal, actions),
+                }
+            })
+            .collect()
+    }
+
+    /// Оставляет в каждой конфликтной ячейке действие, выбранное choose;
+    /// None превращает ячейку в ошибку
+    pub(crate) fn resolve<F>(&mut self, mut choose: F) where F: FnMut(&LrConflict) -> Option<Action> {
+        for conflict in self.conflicts() {
+            let key = (conflict.state, conflict.terminal.clone());
+            match choose(&conflict) {
+                Some(action) => {
+                    self.action.insert(key, vec![action]);
+                }
+                None => {
+                    self.action.remove(&key);
+                }
+            }
+        }
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        self.action.values().all(|actions| actions.len() == 1)
+    }
+
+    pub fn item_to_string(&self, item: &Item, lookaheads: &BTreeSet<String>) -> String {
+        if lookaheads.is_empty() {
+            item.to_string(&self.grammar)
+        } else {
+            let lookaheads: Vec<&str> = lookaheads.iter().map(|x| &x[..]).collect();
+            format!("[{}, {}]", item.to_string(&self.grammar), lookaheads.join("/"))
+        }
+    }
+
+    fn items_for(&self, state: usize, terminal: &str, actions: &[Action]) -> Vec<String> {
+        let mut result = Vec::new();
+        for (item, lookaheads) in &self.states[state] {
+            let involved = actions.iter().any(|action| match action {
+                Action::Shift(_) => match item.next_symbol(&self.grammar) {
+                    Some(Symbol::Terminal(name)) => name == terminal,
+                    _ => false,
+                },
+                Action::Reduce(production) => item.production == *production && item.is_complete(&self.grammar),
+                Action::Accept => item.production == 0 && item.is_complete(&self.grammar),
+            });
+            if involved {
+                result.push(self.item_to_string(item, lookaheads));
+            }
+        }
+        result
+    }
+}
+
+fn add_action(action: &mut BTreeMap<(usize, String), Vec<Action>>, state: usize, terminal: &str, new: Action) {
+    let actions = action.entry((state, terminal.to_owned())).or_default();
+    if !actions.contains(&new) {
+        actions.push(new);
+        actions.sort();
+    }
+}
+
+impl fmt::Display for LrTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, production) in self.grammar.productions().iter().enumerate() {
+            writeln!(f, "({}) {}", index, production)?;
+        }
+        for state in 0..self.states.len() {
+            write!(f, "{}:", state)?;
+            for ((_, terminal), actions) in self.action.range((state, String::new())..(state + 1, String::new())) {
+                let actions: Vec<String> = actions.iter().map(|x| x.to_string()).collect();
+                write!(f, " {}={}", terminal, actions.join("/"))?;
+            }
+            for ((_, nonterminal), target) in self.goto.range((state, String::new())..(state + 1, String::new())) {
+                write!(f, " {}={}", nonterminal, target)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+///Управляющая программа LR-анализатора (алгоритм 4.44)
+pub struct LrParser {
+    table: LrTable,
+}
+
+impl LrParser {
+    pub fn new(table: LrTable) -> Result<Self, Vec<LrConflict>> {
+        if table.is_deterministic() {
+            Ok(LrParser { table })
+        } else {
+            Err(table.conflicts())
+        }
+    }
+
+    pub fn table(&self) -> &LrTable {
+        &self.table
+    }
+
+    pub fn parse(&self, input: &str) -> Result<ParseTree, ParseError> {
+        let (lexemes, errors) = tokenize(input);
+        self.parse_tokens(lexemes).map_err(|error| error.with_lexical(&errors))
+    }
+
+    /// Принимает токены лексера из part2 напрямую
+    pub fn parse_lexer_tokens<I: IntoIterator<Item=Box<dyn Token>>>(&self, tokens: I) -> Result<ParseTree, ParseError> {
+        self.parse_tokens(tokens.into_iter().map(|token| Lexeme::from_token(token.as_ref())))
+    }
+
+    pub fn parse_tokens<I: IntoIterator<Item=Lexeme>>(&self, tokens: I) -> Result<ParseTree, ParseError> {
+        let mut tokens: Vec<Lexeme> = tokens.into_iter().collect();
+        if tokens.last().map(|x| x.terminal != END_MARKER).unwrap_or(true) {
+            tokens.push(Lexeme::end());
+        }
+        let mut states = vec![0];
+        let mut trees: Vec<ParseTree> = Vec::new();
+        let mut position = 0;
+        loop {
+            let state = *states.last().unwrap_or(&0);
+            let lookahead = &tokens[position];
+            match self.table.action(state, &lookahead.terminal).first() {
+                Some(Action::Shift(next)) => {
+                    trees.push(ParseTree::Leaf(lookahead.clone()));
+                    states.push(*next);
+                    position += 1;
+                }
+                Some(Action::Reduce(index)) => {
+                    let production = self.table.grammar().production(*index);
+                    let children = trees.split_off(trees.len() - production.body.len());
+                    states.truncate(states.len() - production.body.len());
+                    let top = *states.last().unwrap_or(&0);
+                    match self.table.goto(top, &production.head) {
+                        Some(next) => states.push(next),
+                        None => unreachable!("GOTO[{}, {}] is empty", top, production.head),
+                    }
+                    trees.push(ParseTree::Node { head: production.head.clone(), children });
+                }
+                Some(Action::Accept) => {
+                    return Ok(trees.pop().unwrap_or_else(|| unreachable!()));
+                }
+                None => {
+                    return Err(ParseError::new(position, lookahead, self.table.expected(state)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part2::lexer::{Lexer, Tag};
+    use crate::span::SourceMap;
+
+    const EXPRESSIONS: &str = "
+        E -> E + T | T
+        T -> T * F | F
+        F -> ( E ) | id
+    ";
+
+    // пример 4.48: грамматика не SLR, но LR(1)
+    const ASSIGNMENTS: &str = "
+        S -> L = R | R
+        L -> * R | id
+        R -> L
+    ";
+
+    #[test]
+    fn closure_goto_test() {
+        let g = Grammar::parse(EXPRESSIONS).unwrap().augmented();
+        let start: ItemSet = vec![Item::new(0, 0)].into_iter().collect();
+        let i0 = closure(&g, &start);
+        assert_eq!(i0.len(), 7);
+        let i1 = goto(&g, &i0, &Symbol::NonTerminal("E".to_owned()));
+        let items: Vec<String> = i1.iter().map(|x| x.to_string(&g)).collect();
+        assert_eq!(items, vec!["E' -> E .", "E -> E . + T"]);
+        assert!(goto(&g, &i0, &Symbol::Terminal(")".to_owned())).is_empty());
+    }
+
+    #[test]
+    fn lr0_automaton_test() {
+        let automaton = Lr0Automaton::new(&Grammar::parse(EXPRESSIONS).unwrap());
+        assert_eq!(automaton.states().len(), 12);
+        let i1 = automaton.goto_state(0, &Symbol::NonTerminal("E".to_owned())).unwrap();
+        let i6 = automaton.goto_state(i1, &Symbol::Terminal("+".to_owned())).unwrap();
+        assert_eq!(automaton.states()[i6].len(), 5);
+        assert!(automaton.to_string().starts_with("I0:\n    E' -> . E\n"));
+    }
+
+    #[test]
+    fn slr_table_test() {
+        let table = LrTable::slr(&Grammar::parse(EXPRESSIONS).unwrap());
+        assert!(table.is_deterministic());
+        assert_eq!(table.action(0, "id").len(), 1);
+        assert_eq!(table.action(0, "+"), &[]);
+        let i1 = table.goto(0, "E").unwrap();
+        assert_eq!(table.action(i1, "$"), &[Action::Accept]);
+        assert_eq!(table.expected(i1), vec!["$".to_owned(), "+".to_owned()]);
+    }
+
+    #[test]
+    fn slr_shift_reduce_conflict_test() {
+        let table = LrTable::slr(&Grammar::parse(ASSIGNMENTS).unwrap());
+        let conflicts = table.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        let conflict = &conflicts[0];
+        assert_eq!(conflict.terminal, "=");
+        assert_eq!(conflict.kind, ConflictKind::ShiftReduce);
+        assert_eq!(conflict.items, vec!["S -> L . = R".to_owned(), "R -> L .".to_owned()]);
+        assert_eq!(conflict.to_string(),
+                   format!("State {}, '=': shift/reduce conflict ({}, r5)\n    S -> L . = R\n    R -> L .",
+                           conflict.state, conflict.actions[0]));
+        assert!(LrParser::new(table).is_err());
+    }
+
+    #[test]
+    fn slr_reduce_reduce_conflict_test() {
+        let table = LrTable::slr(&Grammar::parse("S -> A a | B a\nA -> c\nB -> c").unwrap());
+        let conflicts = table.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].kind, ConflictKind::ReduceReduce);
+        assert_eq!(conflicts[0].actions, vec![Action::Reduce(3), Action::Reduce(4)]);
+        assert_eq!(conflicts[0].items, vec!["A -> c .".to_owned(), "B -> c .".to_owned()]);
+    }
+
+    #[test]
+    fn slr_parse_test() {
+        let parser = LrParser::new(LrTable::slr(&Grammar::parse(EXPRESSIONS).unwrap())).unwrap();
+        let tree = parser.parse("a + b * c").unwrap();
+        assert_eq!(tree.to_string(), "E(E(T(F(a))) + T(T(F(b)) * F(c)))");
+        let tree = parser.parse("(a + b) * c").unwrap();
+        assert_eq!(tree.to_string(), "E(T(T(F(( E(E(T(F(a))) + T(F(b))) ))) * F(c)))");
+
+        let error = parser.parse("a + * c").err().unwrap();
+        assert_eq!(error.position, 2);
+        assert_eq!(error.expected, vec!["(".to_owned(), "id".to_owned()]);
+        let error = parser.parse("(a").err().unwrap();
+        assert_eq!(*error.found, Lexeme::end().with_span(SourceMap::new("(a").span(2, 2)));
+        assert_eq!(parser.parse("a + ?? b").err().unwrap().to_string(), "Invalid character '?' at 1:5");
+    }
+
+    #[test]
+    fn slr_parse_part2_tokens_test() {
+        let parser = LrParser::new(LrTable::slr(&Grammar::parse("
+            S -> id = E
+            E -> E + num | num
+        ").unwrap())).unwrap();
+        let input = "x = 1 + 22";
+        let mut lexer = Lexer::new();
+        let mut tokens = Vec::new();
+        loop {
+            let token = lexer.scan(input).unwrap();
+            let end = token.tag() == &Tag::EOF;
+            tokens.push(token);
+            if end {
+                break;
+            }
+        }
+        let tree = parser.parse_lexer_tokens(tokens).unwrap();
+        assert_eq!(tree.to_string(), "S(x = E(E(1) + 22))");
+    }
+}
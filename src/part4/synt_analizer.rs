@@ -0,0 +1,729 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::part2::lexer::{Lexer, Tag, Token};
+use crate::diagnostics::Diagnostic;
+use crate::lex_error::LexError;
+use crate::span::Span;
+
+/// Пустая цепочка в множествах FIRST
+pub const EPSILON: &str = "ε";
+/// Маркер конца входа
+pub const END_MARKER: &str = "$";
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Symbol {
+    Terminal(String),
+    NonTerminal(String),
+}
+
+impl Symbol {
+    pub fn name(&self) -> &str {
+        match self {
+            Symbol::Terminal(name) | Symbol::NonTerminal(name) => name,
+        }
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            Symbol::Terminal(_) => true,
+            Symbol::NonTerminal(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Production {
+    pub head: String,
+    pub body: Vec<Symbol>,
+}
+
+impl fmt::Display for Production {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ->", self.head)?;
+        if self.body.is_empty() {
+            return write!(f, " {}", EPSILON);
+        }
+        for symbol in &self.body {
+            write!(f, " {}", symbol.name())?;
+        }
+        Ok(())
+    }
+}
+
+/// FIRST или FOLLOW для каждого нетерминала
+pub type SymbolSets = BTreeMap<String, BTreeSet<String>>;
+
+///Контекстно-свободная грамматика
+///
+/// Задаётся текстом, по продукции (или группе альтернатив) на строку:
+///
+/// E  -> T E'
+/// E' -> + T E' | ε
+///    | - T E'
+///
+/// Нетерминалы - все символы, стоящие в левых частях, остальные - терминалы.
+/// Стартовый символ - голова первой продукции.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grammar {
+    start: String,
+    terminals: BTreeSet<String>,
+    nonterminals: BTreeSet<String>,
+    productions: Vec<Production>,
+}
+
+impl Grammar {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut rules: Vec<(String, Vec<String>)> = Vec::new();
+        let mut head: Option<String> = None;
+        for (number, line) in spec.lines().enumerate() {
+            let line = line.split("//").next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let alternatives = if let Some(rest) = line.strip_prefix('|') {
+                if head.is_none() {
+                    return Err(format!("Line {}: alternative without a head", number + 1));
+                }
+                rest
+            } else {
+                let mut parts = line.splitn(2, "->");
+                let left = parts.next().unwrap_or("").trim();
+                let right = match parts.next() {
+                    Some(right) => right,
+                    None => return Err(format!("Line {}: expected '->'", number + 1)),
+                };
+                if left.is_empty() || left.contains(char::is_whitespace) {
+                    return Err(format!("Line {}: invalid head '{}'", number + 1, left));
+                }
+                head = Some(left.to_owned());
+                right
+            };
+            let head = head.clone().unwrap_or_default();
+            for alternative in alternatives.split('|') {
+                let body = alternative.split_whitespace()
+                    .filter(|symbol| *symbol != EPSILON)
+                    .map(str::to_owned)
+                    .collect();
+                rules.push((head.clone(), body));
+            }
+        }
+        Grammar::from_rules(rules)
+    }
+
+    pub(crate) fn from_rules(rules: Vec<(String, Vec<String>)>) -> Result<Self, String> {
+        let start = match rules.first() {
+            Some((head, _)) => head.clone(),
+            None => return Err("Empty grammar".to_owned()),
+        };
+        let nonterminals: BTreeSet<String> = rules.iter().map(|(head, _)| head.clone()).collect();
+        let mut terminals = BTreeSet::new();
+        let productions = rules.into_iter().map(|(head, body)| {
+            let body = body.into_iter().map(|name| {
+                if nonterminals.contains(&name) {
+                    Symbol::NonTerminal(name)
+                } else {
+                    terminals.insert(name.clone());
+                    Symbol::Terminal(name)
+                }
+            }).collect();
+            Production { head, body }
+        }).collect();
+        Ok(Grammar { start, terminals, nonterminals, productions })
+    }
+
+    /// Та же грамматика с другим стартовым символом
+    pub(crate) fn with_start(mut self, start: &str) -> Result<Self, String> {
+        if !self.nonterminals.contains(start) {
+            return Err(format!("Unknown start symbol '{}'", start));
+        }
+        self.start = start.to_owned();
+        Ok(self)
+    }
+
+    pub fn start(&self) -> &str {
+        &self.start
+    }
+
+    pub fn terminals(&self) -> &BTreeSet<String> {
+        &self.terminals
+    }
+
+    pub fn nonterminals(&self) -> &BTreeSet<String> {
+        &self.nonterminals
+    }
+
+    pub fn productions(&self) -> &[Production] {
+        &self.productions
+    }
+
+    pub fn production(&self, index: usize) -> &Production {
+        &self.productions[index]
+    }
+
+    /// Пополненная грамматика: новый стартовый символ S' и продукция S' -> S под номером 0
+    pub fn augmented(&self) -> Grammar {
+        let mut start = format!("{}'", self.start);
+        while self.nonterminals.contains(&start) || self.terminals.contains(&start) {
+            start.push('\'');
+        }
+        let mut grammar = self.clone();
+        grammar.nonterminals.insert(start.clone());
+        grammar.productions.insert(0, Production {
+            head: start.clone(),
+            body: vec![Symbol::NonTerminal(self.start.clone())],
+        });
+        grammar.start = start;
+        grammar
+    }
+
+    pub fn first_sets(&self) -> SymbolSets {
+        let mut first: SymbolSets = self.nonterminals.iter()
+            .map(|name| (name.clone(), BTreeSet::new()))
+            .collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in &self.productions {
+                let addition = first_of_sequence(&first, &production.body);
+                changed |= extend(&mut first, &production.head, addition);
+            }
+        }
+        first
+    }
+
+    pub fn follow_sets(&self, first: &SymbolSets) -> SymbolSets {
+        let mut follow: SymbolSets = self.nonterminals.iter()
+            .map(|name| (name.clone(), BTreeSet::new()))
+            .collect();
+        extend(&mut follow, &self.start, vec![END_MARKER.to_owned()]);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in &self.productions {
+                for (i, symbol) in production.body.iter().enumerate() {
+                    if let Symbol::NonTerminal(name) = symbol {
+                        let rest = first_of_sequence(first, &production.body[i + 1..]);
+                        let mut addition: BTreeSet<String> = rest.iter()
+                            .filter(|x| *x != EPSILON)
+                            .cloned()
+                            .collect();
+                        if rest.contains(EPSILON) {
+                            addition.extend(follow[&production.head].iter().cloned());
+                        }
+                        changed |= extend(&mut follow, name, addition);
+                    }
+                }
+            }
+        }
+        follow
+    }
+}
+
+fn extend<I: IntoIterator<Item=String>>(sets: &mut SymbolSets, key: &str, values: I) -> bool {
+    let set = sets.entry(key.to_owned()).or_default();
+    let before = set.len();
+    set.extend(values);
+    set.len() != before
+}
+
+/// FIRST(X1 X2 ... Xn); для пустой или обнуляемой цепочки содержит ε
+pub fn first_of_sequence(first: &SymbolSets, sequence: &[Symbol]) -> BTreeSet<String> {
+    let mut result = BTreeSet::new();
+    for symbol in sequence {
+        match symbol {
+            Symbol::Terminal(name) => {
+                result.insert(name.clone());
+                return result;
+            }
+            Symbol::NonTerminal(name) => {
+                let set = &first[name];
+                result.extend(set.iter().filter(|x| *x != EPSILON).cloned());
+                if !set.contains(EPSILON) {
+                    return result;
+                }
+            }
+        }
+    }
+    result.insert(EPSILON.to_owned());
+    result
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ll1Conflict {
+    pub nonterminal: String,
+    pub terminal: String,
+    pub productions: Vec<Production>,
+}
+
+impl fmt::Display for Ll1Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "M[{}, {}]:", self.nonterminal, self.terminal)?;
+        for (i, production) in self.productions.iter().enumerate() {
+            write!(f, "{} {}", if i == 0 { "" } else { " |" }, production)?;
+        }
+        Ok(())
+    }
+}
+
+///Таблица предиктивного анализа M[A, a]
+///
+/// В ячейке хранятся номера продукций; больше одной продукции - конфликт.
+#[derive(Debug, Clone)]
+pub struct Ll1Table {
+    grammar: Grammar,
+    first: SymbolSets,
+    follow: SymbolSets,
+    entries: BTreeMap<(String, String), Vec<usize>>,
+}
+
+impl Ll1Table {
+    pub fn new(grammar: Grammar) -> Self {
+        let first = grammar.first_sets();
+        let follow = grammar.follow_sets(&first);
+        let mut entries: BTreeMap<(String, String), Vec<usize>> = BTreeMap::new();
+        for (index, production) in grammar.productions().iter().enumerate() {
+            let first_of_body = first_of_sequence(&first, &production.body);
+            let mut lookaheads: BTreeSet<&String> = first_of_body.iter()
+                .filter(|x| *x != EPSILON)
+                .collect();
+            if first_of_body.contains(EPSILON) {
+                lookaheads.extend(follow[&production.head].iter());
+            }
+            for terminal in lookaheads {
+                entries.entry((production.head.clone(), terminal.clone()))
+                    .or_default()
+                    .push(index);
+            }
+        }
+        Ll1Table { grammar, first, follow, entries }
+    }
+
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    pub fn first(&self) -> &SymbolSets {
+        &self.first
+    }
+
+    pub fn follow(&self) -> &SymbolSets {
+        &self.follow
+    }
+
+    pub fn entry(&self, nonterminal: &str, terminal: &str) -> &[usize] {
+        self.entries.get(&(nonterminal.to_owned(), terminal.to_owned()))
+            .map(|x| &x[..])
+            .unwrap_or(&[])
+    }
+
+    /// Терминалы, для которых у нетерминала есть продукция
+    pub fn expected(&self, nonterminal: &str) -> Vec<String> {
+        self.entries.keys()
+            .filter(|(head, _)| head == nonterminal)
+            .map(|(_, terminal)| terminal.clone())
+            .collect()
+    }
+
+    pub fn conflicts(&self) -> Vec<Ll1Conflict> {
+        self.entries.iter()
+            .filter(|(_, productions)| productions.len() > 1)
+            .map(|((nonterminal, terminal), productions)| Ll1Conflict {
+                nonterminal: nonterminal.clone(),
+                terminal: terminal.clone(),
+                productions: productions.iter().map(|i| self.grammar.production(*i).clone()).collect(),
+            })
+            .collect()
+    }
+
+    pub fn is_ll1(&self) -> bool {
+        self.entries.values().all(|productions| productions.len() == 1)
+    }
+}
+
+impl fmt::Display for Ll1Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for ((nonterminal, terminal), productions) in &self.entries {
+            write!(f, "M[{}, {}] =", nonterminal, terminal)?;
+            for (i, index) in productions.iter().enumerate() {
+                write!(f, "{} {}", if i == 0 { "" } else { " |" }, self.grammar.production(*index))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+///Токен в терминах грамматики: имя терминала и сама лексема
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lexeme {
+    pub terminal: String,
+    pub text: String,
+    pub span: Span,
+}
+
+impl Lexeme {
+    pub fn new(terminal: &str, text: &str) -> Self {
+        Lexeme { terminal: terminal.to_owned(), text: text.to_owned(), span: Span::default() }
+    }
+
+    pub fn with_span(self, span: Span) -> Self {
+        Lexeme { span, ..self }
+    }
+
+    pub fn end() -> Self {
+        Lexeme::new(END_MARKER, END_MARKER)
+    }
+
+    /// Числа становятся терминалом num, идентификаторы - id,
+    /// операторы - терминалом, совпадающим с самим оператором.
+    pub fn from_token(token: &dyn Token) -> Self {
+        let text = token.value();
+        let terminal = match token.tag() {
+            Tag::NUM => "num".to_owned(),
+            Tag::ID => "id".to_owned(),
+            Tag::TRUE => "true".to_owned(),
+            Tag::FALSE => "false".to_owned(),
+            Tag::OP => text.clone(),
+            Tag::EOF => return Lexeme::end().with_span(token.span()),
+        };
+        Lexeme { terminal, text, span: token.span() }
+    }
+}
+
+/// Прогоняет вход через лексер из part2; последний элемент всегда $.
+/// Ошибочный участок входа становится лексемой без имени терминала:
+/// анализатор остановится на нём с ParseError, а подробности - в списке ошибок
+pub fn tokenize(input: &str) -> (Vec<Lexeme>, Vec<LexError>) {
+    let mut lexer = Lexer::new();
+    let mut result = Vec::new();
+    let mut errors = Vec::new();
+    loop {
+        let lexeme = match lexer.scan(input) {
+            Ok(token) => Lexeme::from_token(token.as_ref()),
+            Err(error) => {
+                let lexeme = Lexeme::new("", error.span().text(input)).with_span(error.span());
+                errors.push(error);
+                lexeme
+            }
+        };
+        let is_end = lexeme.terminal == END_MARKER;
+        result.push(lexeme);
+        if is_end {
+            return (result, errors);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseTree {
+    Leaf(Lexeme),
+    Node { head: String, children: Vec<ParseTree> },
+}
+
+impl fmt::Display for ParseTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseTree::Leaf(lexeme) => write!(f, "{}", lexeme.text),
+            ParseTree::Node { head, children } => {
+                write!(f, "{}(", head)?;
+                if children.is_empty() {
+                    write!(f, "{}", EPSILON)?;
+                }
+                for (i, child) in children.iter().enumerate() {
+                    write!(f, "{}{}", if i == 0 { "" } else { " " }, child)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Номер токена во входе
+    pub position: usize,
+    pub found: Box<Lexeme>,
+    pub expected: Vec<String>,
+    /// Ошибка лексера, если анализатор остановился на ошибочном участке входа
+    pub lexical: Option<LexError>,
+}
+
+impl ParseError {
+    pub fn new(position: usize, found: &Lexeme, expected: Vec<String>) -> Self {
+        ParseError { position, found: Box::new(found.clone()), expected, lexical: None }
+    }
+
+    /// Находит среди ошибок tokenize ту, что дала лексему found
+    pub fn with_lexical(self, errors: &[LexError]) -> Self {
+        let lexical = errors.iter().find(|x| self.found.terminal.is_empty() && x.span() == self.found.span).cloned();
+        ParseError { lexical, ..self }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.lexical {
+            Some(error) => write!(f, "{}", error),
+            None => write!(f, "Unexpected '{}' at token {}, expected one of: {}",
+                           self.found.text, self.position, self.expected.join(", ")),
+        }
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        if let Some(lexical) = &error.lexical {
+            return Diagnostic::from(lexical);
+        }
+        let message = if error.found.terminal == END_MARKER {
+            "Unexpected end of input".to_owned()
+        } else {
+            format!("Unexpected '{}'", error.found.text)
+        };
+        Diagnostic::error(&message).with_label(error.found.span, &format!("expected one of: {}", error.expected.join(", ")))
+    }
+}
+
+///Нерекурсивный предиктивный анализатор (алгоритм 4.34)
+pub struct Ll1Parser {
+    table: Ll1Table,
+}
+
+struct Node {
+    symbol: Symbol,
+    lexeme: Option<Lexeme>,
+    children: Vec<usize>,
+}
+
+impl Ll1Parser {
+    pub fn new(grammar: Grammar) -> Result<Self, Vec<Ll1Conflict>> {
+        let table = Ll1Table::new(grammar);
+        if table.is_ll1() {
+            Ok(Ll1Parser { table })
+        } else {
+            Err(table.conflicts())
+        }
+    }
+
+    pub fn table(&self) -> &Ll1Table {
+        &self.table
+    }
+
+    pub fn parse(&self, input: &str) -> Result<ParseTree, ParseError> {
+        let (lexemes, errors) = tokenize(input);
+        self.parse_tokens(lexemes).map_err(|error| error.with_lexical(&errors))
+    }
+
+    /// Принимает токены лексера из part2 напрямую
+    pub fn parse_lexer_tokens<I: IntoIterator<Item=Box<dyn Token>>>(&self, tokens: I) -> Result<ParseTree, ParseError> {
+        self.parse_tokens(tokens.into_iter().map(|token| Lexeme::from_token(token.as_ref())))
+    }
+
+    pub fn parse_tokens<I: IntoIterator<Item=Lexeme>>(&self, tokens: I) -> Result<ParseTree, ParseError> {
+        let mut tokens: Vec<Lexeme> = tokens.into_iter().collect();
+        if tokens.last().map(|x| x.terminal != END_MARKER).unwrap_or(true) {
+            tokens.push(Lexeme::end());
+        }
+        let start = Symbol::NonTerminal(self.table.grammar().start().to_owned());
+        let mut nodes = vec![Node { symbol: start, lexeme: None, children: Vec::new() }];
+        let mut stack = vec![0];
+        let mut position = 0;
+
+        while let Some(top) = stack.pop() {
+            let lookahead = &tokens[position];
+            match nodes[top].symbol.clone() {
+                Symbol::Terminal(terminal) => {
+                    if terminal != lookahead.terminal || terminal == END_MARKER {
+                        return Err(ParseError::new(position, lookahead, vec![terminal]));
+                    }
+                    nodes[top].lexeme = Some(lookahead.clone());
+                    position += 1;
+                }
+                Symbol::NonTerminal(nonterminal) => {
+                    let index = match self.table.entry(&nonterminal, &lookahead.terminal).first() {
+                        Some(index) => *index,
+                        None => return Err(ParseError::new(position, lookahead, self.table.expected(&nonterminal))),
+                    };
+                    let mut children = Vec::new();
+                    for symbol in &self.table.grammar().production(index).body {
+                        nodes.push(Node { symbol: symbol.clone(), lexeme: None, children: Vec::new() });
+                        children.push(nodes.len() - 1);
+                    }
+                    stack.extend(children.iter().rev());
+                    nodes[top].children = children;
+                }
+            }
+        }
+
+        if tokens[position].terminal != END_MARKER {
+            return Err(ParseError::new(position, &tokens[position], vec![END_MARKER.to_owned()]));
+        }
+        Ok(build_tree(&mut nodes, 0))
+    }
+}
+
+fn build_tree(nodes: &mut Vec<Node>, index: usize) -> ParseTree {
+    match nodes[index].lexeme.take() {
+        Some(lexeme) => ParseTree::Leaf(lexeme),
+        None => {
+            let children = std::mem::take(&mut nodes[index].children);
+            ParseTree::Node {
+                head: nodes[index].symbol.name().to_owned(),
+                children: children.into_iter().map(|child| build_tree(nodes, child)).collect(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::SourceMap;
+
+    const EXPRESSIONS: &str = "
+        E  -> T E'
+        E' -> + T E' | ε
+        T  -> F T'
+        T' -> * F T' | ε
+        F  -> ( E ) | id
+    ";
+
+    fn set(items: &[&str]) -> BTreeSet<String> {
+        items.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn grammar_parse_test() {
+        let g = Grammar::parse(EXPRESSIONS).unwrap();
+        assert_eq!(g.start(), "E");
+        assert_eq!(g.nonterminals(), &set(&["E", "E'", "T", "T'", "F"]));
+        assert_eq!(g.terminals(), &set(&["+", "*", "(", ")", "id"]));
+        assert_eq!(g.productions().len(), 8);
+        assert_eq!(g.production(2).to_string(), "E' -> ε");
+        assert_eq!(g.production(6).to_string(), "F -> ( E )");
+
+        let g = Grammar::parse("S -> a S\n   | b").unwrap();
+        assert_eq!(g.production(1).to_string(), "S -> b");
+
+        assert_eq!(Grammar::parse(""), Err("Empty grammar".to_owned()));
+        assert_eq!(Grammar::parse("S a"), Err("Line 1: expected '->'".to_owned()));
+        assert_eq!(Grammar::parse("| a"), Err("Line 1: alternative without a head".to_owned()));
+    }
+
+    #[test]
+    fn augmented_test() {
+        let g = Grammar::parse("S -> S' a | b\nS' -> c").unwrap().augmented();
+        assert_eq!(g.start(), "S''");
+        assert_eq!(g.production(0).to_string(), "S'' -> S");
+        assert_eq!(g.productions().len(), 4);
+        assert!(g.nonterminals().contains("S''"));
+    }
+
+    #[test]
+    fn first_follow_test() {
+        let g = Grammar::parse(EXPRESSIONS).unwrap();
+        let first = g.first_sets();
+        assert_eq!(first["E"], set(&["(", "id"]));
+        assert_eq!(first["E'"], set(&["+", EPSILON]));
+        assert_eq!(first["T'"], set(&["*", EPSILON]));
+        let follow = g.follow_sets(&first);
+        assert_eq!(follow["E"], set(&[")", END_MARKER]));
+        assert_eq!(follow["E'"], set(&[")", END_MARKER]));
+        assert_eq!(follow["T"], set(&["+", ")", END_MARKER]));
+        assert_eq!(follow["T'"], set(&["+", ")", END_MARKER]));
+        assert_eq!(follow["F"], set(&["+", "*", ")", END_MARKER]));
+    }
+
+    #[test]
+    fn ll1_table_test() {
+        let table = Ll1Table::new(Grammar::parse(EXPRESSIONS).unwrap());
+        assert!(table.is_ll1());
+        assert_eq!(table.entry("E", "id"), &[0]);
+        assert_eq!(table.entry("E'", ")"), &[2]);
+        assert_eq!(table.entry("T'", "+"), &[5]);
+        assert_eq!(table.entry("F", "+"), &[] as &[usize]);
+        assert_eq!(table.expected("F"), vec!["(".to_owned(), "id".to_owned()]);
+    }
+
+    #[test]
+    fn ll1_conflicts_test() {
+        let table = Ll1Table::new(Grammar::parse("E -> E + T | T\nT -> id").unwrap());
+        assert!(!table.is_ll1());
+        let conflicts = table.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].to_string(), "M[E, id]: E -> E + T | E -> T");
+
+        // висячий else, пример 4.33
+        let dangling_else = "
+            S  -> i E t S S' | a
+            S' -> e S | ε
+            E  -> b
+        ";
+        let conflicts = Ll1Parser::new(Grammar::parse(dangling_else).unwrap()).err().unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].nonterminal, "S'");
+        assert_eq!(conflicts[0].terminal, "e");
+    }
+
+    #[test]
+    fn tokenize_test() {
+        let (tokens, errors) = tokenize("count + 12*(x)");
+        let terminals: Vec<&str> = tokens.iter().map(|x| &x.terminal[..]).collect();
+        assert_eq!(terminals, vec!["id", "+", "num", "*", "(", "id", ")", "$"]);
+        assert_eq!(tokens[2].text, "12");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn tokenize_errors_test() {
+        let (tokens, errors) = tokenize("a + ?? b");
+        let terminals: Vec<&str> = tokens.iter().map(|x| &x.terminal[..]).collect();
+        assert_eq!(terminals, vec!["id", "+", "", "id", "$"]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].to_string(), "Invalid character '?' at 1:5");
+
+        let parser = Ll1Parser::new(Grammar::parse(EXPRESSIONS).unwrap()).unwrap();
+        let error = parser.parse("a + ?? b").err().unwrap();
+        assert_eq!(error.position, 2);
+        assert_eq!(error.found.text, "??");
+        assert_eq!(error.found.span.start.column, 5);
+        // вместо "Unexpected '??'" - ошибка лексера
+        assert_eq!(error.lexical, Some(errors[0].clone()));
+        assert_eq!(error.to_string(), "Invalid character '?' at 1:5");
+        assert!(Diagnostic::from(&error).render(&SourceMap::new("a + ?? b")).starts_with("error: Invalid character '?'"));
+        // синтаксическая ошибка раньше лексической остаётся синтаксической
+        let error = parser.parse("a + * ??").err().unwrap();
+        assert_eq!((error.found.text.as_str(), error.lexical), ("*", None));
+    }
+
+    #[test]
+    fn ll1_parse_test() {
+        let parser = Ll1Parser::new(Grammar::parse(EXPRESSIONS).unwrap()).unwrap();
+        let tree = parser.parse("a + b * c").unwrap();
+        assert_eq!(tree.to_string(),
+                   "E(T(F(a) T'(ε)) E'(+ T(F(b) T'(* F(c) T'(ε))) E'(ε)))");
+        let tree = parser.parse("(a)").unwrap();
+        assert_eq!(tree.to_string(), "E(T(F(( E(T(F(a) T'(ε)) E'(ε)) )) T'(ε)) E'(ε))");
+    }
+
+    #[test]
+    fn ll1_parse_error_test() {
+        let parser = Ll1Parser::new(Grammar::parse(EXPRESSIONS).unwrap()).unwrap();
+        let error = parser.parse("a + * b").err().unwrap();
+        assert_eq!(error.position, 2);
+        assert_eq!(*error.found, Lexeme::new("*", "*").with_span(SourceMap::new("a + * b").span(4, 5)));
+        assert_eq!(error.expected, vec!["(".to_owned(), "id".to_owned()]);
+        assert_eq!(error.to_string(), "Unexpected '*' at token 2, expected one of: (, id");
+        assert_eq!(Diagnostic::from(&error).render(&SourceMap::new("a + * b")),
+                   "error: Unexpected '*'\n --> 1:5\n  |\n1 | a + * b\n  |     ^ expected one of: (, id\n");
+
+        let error = parser.parse("(a").err().unwrap();
+        assert_eq!(error.expected, vec![")".to_owned()]);
+        assert_eq!(*error.found, Lexeme::end().with_span(SourceMap::new("(a").span(2, 2)));
+
+        let error = parser.parse_tokens(vec![Lexeme::new("id", "a"), Lexeme::new("id", "b")]).err().unwrap();
+        assert_eq!(error.position, 1);
+        assert_eq!(error.expected, vec!["$".to_owned(), ")".to_owned(), "*".to_owned(), "+".to_owned()]);
+    }
+}
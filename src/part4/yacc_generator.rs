@@ -0,0 +1,564 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::lr_analizer::{Action, ConflictKind, LrConflict, LrParser, LrTable};
+use super::synt_analizer::{tokenize, Grammar, Lexeme, ParseError, ParseTree, Production, Symbol};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    Left,
+    Right,
+    NonAssoc,
+}
+
+///Правило спецификации: продукция, %prec и семантическое действие
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct YaccRule {
+    pub production: Production,
+    pub prec: Option<String>,
+    /// Шаблон значения левой части: $1, $2... заменяются значениями символов тела
+    pub action: Option<String>,
+    pub line: usize,
+}
+
+///Спецификация в духе Yacc
+///
+/// %token num id
+/// %left '+' '-'
+/// %left '*'
+/// %right UMINUS
+/// %start expr
+/// %%
+/// expr : expr '+' expr          { $1 $3 + }
+///      | '-' expr %prec UMINUS  { $2 neg }
+///      | '(' expr ')'           { $2 }
+///      | num
+///      ;
+/// %%
+///
+/// Терминалы - объявленные в %token, %left, %right, %nonassoc имена и литералы в кавычках.
+/// Каждая следующая строка приоритетов связывает сильнее предыдущей.
+/// Действие по умолчанию - $1. Всё после второго %% игнорируется.
+#[derive(Debug, Clone)]
+pub struct YaccSpec {
+    grammar: Grammar,
+    tokens: BTreeSet<String>,
+    precedence: BTreeMap<String, (usize, Associativity)>,
+    rules: Vec<YaccRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Name(String),
+    Literal(String),
+    Colon,
+    Bar,
+    Semicolon,
+    Prec,
+    Action(String),
+}
+
+impl YaccSpec {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut tokens = BTreeSet::new();
+        let mut precedence = BTreeMap::new();
+        let mut start = None;
+        let mut lines = spec.lines().enumerate();
+        for (number, line) in lines.by_ref() {
+            let line = line.split("//").next().unwrap_or("").trim();
+            if line == "%%" {
+                break;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap_or("");
+            let names: Vec<String> = words.map(unquote).collect();
+            let associativity = match keyword {
+                "%token" => {
+                    tokens.extend(names);
+                    continue;
+                }
+                "%start" if names.len() == 1 => {
+                    start = Some(names[0].clone());
+                    continue;
+                }
+                "%left" => Associativity::Left,
+                "%right" => Associativity::Right,
+                "%nonassoc" => Associativity::NonAssoc,
+                _ => return Err(format!("Line {}: unknown declaration '{}'", number + 1, line)),
+            };
+            let level = precedence.values().map(|(level, _)| *level).max().unwrap_or(0) + 1;
+            for name in names {
+                tokens.insert(name.clone());
+                precedence.insert(name, (level, associativity));
+            }
+        }
+
+        let mut rules_text = Vec::new();
+        let first_line = lines.clone().next().map(|(number, _)| number + 1).unwrap_or(0);
+        for (_, line) in lines {
+            if line.trim() == "%%" {
+                break;
+            }
+            rules_text.push(line);
+        }
+        let pieces = split_pieces(&rules_text.join("\n"), first_line)?;
+        let rules = parse_rules(&pieces, &tokens, &precedence)?;
+
+        let grammar = Grammar::from_rules(rules.iter()
+            .map(|rule| (rule.production.head.clone(), rule.production.body.iter().map(|x| x.name().to_owned()).collect()))
+            .collect())?;
+        let grammar = match start {
+            Some(start) => grammar.with_start(&start)?,
+            None => grammar,
+        };
+        Ok(YaccSpec { grammar, tokens, precedence, rules })
+    }
+
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    pub fn tokens(&self) -> &BTreeSet<String> {
+        &self.tokens
+    }
+
+    pub fn rules(&self) -> &[YaccRule] {
+        &self.rules
+    }
+
+    /// Уровень приоритета и ассоциативность терминала
+    pub fn precedence(&self, terminal: &str) -> Option<(usize, Associativity)> {
+        self.precedence.get(terminal).cloned()
+    }
+
+    /// Приоритет правила: из %prec, иначе от последнего терминала тела
+    pub fn rule_precedence(&self, rule: usize) -> Option<(usize, Associativity)> {
+        let rule = &self.rules[rule];
+        let terminal = rule.prec.as_ref().map(|x| &x[..])
+            .or_else(|| rule.production.body.iter().rev().find(|x| x.is_terminal()).map(|x| x.name()))?;
+        self.precedence(terminal)
+    }
+
+    /// LALR(1)-таблица, конфликты которой разрешены по правилам yacc
+    pub fn generate(&self) -> YaccParser {
+        let mut table = LrTable::lalr(&self.grammar);
+        let mut conflicts = Vec::new();
+        table.resolve(|conflict| {
+            let (action, resolved) = self.resolve(conflict);
+            if !resolved {
+                conflicts.push(conflict.clone());
+            }
+            action
+        });
+        let index = self.rules.iter().enumerate()
+            .map(|(i, rule)| ((rule.production.head.clone(), rule.production.body.iter().map(|x| x.name().to_owned()).collect()), i))
+            .collect();
+        let parser = LrParser::new(table).unwrap_or_else(|_| unreachable!("all conflicts are resolved"));
+        YaccParser { parser, actions: self.rules.iter().map(|x| x.action.clone()).collect(), index, conflicts }
+    }
+
+    // свёртка/свёртка - по более ранней продукции, сдвиг/свёртка - по приоритетам,
+    // а без них в пользу сдвига; второе значение - разрешён ли конфликт объявлениями
+    fn resolve(&self, conflict: &LrConflict) -> (Option<Action>, bool) {
+        let shift = conflict.actions.iter().find(|x| matches!(x, Action::Shift(_))).cloned();
+        let reduce = conflict.actions.iter().find_map(|x| match x {
+            Action::Reduce(production) => Some(*production),
+            _ => None,
+        });
+        let (shift, reduce) = match (shift, reduce) {
+            (Some(shift), Some(reduce)) if conflict.kind == ConflictKind::ShiftReduce => (shift, reduce),
+            _ => return (conflict.actions.first().cloned(), false),
+        };
+        let reduce_reduce = conflict.actions.len() > 2;
+        // продукция 0 пополненной грамматики - S' -> S
+        match (self.precedence(&conflict.terminal), self.rule_precedence(reduce - 1)) {
+            (Some((terminal, associativity)), Some((rule, _))) => {
+                let action = if rule > terminal {
+                    Some(Action::Reduce(reduce))
+                } else if rule < terminal {
+                    Some(shift)
+                } else {
+                    match associativity {
+                        Associativity::Left => Some(Action::Reduce(reduce)),
+                        Associativity::Right => Some(shift),
+                        Associativity::NonAssoc => None,
+                    }
+                };
+                (action, !reduce_reduce)
+            }
+            _ => (Some(shift), false),
+        }
+    }
+}
+
+///Сгенерированный транслятор: LR-анализатор и семантические действия
+pub struct YaccParser {
+    parser: LrParser,
+    actions: Vec<Option<String>>,
+    index: BTreeMap<(String, Vec<String>), usize>,
+    conflicts: Vec<LrConflict>,
+}
+
+impl YaccParser {
+    pub fn table(&self) -> &LrTable {
+        self.parser.table()
+    }
+
+    /// Конфликты, не разрешённые приоритетами (разрешены по умолчанию, как в yacc)
+    pub fn conflicts(&self) -> &[LrConflict] {
+        &self.conflicts
+    }
+
+    pub fn parse(&self, input: &str) -> Result<ParseTree, ParseError> {
+        self.parser.parse(input)
+    }
+
+    /// Значение стартового символа для входа, разобранного лексером из part2
+    pub fn translate(&self, input: &str) -> Result<String, ParseError> {
+        let (lexemes, errors) = tokenize(input);
+        self.translate_lexemes(lexemes).map_err(|error| error.with_lexical(&errors))
+    }
+
+    pub fn translate_lexemes<I: IntoIterator<Item=Lexeme>>(&self, tokens: I) -> Result<String, ParseError> {
+        Ok(self.value(&self.parser.parse_tokens(tokens)?))
+    }
+
+    fn value(&self, tree: &ParseTree) -> String {
+        match tree {
+            ParseTree::Leaf(lexeme) => lexeme.text.clone(),
+            ParseTree::Node { head, children } => {
+                let body = children.iter().map(|child| match child {
+                    ParseTree::Leaf(lexeme) => lexeme.terminal.clone(),
+                    ParseTree::Node { head, .. } => head.clone(),
+                }).collect();
+                let values: Vec<String> = children.iter().map(|child| self.value(child)).collect();
+                match &self.actions[self.index[&(head.clone(), body)]] {
+                    Some(action) => substitute(action, &values),
+                    None => values.into_iter().next().unwrap_or_default(),
+                }
+            }
+        }
+    }
+}
+
+fn unquote(name: &str) -> String {
+    if name.len() > 2 && name.starts_with('\'') && name.ends_with('\'') {
+        name[1..name.len() - 1].to_owned()
+    } else {
+        name.to_owned()
+    }
+}
+
+fn substitute(action: &str, values: &[String]) -> String {
+    let mut result = String::new();
+    let mut chars = action.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '$' && chars.peek().map(|x| x.is_ascii_digit()).unwrap_or(false) {
+            let mut number = 0;
+            while let Some(digit) = chars.peek().and_then(|x| x.to_digit(10)) {
+                number = number * 10 + digit as usize;
+                chars.next();
+            }
+            result += &values[number - 1];
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+// номера $n в действии
+fn references(action: &str) -> Vec<usize> {
+    let mut result = Vec::new();
+    let mut chars = action.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '$' {
+            let digits: String = std::iter::from_fn(|| chars.next_if(|x| x.is_ascii_digit())).collect();
+            result.push(digits.parse().unwrap_or(0));
+        }
+    }
+    result
+}
+
+fn split_pieces(text: &str, first_line: usize) -> Result<Vec<(Piece, usize)>, String> {
+    let mut result = Vec::new();
+    let mut line = first_line;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            _ if c.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.next_if(|x| *x != '\n').is_some() {}
+            }
+            ':' => result.push((Piece::Colon, line)),
+            '|' => result.push((Piece::Bar, line)),
+            ';' => result.push((Piece::Semicolon, line)),
+            '\'' => {
+                let literal: String = std::iter::from_fn(|| chars.next_if(|x| *x != '\'' && *x != '\n')).collect();
+                if chars.next() != Some('\'') || literal.is_empty() {
+                    return Err(format!("Line {}: unclosed literal", line));
+                }
+                result.push((Piece::Literal(literal), line));
+            }
+            '{' => {
+                let start = line;
+                let mut depth = 1;
+                let mut action = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') if depth == 1 => break,
+                        Some(c) => {
+                            match c {
+                                '{' => depth += 1,
+                                '}' => depth -= 1,
+                                '\n' => line += 1,
+                                _ => {}
+                            }
+                            action.push(c);
+                        }
+                        None => return Err(format!("Line {}: unclosed action", start)),
+                    }
+                }
+                result.push((Piece::Action(action), start));
+            }
+            '%' => {
+                let word: String = std::iter::from_fn(|| chars.next_if(|x| x.is_alphanumeric())).collect();
+                if word != "prec" {
+                    return Err(format!("Line {}: unknown directive '%{}'", line, word));
+                }
+                result.push((Piece::Prec, line));
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                let mut name = c.to_string();
+                name.extend(std::iter::from_fn(|| chars.next_if(|x| x.is_alphanumeric() || *x == '_' || *x == '\'')));
+                result.push((Piece::Name(name), line));
+            }
+            _ => return Err(format!("Line {}: unexpected '{}'", line, c)),
+        }
+    }
+    Ok(result)
+}
+
+fn parse_rules(pieces: &[(Piece, usize)],
+               tokens: &BTreeSet<String>,
+               precedence: &BTreeMap<String, (usize, Associativity)>) -> Result<Vec<YaccRule>, String> {
+    let heads: BTreeSet<&str> = pieces.windows(2)
+        .filter_map(|pair| match pair {
+            [(Piece::Name(name), _), (Piece::Colon, _)] => Some(&name[..]),
+            _ => None,
+        })
+        .collect();
+    if let Some(head) = heads.iter().find(|head| tokens.contains(**head)) {
+        return Err(format!("Token '{}' used as a rule head", head));
+    }
+    let mut rules = Vec::new();
+    let mut position = 0;
+    while position < pieces.len() {
+        let (head, line) = match (&pieces[position], pieces.get(position + 1)) {
+            ((Piece::Name(head), line), Some((Piece::Colon, _))) => (head.clone(), *line),
+            ((_, line), _) => return Err(format!("Line {}: expected rule head", line)),
+        };
+        position += 2;
+        loop {
+            let mut rule = YaccRule {
+                production: Production { head: head.clone(), body: Vec::new() },
+                prec: None,
+                action: None,
+                line: pieces.get(position).map(|(_, line)| *line).unwrap_or(line),
+            };
+            loop {
+                match pieces.get(position) {
+                    Some((Piece::Name(name), line)) => {
+                        if let Some((Piece::Colon, _)) = pieces.get(position + 1) {
+                            break;
+                        }
+                        if rule.action.is_some() {
+                            return Err(format!("Line {}: action must end the alternative", line));
+                        }
+                        let symbol = if heads.contains(&name[..]) {
+                            Symbol::NonTerminal(name.clone())
+                        } else if tokens.contains(name) {
+                            Symbol::Terminal(name.clone())
+                        } else {
+                            return Err(format!("Line {}: undeclared symbol '{}'", line, name));
+                        };
+                        rule.production.body.push(symbol);
+                    }
+                    Some((Piece::Literal(literal), line)) => {
+                        if rule.action.is_some() {
+                            return Err(format!("Line {}: action must end the alternative", line));
+                        }
+                        rule.production.body.push(Symbol::Terminal(literal.clone()));
+                    }
+                    Some((Piece::Prec, line)) => {
+                        let name = match pieces.get(position + 1) {
+                            Some((Piece::Name(name), _)) | Some((Piece::Literal(name), _)) => name.clone(),
+                            _ => return Err(format!("Line {}: expected symbol after %prec", line)),
+                        };
+                        if !precedence.contains_key(&name) {
+                            return Err(format!("Line {}: %prec symbol '{}' has no precedence", line, name));
+                        }
+                        rule.prec = Some(name);
+                        position += 1;
+                    }
+                    Some((Piece::Action(action), line)) => {
+                        let length = rule.production.body.len();
+                        if let Some(n) = references(action).into_iter().find(|n| *n == 0 || *n > length) {
+                            return Err(format!("Line {}: ${} out of range in action", line, n));
+                        }
+                        rule.action = Some(action.trim().to_owned());
+                    }
+                    _ => break,
+                }
+                position += 1;
+            }
+            rules.push(rule);
+            match pieces.get(position) {
+                Some((Piece::Bar, _)) => position += 1,
+                Some((Piece::Semicolon, _)) => {
+                    position += 1;
+                    break;
+                }
+                Some((Piece::Colon, line)) => return Err(format!("Line {}: unexpected ':'", line)),
+                _ => break,
+            }
+        }
+    }
+    if rules.is_empty() {
+        return Err("No rules".to_owned());
+    }
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPRESSIONS: &str = "
+// арифметика с приоритетами вместо E, T, F
+%token num id
+%left '+' '-'
+%left '*'
+%right UMINUS
+%%
+expr : expr '+' expr          { $1 $3 + }
+     | expr '-' expr          { $1 $3 - }
+     | expr '*' expr          { $1 $3 * }
+     | '-' expr %prec UMINUS  { $2 neg }
+     | '(' expr ')'           { $2 }
+     | num
+     | id
+     ;
+%%
+";
+
+    #[test]
+    fn parse_spec_test() {
+        let spec = YaccSpec::parse(EXPRESSIONS).unwrap();
+        assert_eq!(spec.rules().len(), 7);
+        assert_eq!(spec.grammar().start(), "expr");
+        assert_eq!(spec.rules()[0].production.to_string(), "expr -> expr + expr");
+        assert_eq!(spec.rules()[0].action, Some("$1 $3 +".to_owned()));
+        assert_eq!(spec.rules()[3].prec, Some("UMINUS".to_owned()));
+        assert_eq!(spec.rules()[5].action, None);
+        assert_eq!(spec.rules()[6].line, 14);
+        assert_eq!(spec.rules()[4].production.body[0], Symbol::Terminal("(".to_owned()));
+        assert_eq!(spec.precedence("-"), Some((1, Associativity::Left)));
+        assert_eq!(spec.precedence("UMINUS"), Some((3, Associativity::Right)));
+        assert_eq!(spec.precedence("num"), None);
+        assert_eq!(spec.rule_precedence(2), Some((2, Associativity::Left)));
+        assert_eq!(spec.rule_precedence(3), Some((3, Associativity::Right)));
+        assert_eq!(spec.rule_precedence(5), None);
+    }
+
+    #[test]
+    fn parse_spec_error_test() {
+        assert_eq!(YaccSpec::parse("%token a\n%%\n").err(), Some("No rules".to_owned()));
+        assert_eq!(YaccSpec::parse("%tokens a\n%%\ns : a ;").err(), Some("Line 1: unknown declaration '%tokens a'".to_owned()));
+        assert_eq!(YaccSpec::parse("%token a\n%%\ns : a b ;").err(), Some("Line 3: undeclared symbol 'b'".to_owned()));
+        assert_eq!(YaccSpec::parse("%token a\n%%\ns : a { $2 } ;").err(), Some("Line 3: $2 out of range in action".to_owned()));
+        assert_eq!(YaccSpec::parse("%token a\n%%\ns : a %prec a ;").err(), Some("Line 3: %prec symbol 'a' has no precedence".to_owned()));
+        assert_eq!(YaccSpec::parse("%token a\n%%\ns : a\n  { $1 ").err(), Some("Line 4: unclosed action".to_owned()));
+        assert_eq!(YaccSpec::parse("%token a\n%%\na : a ;").err(), Some("Token 'a' used as a rule head".to_owned()));
+        assert_eq!(YaccSpec::parse("%token a\n%start t\n%%\ns : a ;").err(), Some("Unknown start symbol 't'".to_owned()));
+    }
+
+    #[test]
+    fn precedence_test() {
+        let parser = YaccSpec::parse(EXPRESSIONS).unwrap().generate();
+        assert!(parser.conflicts().is_empty());
+        assert_eq!(parser.translate("1+2*3-4"), Ok("1 2 3 * + 4 -".to_owned()));
+        assert_eq!(parser.translate("1-2-3"), Ok("1 2 - 3 -".to_owned()));
+        assert_eq!(parser.translate("-a*(b+2)"), Ok("a neg b 2 + *".to_owned()));
+        assert_eq!(parser.translate("--a-b"), Ok("a neg neg b -".to_owned()));
+        assert!(parser.translate("1+*2").is_err());
+    }
+
+    #[test]
+    fn associativity_test() {
+        let spec = "%token id\n%right '='\n%nonassoc '<'\n%%\ns : s '=' s { $1 $3 = } | s '<' s { $1 $3 < } | id ;";
+        let parser = YaccSpec::parse(spec).unwrap().generate();
+        assert!(parser.conflicts().is_empty());
+        assert_eq!(parser.translate("a = b = c"), Ok("a b c = =".to_owned()));
+        assert_eq!(parser.translate("a = b < c"), Ok("a b c < =".to_owned()));
+        let error = parser.translate("a < b < c").unwrap_err();
+        assert_eq!(error.position, 3);
+        assert_eq!(error.found.text, "<");
+    }
+
+    #[test]
+    fn unresolved_conflicts_test() {
+        // без объявлений приоритетов yacc предпочитает сдвиг
+        let parser = YaccSpec::parse("%token num\n%%\ne : e '+' e { $1 $3 + } | num ;").unwrap().generate();
+        assert_eq!(parser.conflicts().len(), 1);
+        assert_eq!(parser.conflicts()[0].kind, ConflictKind::ShiftReduce);
+        assert_eq!(parser.conflicts()[0].terminal, "+");
+        assert_eq!(parser.translate("1+2+3"), Ok("1 2 3 + +".to_owned()));
+
+        // свёртка/свёртка - в пользу продукции, стоящей в спецификации раньше
+        let parser = YaccSpec::parse("%token id\n%%\ns : a { A } | b { B } ;\na : id ;\nb : id ;").unwrap().generate();
+        assert_eq!(parser.conflicts().len(), 1);
+        assert_eq!(parser.conflicts()[0].kind, ConflictKind::ReduceReduce);
+        assert_eq!(parser.translate("x"), Ok("A".to_owned()));
+    }
+
+    #[test]
+    fn start_and_epsilon_test() {
+        let spec = "%token id\n%start list\n%%\nitem : id { [$1] } ;\nlist : list item { $1$2 } | { } ;";
+        let parser = YaccSpec::parse(spec).unwrap().generate();
+        assert_eq!(parser.translate("a b c"), Ok("[a][b][c]".to_owned()));
+        assert_eq!(parser.translate(""), Ok("".to_owned()));
+    }
+
+    #[test]
+    fn yacc_translator_test() {
+        use crate::part2::simple_translator::SimpleTranslator;
+        // та же схема трансляции, записанная декларативно
+        let spec = "
+            %token num id
+            %%
+            expr   : expr '+' term    {$1 $3 +}
+                   | expr '-' term    {$1 $3 -}
+                   | term
+                   ;
+            term   : term '*' factor  {$1 $3 *}
+                   | factor
+                   ;
+            factor : '-' factor       {$2 neg}
+                   | '(' expr ')'     {$2}
+                   | num
+                   | id
+                   ;
+        ";
+        let parser = YaccSpec::parse(spec).unwrap().generate();
+        assert!(parser.conflicts().is_empty());
+        for input in &["2+3-4", "2+3-4-9", "7", "12 * (x - -4) - y * z"] {
+            assert_eq!(parser.translate(input).ok(), SimpleTranslator::from_string(input).expr().ok());
+        }
+        assert!(parser.translate("2+*3").is_err());
+        assert_eq!(parser.translate("2+3?").unwrap_err().to_string(), "Invalid character '?' at 1:4");
+    }
+}
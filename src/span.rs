@@ -0,0 +1,179 @@
+use std::fmt;
+
+///Позиция в исходном тексте: строка и столбец с единицы, смещение в байтах с нуля
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub line: usize,
+    /// Номер символа (не байта) в строке
+    pub column: usize,
+    pub offset: usize,
+}
+
+impl Position {
+    pub fn new(line: usize, column: usize, offset: usize) -> Self {
+        Position { line, column, offset }
+    }
+
+    /// Позиция сразу за символом c
+    pub fn advance(&mut self, c: char) {
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position::new(1, 1, 0)
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+///Участок исходного текста; end указывает на позицию сразу за последним символом
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Span { start, end }
+    }
+
+    /// Пустой участок в точке position
+    pub fn point(position: Position) -> Self {
+        Span::new(position, position)
+    }
+
+    /// Длина в байтах
+    pub fn len(&self) -> usize {
+        self.end.offset - self.start.offset
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Наименьший участок, покрывающий оба
+    pub fn merge(&self, other: &Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start.offset..self.end.offset]
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+///Исходный текст с таблицей начал строк: смещение -> строка и столбец
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMap {
+    source: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceMap {
+    pub fn new(source: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        SourceMap { source: source.to_owned(), line_starts }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn lines_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Смещения за концом текста прижимаются к концу
+    pub fn position(&self, offset: usize) -> Position {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        let column = self.source[self.line_starts[line]..offset].chars().count() + 1;
+        Position::new(line + 1, column, offset)
+    }
+
+    pub fn span(&self, start: usize, end: usize) -> Span {
+        Span::new(self.position(start), self.position(end))
+    }
+
+    /// Текст строки без перевода строки; строки нумеруются с единицы
+    pub fn line(&self, line: usize) -> Option<&str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self.line_starts.get(line).map(|x| x - 1).unwrap_or(self.source.len());
+        Some(self.source[start..end].trim_end_matches('\r'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn position_test() {
+        let map = SourceMap::new("a = 3\nпривет = 23\n\nc");
+        assert_eq!(map.lines_count(), 4);
+        assert_eq!(map.position(0), Position::new(1, 1, 0));
+        assert_eq!(map.position(4), Position::new(1, 5, 4));
+        assert_eq!(map.position(5), Position::new(1, 6, 5));
+        assert_eq!(map.position(6), Position::new(2, 1, 6));
+        // "привет" - 12 байт, 6 символов
+        assert_eq!(map.position(18), Position::new(2, 7, 18));
+        assert_eq!(map.position(25), Position::new(4, 1, 25));
+        assert_eq!(map.position(100), Position::new(4, 2, 26));
+        assert_eq!(map.position(7), Position::new(2, 1, 6));
+    }
+
+    #[test]
+    fn lines_test() {
+        let map = SourceMap::new("first\r\nsecond\n\nlast");
+        assert_eq!(map.line(1), Some("first"));
+        assert_eq!(map.line(2), Some("second"));
+        assert_eq!(map.line(3), Some(""));
+        assert_eq!(map.line(4), Some("last"));
+        assert_eq!(map.line(0), None);
+        assert_eq!(map.line(5), None);
+    }
+
+    #[test]
+    fn span_test() {
+        let source = "x1 <= 42\ny";
+        let map = SourceMap::new(source);
+        let span = map.span(3, 5);
+        assert_eq!(span.to_string(), "1:4-1:6");
+        assert_eq!(span.text(source), "<=");
+        assert_eq!(span.len(), 2);
+        assert_eq!(span.merge(&map.span(9, 10)).text(source), "<= 42\ny");
+        assert!(Span::point(map.position(9)).is_empty());
+
+        let mut position = Position::default();
+        for c in "x1 <= 42\n".chars() {
+            position.advance(c);
+        }
+        assert_eq!(position, map.position(9));
+    }
+}